@@ -25,7 +25,7 @@ use crate::error::*;
 use crate::merkle::*;
 use crate::output::*;
 use failure::Error;
-use stegos_crypto::bulletproofs::validate_range_proof;
+use stegos_crypto::bulletproofs::{fee_a, validate_range_proof};
 use stegos_crypto::curve1174::cpt::Pt;
 use stegos_crypto::curve1174::ecpt::ECp;
 use stegos_crypto::curve1174::fields::Fr;
@@ -33,6 +33,21 @@ use stegos_crypto::curve1174::G;
 use stegos_crypto::hash::{Hash, Hashable, Hasher};
 use stegos_crypto::pbc::secure::PublicKey as SecurePublicKey;
 
+/// The maximal number of inputs of a monetary block.
+pub const MAX_BLOCK_INPUTS: usize = 1024;
+
+/// The maximal number of outputs of a monetary block.
+pub const MAX_BLOCK_OUTPUTS: usize = 1024;
+
+/// The maximal size of a serialized block in bytes.
+pub const MAX_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+
+/// The number of previous blocks used to calculate the median time past.
+pub const MEDIAN_TIME_PAST_BLOCKS: usize = 11;
+
+/// The maximal difference in seconds between the timestamp of a block and the local time.
+pub const MAX_FUTURE_DRIFT: u64 = 60;
+
 /// General Block Header.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BaseBlockHeader {
@@ -114,6 +129,13 @@ pub struct MonetaryBlockHeader {
 
     /// Merklish root of all range proofs for output.
     pub outputs_range_hash: Hash,
+
+    /// The root hash of the set of unspent outputs after this block, see UtxoTree.
+    pub utxo_root: Hash,
+
+    /// The amount of money created (positive) or destroyed (negative) by this block.
+    /// Only the genesis block can mint the initial supply, see Blockchain::validate_block().
+    pub monetary_adjustment: i64,
 }
 
 impl Hashable for MonetaryBlockHeader {
@@ -122,6 +144,8 @@ impl Hashable for MonetaryBlockHeader {
         self.gamma.hash(state);
         self.inputs_range_hash.hash(state);
         self.outputs_range_hash.hash(state);
+        self.utxo_root.hash(state);
+        self.monetary_adjustment.hash(state);
     }
 }
 
@@ -209,31 +233,20 @@ impl MonetaryBlock {
         gamma: Fr,
         inputs: &[Hash],
         outputs: &[Output],
+        utxo_root: Hash,
+        monetary_adjustment: i64,
     ) -> MonetaryBlock {
         // Create inputs array
-        let mut hasher = Hasher::new();
-        let inputs_count: u64 = inputs.len() as u64;
-        inputs_count.hash(&mut hasher);
-        for input in inputs {
-            input.hash(&mut hasher);
-        }
-        let inputs_range_hash = hasher.result();
+        let inputs_range_hash = range_hash(inputs.iter());
         let inputs = inputs.iter().map(|o| o.clone()).collect::<Vec<Hash>>();
 
         // Create outputs tree
-        let mut hasher = Hasher::new();
-        let outputs_count: u64 = outputs.len() as u64;
-        outputs_count.hash(&mut hasher);
+        let outputs_range_hash = range_hash(outputs.iter());
+        let mut builder = MerkleBuilder::<Box<Output>>::new();
         for output in outputs {
-            output.hash(&mut hasher);
+            builder.push(Box::new(output.clone()));
         }
-        let outputs_range_hash = hasher.result();
-        let outputs = outputs
-            .iter()
-            .map(|o| Box::<Output>::new(o.clone()))
-            .collect::<Vec<Box<Output>>>();
-
-        let outputs = Merkle::from_array(&outputs);
+        let outputs = builder.build();
 
         // Create header
         let header = MonetaryBlockHeader {
@@ -241,6 +254,8 @@ impl MonetaryBlock {
             gamma,
             inputs_range_hash,
             outputs_range_hash,
+            utxo_root,
+            monetary_adjustment,
         };
 
         // Create the block
@@ -250,6 +265,56 @@ impl MonetaryBlock {
         block
     }
 
+    /// Check the number of inputs and outputs, see MAX_BLOCK_INPUTS and MAX_BLOCK_OUTPUTS.
+    pub fn validate_limits(&self) -> Result<(), BlockchainError> {
+        let inputs_count = self.body.inputs.len();
+        if inputs_count > MAX_BLOCK_INPUTS {
+            return Err(BlockchainError::TooManyInputs(
+                Hash::digest(self),
+                inputs_count,
+                MAX_BLOCK_INPUTS,
+            ));
+        }
+
+        let outputs_count = self.body.outputs.leafs().len();
+        if outputs_count > MAX_BLOCK_OUTPUTS {
+            return Err(BlockchainError::TooManyOutputs(
+                Hash::digest(self),
+                outputs_count,
+                MAX_BLOCK_OUTPUTS,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Check that inputs_range_hash and outputs_range_hash of the header match the body.
+    ///
+    /// Outputs of the block must not be pruned.
+    ///
+    pub fn validate_range_hashes(&self) -> Result<(), BlockchainError> {
+        let inputs_range_hash = range_hash(self.body.inputs.iter());
+        if inputs_range_hash != self.header.inputs_range_hash {
+            return Err(BlockchainError::InvalidInputsRangeHash(
+                Hash::digest(self),
+                self.header.inputs_range_hash,
+                inputs_range_hash,
+            ));
+        }
+
+        let outputs = self.body.outputs.leafs();
+        let outputs_range_hash = range_hash(outputs.iter().map(|(o, _path)| &***o));
+        if outputs_range_hash != self.header.outputs_range_hash {
+            return Err(BlockchainError::InvalidOutputsRangeHash(
+                Hash::digest(self),
+                self.header.outputs_range_hash,
+                outputs_range_hash,
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Validate the monetary balance of block.
     ///
     /// # Arguments
@@ -260,7 +325,7 @@ impl MonetaryBlock {
         //
         // Calculate the pedersen commitment difference in order to check the monetary balance:
         //
-        //     pedersen_commitment_diff = \sum C_i - \sum C_o
+        //     pedersen_commitment_diff = \sum C_i - \sum C_o + monetary_adjustment * A
         //
 
         let mut pedersen_commitment_diff = ECp::inf();
@@ -292,6 +357,9 @@ impl MonetaryBlock {
             pedersen_commitment_diff -= pedersen_commitment;
         }
 
+        // +monetary_adjustment * A
+        pedersen_commitment_diff += fee_a(self.header.monetary_adjustment);
+
         // Check the monetary balance
         if pedersen_commitment_diff != self.header.gamma * (*G) {
             return Err(BlockchainError::InvalidBlockBalance.into());
@@ -307,8 +375,23 @@ impl Hashable for MonetaryBlock {
     }
 }
 
+/// Calculate a Merklish root of inputs or outputs, see MonetaryBlockHeader.
+fn range_hash<'a, T, I>(values: I) -> Hash
+where
+    T: Hashable + 'a,
+    I: ExactSizeIterator<Item = &'a T>,
+{
+    let mut hasher = Hasher::new();
+    let count: u64 = values.len() as u64;
+    count.hash(&mut hasher);
+    for value in values {
+        value.hash(&mut hasher);
+    }
+    hasher.result()
+}
+
 /// Types of blocks supported by this blockchain.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Block {
     KeyBlock(KeyBlock),
     MonetaryBlock(MonetaryBlock),
@@ -321,6 +404,16 @@ impl Block {
             Block::MonetaryBlock(MonetaryBlock { header, body: _ }) => &header.base,
         }
     }
+
+    /// Returns a copy of the header of this block.
+    pub fn header(&self) -> BlockHeader {
+        match self {
+            Block::KeyBlock(KeyBlock { header }) => BlockHeader::KeyBlockHeader(header.clone()),
+            Block::MonetaryBlock(MonetaryBlock { header, body: _ }) => {
+                BlockHeader::MonetaryBlockHeader(header.clone())
+            }
+        }
+    }
 }
 
 impl Hashable for Block {
@@ -332,6 +425,33 @@ impl Hashable for Block {
     }
 }
 
+/// Headers of blocks supported by this blockchain.
+///
+/// The hash of a header is equal to the hash of its block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockHeader {
+    KeyBlockHeader(KeyBlockHeader),
+    MonetaryBlockHeader(MonetaryBlockHeader),
+}
+
+impl BlockHeader {
+    pub fn base_header(&self) -> &BaseBlockHeader {
+        match self {
+            BlockHeader::KeyBlockHeader(header) => &header.base,
+            BlockHeader::MonetaryBlockHeader(header) => &header.base,
+        }
+    }
+}
+
+impl Hashable for BlockHeader {
+    fn hash(&self, state: &mut Hasher) {
+        match self {
+            BlockHeader::KeyBlockHeader(header) => header.hash(state),
+            BlockHeader::MonetaryBlockHeader(header) => header.hash(state),
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -362,7 +482,7 @@ pub mod tests {
                 Output::new_monetary(timestamp, &skey1, &pkey2, amount).unwrap();
             let outputs1 = [output1];
             let gamma = gamma0 - gamma1;
-            let block = MonetaryBlock::new(base, gamma, &inputs1, &outputs1);
+            let block = MonetaryBlock::new(base, gamma, &inputs1, &outputs1, Hash::zero(), 0);
             block.validate(&[output0]).expect("block is valid");
         }
 
@@ -378,7 +498,7 @@ pub mod tests {
                 Output::new_monetary(timestamp, &skey1, &pkey2, amount - 1).unwrap();
             let outputs1 = [output1];
             let gamma = gamma0 - gamma1;
-            let block = MonetaryBlock::new(base, gamma, &inputs1, &outputs1);
+            let block = MonetaryBlock::new(base, gamma, &inputs1, &outputs1, Hash::zero(), 0);
             match block.validate(&[output0]) {
                 Err(e) => match e.downcast::<BlockchainError>().unwrap() {
                     BlockchainError::InvalidBlockBalance => {}
@@ -387,5 +507,53 @@ pub mod tests {
                 _ => panic!(),
             }
         }
+
+        //
+        // Block with monetary adjustment
+        //
+        {
+            let (output0, gamma0) =
+                Output::new_monetary(timestamp, &skey0, &pkey1, amount).unwrap();
+            let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
+            let inputs1 = [Hash::digest(&output0)];
+            let (output1, gamma1) =
+                Output::new_monetary(timestamp, &skey1, &pkey2, amount - 1).unwrap();
+            let outputs1 = [output1];
+            let gamma = gamma0 - gamma1;
+            let block = MonetaryBlock::new(base, gamma, &inputs1, &outputs1, Hash::zero(), -1);
+            block.validate(&[output0]).expect("block is valid");
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let (skey, pkey, _sig) = make_random_keys();
+        let timestamp = Utc::now().timestamp() as u64;
+        let previous = Hash::digest(&"test".to_string());
+        let base = BaseBlockHeader::new(1, previous, 1, timestamp);
+        let (output, gamma) = Output::new_monetary(timestamp, &skey, &pkey, 1).unwrap();
+
+        let inputs = vec![Hash::digest(&output); MAX_BLOCK_INPUTS];
+        let outputs = vec![output.clone(); MAX_BLOCK_OUTPUTS];
+        let block = MonetaryBlock::new(base.clone(), gamma, &inputs, &outputs, Hash::zero(), 0);
+        block.validate_limits().expect("block is valid");
+
+        let inputs = vec![Hash::digest(&output); MAX_BLOCK_INPUTS + 1];
+        let block = MonetaryBlock::new(base.clone(), gamma, &inputs, &[], Hash::zero(), 0);
+        match block.validate_limits() {
+            Err(BlockchainError::TooManyInputs(_, count, max)) => {
+                assert_eq!((count, max), (MAX_BLOCK_INPUTS + 1, MAX_BLOCK_INPUTS))
+            }
+            _ => panic!(),
+        }
+
+        let outputs = vec![output.clone(); MAX_BLOCK_OUTPUTS + 1];
+        let block = MonetaryBlock::new(base, gamma, &[], &outputs, Hash::zero(), 0);
+        match block.validate_limits() {
+            Err(BlockchainError::TooManyOutputs(_, count, max)) => {
+                assert_eq!((count, max), (MAX_BLOCK_OUTPUTS + 1, MAX_BLOCK_OUTPUTS))
+            }
+            _ => panic!(),
+        }
     }
 }
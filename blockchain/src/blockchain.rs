@@ -25,21 +25,90 @@ use crate::block::*;
 use crate::error::*;
 use crate::merkle::*;
 use crate::output::*;
+use crate::store::*;
+use crate::utxo::*;
+use chrono::Utc;
+use failure::Error;
 use log::*;
+use std::cmp;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::path::Path;
 use std::vec::Vec;
+use stegos_crypto::bulletproofs::fee_a;
+use stegos_crypto::curve1174::cpt::Pt;
+use stegos_crypto::curve1174::ecpt::ECp;
+use stegos_crypto::curve1174::fields::Fr;
+use stegos_crypto::curve1174::G;
 use stegos_crypto::hash::*;
 
-type BlockId = usize;
+pub(crate) type BlockId = usize;
+
+/// The maximal number of blocks of the main chain which can be reverted by a reorganization.
+pub const MAX_REORG_DEPTH: usize = 32;
+
+/// The maximal number of blocks kept in side branches.
+pub const MAX_SIDE_BLOCKS: usize = 128;
 
 /// A help to find UTXO in this blockchain.
-struct OutputKey {
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OutputKey {
     /// The short block identifier.
     pub block_id: BlockId,
     /// Merkle Tree path inside block.
     pub path: MerklePath,
 }
 
+/// Undo information for a spent output.
+#[derive(Clone, Debug)]
+pub(crate) struct SpentOutput {
+    /// The hash of output.
+    pub hash: Hash,
+    /// The original position of output.
+    pub key: OutputKey,
+    /// Hashes of siblings in Merkle Tree, see Merkle::siblings().
+    pub siblings: Vec<Option<Hash>>,
+    /// The output itself.
+    pub output: Output,
+}
+
+/// Changes of the main chain made by push_block().
+#[derive(Clone, Debug)]
+pub enum ChainEvent {
+    /// A block has been added to the main chain.
+    Registered {
+        block: Block,
+        pruned: Vec<Output>,
+        expired: Vec<Output>,
+    },
+    /// A block has been removed from the main chain.
+    Reverted { block: Block, restored: Vec<Output> },
+}
+
+/// A snapshot of the main chain for fast synchronization, see Blockchain::snapshot().
+///
+/// Monetary blocks of the snapshot keep only unspent outputs, so the snapshot is
+/// the set of unspent outputs plus the history of block headers.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// Blocks of the main chain, starting from the genesis.
+    pub blocks: Vec<Block>,
+    /// Sums of commitments of outputs expired by monetary blocks, see Blockchain::audit_supply().
+    pub expired: BTreeMap<BlockId, Pt>,
+}
+
+impl Snapshot {
+    /// Returns the epoch of the last block.
+    pub fn epoch(&self) -> u64 {
+        match self.blocks.last() {
+            Some(block) => block.base_header().epoch,
+            None => 0,
+        }
+    }
+}
+
 /// The Blockchain.
 pub struct Blockchain {
     /// Blockchain blocks stored in-memory.
@@ -49,6 +118,20 @@ pub struct Blockchain {
     block_by_hash: HashMap<Hash, BlockId>,
     /// Unspent outputs by hash.
     output_by_hash: HashMap<Hash, OutputKey>,
+    /// Outputs spent by monetary blocks, needed to revert these blocks.
+    undo: HashMap<BlockId, Vec<SpentOutput>>,
+    /// Sums of commitments of outputs expired by monetary blocks, needed to audit the supply.
+    expired: BTreeMap<BlockId, Pt>,
+    /// Unspent data outputs by the epoch when they expire.
+    expiry: BTreeMap<u64, HashSet<Hash>>,
+    /// Commitment to the set of unspent outputs.
+    utxo_tree: UtxoTree,
+    /// Blocks of side branches by hash.
+    side_blocks: HashMap<Hash, Block>,
+    /// Hashes of side blocks in the order of arrival.
+    side_queue: VecDeque<Hash>,
+    /// Persistent storage, None for in-memory blockchain.
+    store: Option<BlockStore>,
 }
 
 impl Blockchain {
@@ -60,20 +143,196 @@ impl Blockchain {
         let blocks = Vec::new();
         let block_by_hash = HashMap::<Hash, BlockId>::new();
         let output_by_hash = HashMap::<Hash, OutputKey>::new();
+        let undo = HashMap::<BlockId, Vec<SpentOutput>>::new();
+        let expired = BTreeMap::<BlockId, Pt>::new();
+        let expiry = BTreeMap::<u64, HashSet<Hash>>::new();
+        let utxo_tree = UtxoTree::new();
+        let side_blocks = HashMap::<Hash, Block>::new();
+        let side_queue = VecDeque::<Hash>::new();
+        let store = None;
         let blockchain = Blockchain {
             blocks,
             block_by_hash,
             output_by_hash,
+            undo,
+            expired,
+            expiry,
+            utxo_tree,
+            side_blocks,
+            side_queue,
+            store,
         };
         blockchain
     }
 
+    /// Open the persistent blockchain and resume from the last stored block.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the database file, created if doesn't exist.
+    /// * `codec` - Serialization of blocks.
+    ///
+    pub fn open<P: AsRef<Path>>(path: P, codec: Box<dyn BlockCodec>) -> Result<Blockchain, Error> {
+        let store = BlockStore::open(path, codec)?;
+
+        let mut blocks = Vec::<Block>::new();
+        while let Some(block) = store.block(blocks.len())? {
+            blocks.push(block);
+        }
+        let block_by_hash = store.block_by_hash()?;
+        let output_by_hash = store.output_by_hash()?;
+        let expired = store.expired()?;
+
+        // Check indexes.
+        if block_by_hash.len() != blocks.len() {
+            let msg = format!(
+                "blocks={}, block_hashes={}",
+                blocks.len(),
+                block_by_hash.len()
+            );
+            return Err(BlockchainError::CorruptedStorage(msg).into());
+        }
+        for (block_id, block) in blocks.iter().enumerate() {
+            let hash = Hash::digest(block);
+            if block_by_hash.get(&hash) != Some(&block_id) {
+                let msg = format!("missing index for block {}", hash);
+                return Err(BlockchainError::CorruptedStorage(msg).into());
+            }
+        }
+
+        // Load undo data, taking spent outputs from blocks before they are pruned.
+        let mut undo = HashMap::<BlockId, Vec<SpentOutput>>::new();
+        for (block_id, block) in blocks.iter().enumerate() {
+            if let Block::KeyBlock(_) = block {
+                continue;
+            }
+            let records = match store.undo(block_id)? {
+                Some(records) => records,
+                None => continue,
+            };
+            let mut spent = Vec::<SpentOutput>::with_capacity(records.len());
+            for (hash, key, siblings) in records {
+                let output = match blocks.get(key.block_id) {
+                    Some(Block::MonetaryBlock(MonetaryBlock { header: _, body })) => {
+                        body.outputs.lookup(&key.path)
+                    }
+                    _ => None,
+                };
+                let output = match output {
+                    Some(output) if Hash::digest(output) == hash => (**output).clone(),
+                    _ => {
+                        let msg = format!("invalid undo data for output {}", hash);
+                        return Err(BlockchainError::CorruptedStorage(msg).into());
+                    }
+                };
+                spent.push(SpentOutput {
+                    hash,
+                    key,
+                    siblings,
+                    output,
+                });
+            }
+            undo.insert(block_id, spent);
+        }
+
+        // Prune spent outputs.
+        for (block_id, block) in blocks.iter_mut().enumerate() {
+            if let Block::MonetaryBlock(MonetaryBlock { header: _, body }) = block {
+                let spent: Vec<MerklePath> = body
+                    .outputs
+                    .leafs()
+                    .iter()
+                    .filter(|(o, path)| match output_by_hash.get(&Hash::digest(*o)) {
+                        Some(key) => key.block_id != block_id || key.path != *path,
+                        None => true,
+                    })
+                    .map(|(_o, path)| *path)
+                    .collect();
+                for path in spent {
+                    body.outputs.prune(&path);
+                }
+            }
+        }
+
+        let utxo_tree = UtxoTree::from_hashes(output_by_hash.keys());
+        let mut blockchain = Blockchain {
+            blocks,
+            block_by_hash,
+            output_by_hash,
+            undo,
+            expired,
+            expiry: BTreeMap::new(),
+            utxo_tree,
+            side_blocks: HashMap::new(),
+            side_queue: VecDeque::new(),
+            store: Some(store),
+        };
+
+        // Check that all unspent outputs exist.
+        let mut expiry = Vec::<(u64, Hash)>::new();
+        for (hash, OutputKey { block_id, path: _ }) in &blockchain.output_by_hash {
+            let output = if *block_id < blockchain.blocks.len() {
+                blockchain.output_by_hash(hash)
+            } else {
+                None
+            };
+            match output {
+                Some(output) => {
+                    let epoch = blockchain.blocks[*block_id].base_header().epoch;
+                    if let Some(e) = output_expiry(epoch, output) {
+                        expiry.push((e, *hash));
+                    }
+                }
+                None => {
+                    let msg = format!("missing UTXO {}", hash);
+                    return Err(BlockchainError::CorruptedStorage(msg).into());
+                }
+            }
+        }
+        for (e, hash) in expiry {
+            blockchain.insert_expiry(e, hash);
+        }
+
+        // Check the set of unspent outputs against the last monetary block.
+        let last_monetary = blockchain
+            .blocks
+            .iter()
+            .rev()
+            .find_map(|block| match block {
+                Block::MonetaryBlock(monetary_block) => Some(monetary_block),
+                Block::KeyBlock(_) => None,
+            });
+        if let Some(MonetaryBlock { header, body: _ }) = last_monetary {
+            let utxo_root = blockchain.utxo_tree.roothash();
+            if header.utxo_root != utxo_root {
+                let msg = format!(
+                    "UTXO root mismatch: expected={}, got={}",
+                    header.utxo_root, utxo_root
+                );
+                return Err(BlockchainError::CorruptedStorage(msg).into());
+            }
+        }
+
+        info!(
+            "Loaded blockchain: blocks={}, unspent={}",
+            blockchain.blocks.len(),
+            blockchain.output_by_hash.len()
+        );
+
+        Ok(blockchain)
+    }
+
     /// Returns an iterator over UTXO hashes.
     pub fn unspent(&self) -> Vec<Hash> {
         // TODO: return iterator instead.
         self.output_by_hash.keys().cloned().collect()
     }
 
+    /// Returns true if the UTXO exists.
+    pub fn contains_output(&self, output_hash: &Hash) -> bool {
+        self.output_by_hash.contains_key(output_hash)
+    }
+
     /// Find UTXO by its hash.
     pub fn output_by_hash(&self, output_hash: &Hash) -> Option<&Output> {
         if let Some(OutputKey { block_id, path }) = self.output_by_hash.get(output_hash) {
@@ -109,6 +368,23 @@ impl Blockchain {
         Ok(outputs)
     }
 
+    /// Returns the root hash of the set of unspent outputs, see UtxoTree.
+    pub fn utxo_root(&self) -> Hash {
+        self.utxo_tree.roothash()
+    }
+
+    /// Create a proof that an output is unspent or not, see UtxoProof::verify().
+    pub fn utxo_proof(&self, output_hash: &Hash) -> UtxoProof {
+        self.utxo_tree.proof(output_hash)
+    }
+
+    /// Calculate the UTXO root for the next monetary block of the epoch with these inputs
+    /// and outputs.
+    pub fn next_utxo_root(&self, epoch: u64, inputs: &[Hash], outputs: &[Output]) -> Hash {
+        let outputs: Vec<Hash> = outputs.iter().map(|o| Hash::digest(o)).collect();
+        self.utxo_tree_after(epoch, inputs, &outputs).roothash()
+    }
+
     /// Find block by its hash
     pub fn block_by_hash(&self, block_hash: &Hash) -> Option<&Block> {
         if let Some(block_id) = self.block_by_hash.get(block_hash) {
@@ -117,6 +393,52 @@ impl Blockchain {
         return None;
     }
 
+    /// Find block by its hash and load it from the storage with all outputs,
+    /// as it was registered.
+    ///
+    /// Blocks restored from a snapshot and blocks of a blockchain without
+    /// the persistent storage are returned with pruned outputs.
+    pub fn full_block_by_hash(&self, block_hash: &Hash) -> Result<Option<Block>, Error> {
+        let block_id = match self.block_by_hash.get(block_hash) {
+            Some(block_id) => *block_id,
+            None => return Ok(None),
+        };
+        match self.store {
+            Some(ref store) => match store.block(block_id)? {
+                Some(block) => Ok(Some(block)),
+                None => {
+                    let msg = format!("missing block {}", block_hash);
+                    Err(BlockchainError::CorruptedStorage(msg).into())
+                }
+            },
+            None => Ok(Some(self.blocks[block_id].clone())),
+        }
+    }
+
+    /// Returns true if the block is known, either in the main chain or in a side branch.
+    pub fn contains_block(&self, block_hash: &Hash) -> bool {
+        self.block_by_hash.contains_key(block_hash) || self.side_blocks.contains_key(block_hash)
+    }
+
+    /// Returns headers of the main chain blocks with epoch in [from_epoch, to_epoch],
+    /// at most `limit` headers starting from the earliest one.
+    pub fn headers_by_epoch(
+        &self,
+        from_epoch: u64,
+        to_epoch: u64,
+        limit: usize,
+    ) -> Vec<BlockHeader> {
+        self.blocks
+            .iter()
+            .filter(|block| {
+                let epoch = block.base_header().epoch;
+                epoch >= from_epoch && epoch <= to_epoch
+            })
+            .take(limit)
+            .map(|block| block.header())
+            .collect()
+    }
+
     /// Return all blocks.
     pub fn blocks(&self) -> &[Block] {
         self.blocks.as_slice()
@@ -128,9 +450,118 @@ impl Blockchain {
         self.blocks.last().unwrap()
     }
 
+    /// Returns the median timestamp of the last blocks of the main chain.
+    ///
+    /// Timestamps of new blocks must not be less than this value.
+    ///
+    pub fn median_time_past(&self) -> u64 {
+        median_time_past(self.blocks.iter().rev().map(|block| block.base_header()))
+    }
+
+    /// Validate a block as the next block of the main chain, without registering it.
+    ///
+    /// Checks the linkage with the last block, epoch, timestamp, the serialized size,
+    /// witnesses of key blocks, limits and consistency of headers and bodies of monetary blocks,
+    /// inputs, outputs, range proofs, the monetary balance and the monetary adjustment.
+    ///
+    pub fn validate_block(&self, block: &Block) -> Result<(), Error> {
+        let block_hash = Hash::digest(block);
+        if self.block_by_hash.contains_key(&block_hash) {
+            return Err(BlockchainError::BlockHashCollision(block_hash).into());
+        }
+
+        // Check linkage with the last block.
+        let previous = self
+            .blocks
+            .last()
+            .map(|last| (Hash::digest(last), last.base_header()));
+        validate_linkage(previous, &block_hash, block.base_header())?;
+        let now = Utc::now().timestamp() as u64;
+        validate_timestamp(
+            &block_hash,
+            block.base_header(),
+            self.median_time_past(),
+            now,
+        )?;
+
+        // TODO: check CoSi multisignature when it is added to BaseBlockHeader.
+
+        // The serialized size is defined by the codec of the persistent storage.
+        if let Some(ref store) = self.store {
+            let size = store.encoded_size(block);
+            if size > MAX_BLOCK_SIZE {
+                return Err(BlockchainError::BlockTooLarge(size, MAX_BLOCK_SIZE).into());
+            }
+        }
+
+        match block {
+            Block::KeyBlock(key_block) => {
+                validate_key_block(&block_hash, &key_block.header, self.last_key_block())?
+            }
+            Block::MonetaryBlock(monetary_block) => {
+                // The initial supply is minted by the monetary block of the genesis,
+                // which follows the first key block.
+                let adjustment = monetary_block.header.monetary_adjustment;
+                if adjustment != 0 && self.blocks.len() != 1 {
+                    return Err(
+                        BlockchainError::InvalidMonetaryAdjustment(block_hash, adjustment).into(),
+                    );
+                }
+
+                monetary_block.validate_limits()?;
+                monetary_block.validate_range_hashes()?;
+                let outputs = &monetary_block.body.outputs;
+                if outputs.validate().is_err() {
+                    return Err(BlockchainError::InvalidOutputsTree(block_hash).into());
+                }
+
+                // Check inputs.
+                let inputs = &monetary_block.body.inputs;
+                let mut inputs_set = HashSet::<Hash>::with_capacity(inputs.len());
+                for input_hash in inputs {
+                    if !inputs_set.insert(*input_hash) {
+                        return Err(BlockchainError::DuplicateInput(block_hash, *input_hash).into());
+                    }
+                }
+                let inputs = self.outputs_by_hashes(inputs)?;
+
+                // Check outputs.
+                let mut outputs_set = HashSet::<Hash>::new();
+                for (output, _path) in outputs.leafs() {
+                    let output_hash = Hash::digest(output);
+                    if self.output_by_hash.contains_key(&output_hash)
+                        || !outputs_set.insert(output_hash)
+                    {
+                        return Err(BlockchainError::OutputHashCollision(output_hash).into());
+                    }
+                }
+
+                // Check the commitment to the set of unspent outputs.
+                let outputs: Vec<Hash> = outputs_set.into_iter().collect();
+                let epoch = monetary_block.header.base.epoch;
+                let input_hashes = &monetary_block.body.inputs;
+                let utxo_root = self.utxo_tree_after(epoch, input_hashes, &outputs);
+                let utxo_root = utxo_root.roothash();
+                if monetary_block.header.utxo_root != utxo_root {
+                    return Err(BlockchainError::InvalidUtxoRoot(
+                        block_hash,
+                        utxo_root,
+                        monetary_block.header.utxo_root,
+                    )
+                    .into());
+                }
+
+                // Check range proofs and the monetary balance.
+                monetary_block.validate(&inputs)?;
+            }
+        }
+
+        Ok(())
+    }
+
     //----------------------------------------------------------------------------------------------
 
-    pub fn register_key_block(&mut self, block: KeyBlock) -> Result<(), BlockchainError> {
+    pub fn register_key_block(&mut self, block: KeyBlock) -> Result<(), Error> {
         let block_id = self.blocks.len();
 
         // Check previous hash.
@@ -140,14 +571,15 @@ impl Blockchain {
                 return Err(BlockchainError::PreviousHashMismatch(
                     previous_hash,
                     block.header.base.previous,
-                ));
+                )
+                .into());
             }
         }
 
         // Check new hash.
         let this_hash = Hash::digest(&block);
         if let Some(_) = self.block_by_hash.get(&this_hash) {
-            return Err(BlockchainError::BlockHashCollision(this_hash));
+            return Err(BlockchainError::BlockHashCollision(this_hash).into());
         }
 
         // -----------------------------------------------------------------------------------------
@@ -156,20 +588,31 @@ impl Blockchain {
 
         info!("Register Key Block: hash={}", this_hash);
 
+        let block = Block::KeyBlock(block);
+        if let Some(ref mut store) = self.store {
+            store.commit_block(block_id, &block, &[], &[], None)?;
+        }
+
         if let Some(_) = self.block_by_hash.insert(this_hash.clone(), block_id) {
             panic!("Block hash collision");
         }
 
-        self.blocks.push(Block::KeyBlock(block));
+        self.blocks.push(block);
 
         Ok(())
     }
 
+    /// Register a monetary block.
+    ///
+    /// Data outputs which TTL has elapsed at the epoch of this block are pruned too.
+    /// Returns outputs spent by the block and expired data outputs.
+    ///
     pub fn register_monetary_block(
         &mut self,
         block: MonetaryBlock,
-    ) -> Result<(Vec<Output>), BlockchainError> {
+    ) -> Result<(Vec<Output>, Vec<Output>), Error> {
         let block_id = self.blocks.len();
+        let epoch = block.header.base.epoch;
 
         // Check previous hash.
         if let Some(previous_block) = self.blocks.last() {
@@ -178,36 +621,30 @@ impl Blockchain {
                 return Err(BlockchainError::PreviousHashMismatch(
                     previous_hash,
                     block.header.base.previous,
-                ));
+                )
+                .into());
             }
         }
 
         // Check new hash.
         let this_hash = Hash::digest(&block);
         if let Some(_) = self.block_by_hash.get(&this_hash) {
-            return Err(BlockchainError::BlockHashCollision(this_hash));
+            return Err(BlockchainError::BlockHashCollision(this_hash).into());
         }
 
         // Check all inputs.
+        let mut inputs_set = HashSet::<Hash>::with_capacity(block.body.inputs.len());
+        let mut spent = Vec::<SpentOutput>::with_capacity(block.body.inputs.len());
         for output_hash in &block.body.inputs {
-            if let Some(OutputKey { block_id, path }) = self.output_by_hash.get(output_hash) {
-                assert!(*block_id < self.blocks.len());
-                let block = &self.blocks[*block_id];
-                if let Block::MonetaryBlock(MonetaryBlock { header: _, body }) = block {
-                    if let Some(output) = body.outputs.lookup(&path) {
-                        // Check that hash is the same.
-                        assert_eq!(Hash::digest(output), *output_hash);
-                    } else {
-                        // Internal database inconsistency - missing UTXO in block.
-                        unreachable!();
-                    }
-                } else {
-                    // Internal database inconsistency - invalid block type.
-                    unreachable!();
+            if !inputs_set.insert(*output_hash) {
+                return Err(BlockchainError::DuplicateInput(this_hash, *output_hash).into());
+            }
+            match self.spent_output(output_hash) {
+                Some(spent_output) => spent.push(spent_output),
+                None => {
+                    // Cannot find UTXO referred by block.
+                    return Err(BlockchainError::MissingUTXO(*output_hash).into());
                 }
-            } else {
-                // Cannot find UTXO referred by block.
-                return Err(BlockchainError::MissingUTXO(*output_hash));
             }
         }
 
@@ -219,39 +656,91 @@ impl Blockchain {
             .iter()
             .map(|(o, path)| (Hash::digest(*o), *path))
             .collect::<Vec<(Hash, MerklePath)>>();
+        let mut outputs_set = HashSet::<Hash>::with_capacity(outputs_pathes.len());
         for (hash, _path) in &outputs_pathes {
-            if let Some(_) = self.output_by_hash.get(hash) {
-                return Err(BlockchainError::OutputHashCollision(*hash));
+            if self.output_by_hash.contains_key(hash) || !outputs_set.insert(*hash) {
+                return Err(BlockchainError::OutputHashCollision(*hash).into());
+            }
+        }
+        let created_expiry: Vec<(u64, Hash)> = block
+            .body
+            .outputs
+            .leafs()
+            .iter()
+            .filter_map(|(o, _path)| output_expiry(epoch, o).map(|e| (e, Hash::digest(*o))))
+            .collect();
+
+        // Find data outputs expired at this epoch, except ones spent by this block.
+        let inputs_count = spent.len();
+        for (_expiry, hashes) in self.expiry.range(..=epoch) {
+            for hash in hashes {
+                if !inputs_set.contains(hash) {
+                    spent.push(self.spent_output(hash).expect("expiring output is unspent"));
+                }
             }
         }
 
+        // Commitments of expired outputs are kept for audit_supply().
+        let expired_sum = if spent.len() > inputs_count {
+            let expired = spent[inputs_count..].iter().map(|s| &s.output);
+            Some(commitments_sum(expired)?)
+        } else {
+            None
+        };
+
+        // Check the commitment to the set of unspent outputs.
+        let created: Vec<Hash> = outputs_pathes.iter().map(|(hash, _path)| *hash).collect();
+        let utxo_tree = self.utxo_tree_after(epoch, &block.body.inputs, &created);
+        if block.header.utxo_root != utxo_tree.roothash() {
+            return Err(BlockchainError::InvalidUtxoRoot(
+                this_hash,
+                utxo_tree.roothash(),
+                block.header.utxo_root,
+            )
+            .into());
+        }
+
         // -----------------------------------------------------------------------------------------
         // Alright, starting transaction.
+        //
+        // All checks are passed above, so the in-memory part below never fails.
+        // The persistent part is written first as a single atomic batch, which is either
+        // completely applied or completely discarded on the next start.
         // -----------------------------------------------------------------------------------------
         info!("Register Monetary Block: hash={}", this_hash);
 
-        let mut pruned: Vec<Output> = Vec::with_capacity(block.body.inputs.len());
+        let block = Block::MonetaryBlock(block);
+        if let Some(ref mut store) = self.store {
+            store.commit_block(block_id, &block, &spent, &outputs_pathes, expired_sum)?;
+        }
 
-        // Remove spent outputs.
-        for output_hash in &block.body.inputs {
-            info!("Prune UXTO: hash={}", output_hash);
-            // Remove from the set of unspent outputs.
-            if let Some(OutputKey { block_id, path }) = self.output_by_hash.remove(output_hash) {
-                let block = &mut self.blocks[block_id];
-                if let Block::MonetaryBlock(MonetaryBlock { header: _, body }) = block {
-                    // Remove from the block.
-                    if let Some(output) = body.outputs.prune(&path) {
-                        pruned.push(*output);
-                    } else {
-                        unreachable!();
-                    }
-                } else {
-                    unreachable!();
-                }
+        let mut pruned: Vec<Output> = Vec::with_capacity(spent.len());
+
+        // Remove spent and expired outputs.
+        for (i, SpentOutput { hash, key, .. }) in spent.iter().enumerate() {
+            if i < inputs_count {
+                info!("Prune UXTO: hash={}", hash);
             } else {
+                info!("Expire UXTO: hash={}", hash);
+            }
+            // Remove from the set of unspent outputs.
+            if let None = self.output_by_hash.remove(hash) {
                 unreachable!();
             }
+            // Remove from the block.
+            let (output_epoch, output) = match &mut self.blocks[key.block_id] {
+                Block::MonetaryBlock(MonetaryBlock { header, body }) => {
+                    let output = body.outputs.prune(&key.path).expect("checked above");
+                    (header.base.epoch, output)
+                }
+                Block::KeyBlock(_) => unreachable!(),
+            };
+            if let Some(expiry) = output_expiry(output_epoch, &output) {
+                self.remove_expiry(expiry, hash);
+            }
+            pruned.push(*output);
         }
+        let expired = pruned.split_off(inputs_count);
 
         // Register create unspent outputs.
         for (hash, path) in outputs_pathes {
@@ -263,80 +752,2212 @@ impl Blockchain {
                 unreachable!();
             }
         }
+        for (expiry, hash) in created_expiry {
+            self.insert_expiry(expiry, hash);
+        }
 
         // Register block
         if let Some(_) = self.block_by_hash.insert(this_hash.clone(), block_id) {
             unreachable!();
         }
+        self.undo.insert(block_id, spent);
+        if let Some(expired_sum) = expired_sum {
+            self.expired.insert(block_id, expired_sum);
+        }
+        self.utxo_tree = utxo_tree;
 
         // Must be the last line to make Rust happy.
-        self.blocks.push(Block::MonetaryBlock(block));
+        self.blocks.push(block);
 
-        Ok(pruned)
+        Ok((pruned, expired))
     }
-}
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
+    /// Add a block received from the network.
+    ///
+    /// The block either extends the main chain or is kept in a side branch.
+    /// The main chain is switched to a side branch when the branch becomes better
+    /// according to the fork-choice rule, see is_better_chain().
+    /// Side blocks are limited by validate_side_block() and by MAX_SIDE_BLOCKS.
+    ///
+    /// Returns changes of the main chain.
+    ///
+    pub fn push_block(&mut self, block: Block) -> Result<Vec<ChainEvent>, Error> {
+        let block_hash = Hash::digest(&block);
+        if self.contains_block(&block_hash) {
+            return Err(BlockchainError::BlockHashCollision(block_hash).into());
+        }
 
-    use chrono::prelude::Utc;
+        // Extends the main chain.
+        let previous = block.base_header().previous;
+        let last_hash = match self.blocks.last() {
+            Some(last) => Hash::digest(last),
+            None => previous,
+        };
+        if previous == last_hash {
+            let (pruned, expired) = self.apply_block(block.clone())?;
+            return Ok(vec![ChainEvent::Registered {
+                block,
+                pruned,
+                expired,
+            }]);
+        }
 
-    use crate::genesis::genesis;
-    use stegos_crypto::curve1174::cpt::make_random_keys;
-    use stegos_keychain::KeyChain;
+        // Find the fork point.
+        let mut branch = vec![block_hash];
+        let mut fork = previous;
+        while let Some(side_block) = self.side_blocks.get(&fork) {
+            branch.push(fork);
+            fork = side_block.base_header().previous;
+        }
+        branch.reverse();
+        let fork_id = match self.block_by_hash.get(&fork) {
+            Some(block_id) => *block_id,
+            None => {
+                return Err(BlockchainError::PreviousHashMismatch(last_hash, previous).into());
+            }
+        };
 
-    pub fn iterate(blockchain: &mut Blockchain) -> Result<(), BlockchainError> {
-        let version = 1;
-        let timestamp = Utc::now().timestamp() as u64;
-        let (epoch, previous) = {
-            let last = blockchain.last_block();
-            let base_header = last.base_header();
-            let epoch = base_header.epoch + 1;
-            let previous = Hash::digest(last);
-            (epoch, previous)
+        self.validate_side_block(&block_hash, &block, fork_id, &branch[..branch.len() - 1])?;
+
+        let epoch = block.base_header().epoch;
+        let height = fork_id + 1 + branch.len();
+        self.insert_side_block(block_hash, block);
+
+        let events = if self.is_better_chain(epoch, height) {
+            self.reorganize(fork_id, &branch)
+        } else {
+            info!(
+                "Added block to side branch: hash={}, fork={}, epoch={}, height={}",
+                block_hash, fork, epoch, height
+            );
+            Ok(Vec::new())
         };
 
-        let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
+        // Evict the oldest side blocks.
+        while self.side_blocks.len() > MAX_SIDE_BLOCKS {
+            let oldest = self.side_queue.front().cloned().expect("side blocks");
+            debug!("Evict side block: hash={}", oldest);
+            self.remove_side_block(&oldest);
+            self.remove_side_branch(&oldest);
+        }
 
-        let (skey, pkey, _sig) = make_random_keys();
+        events
+    }
 
-        let output_hash = blockchain.output_by_hash.keys().next().unwrap().clone();
-        let input = output_hash.clone();
-        let inputs = [input];
+    /// Remove the last block from the main chain.
+    ///
+    /// Outputs created by this block are removed and outputs spent by this block are restored
+    /// into their original positions in Merkle Trees, using undo data saved on registration.
+    /// Returns the block and restored outputs.
+    ///
+    pub fn unregister_last_block(&mut self) -> Result<(Block, Vec<Output>), Error> {
+        assert!(self.blocks.len() > 0);
+        let block_id = self.blocks.len() - 1;
+        let block_hash = Hash::digest(&self.blocks[block_id]);
 
-        let amount: i64 = 112;
-        let (output, gamma) =
-            Output::new_monetary(timestamp, &skey, &pkey, amount).expect("tests have valid keys");
-        let outputs = [output];
+        let (created, spent) = match self.blocks[block_id] {
+            Block::KeyBlock(_) => (Vec::new(), Vec::new()),
+            Block::MonetaryBlock(MonetaryBlock {
+                ref header,
+                ref body,
+            }) => {
+                let spent = match self.undo.get(&block_id) {
+                    Some(spent) => spent.clone(),
+                    None => return Err(BlockchainError::MissingUndoData(block_hash).into()),
+                };
+                // Sic: outputs of the last block can't be spent.
+                let created: Vec<(Hash, Option<u64>)> = body
+                    .outputs
+                    .leafs()
+                    .iter()
+                    .map(|(o, _path)| (Hash::digest(*o), output_expiry(header.base.epoch, o)))
+                    .collect();
+                (created, spent)
+            }
+        };
 
-        let block = MonetaryBlock::new(base, gamma, &inputs, &outputs);
+        // -----------------------------------------------------------------------------------------
+        // Alright, starting transaction.
+        // -----------------------------------------------------------------------------------------
+        info!("Revert Block: hash={}", block_hash);
 
-        blockchain.register_monetary_block(block)?;
+        if let Some(ref mut store) = self.store {
+            let created: Vec<Hash> = created.iter().map(|(hash, _expiry)| *hash).collect();
+            let restored: Vec<(Hash, OutputKey)> = spent.iter().map(|s| (s.hash, s.key)).collect();
+            store.revert_block(block_id, &block_hash, &created, &restored)?;
+        }
 
-        Ok(())
+        // Remove created outputs.
+        for (hash, expiry) in &created {
+            info!("Remove UXTO: hash={}", hash);
+            if let None = self.output_by_hash.remove(hash) {
+                unreachable!();
+            }
+            if let Some(expiry) = expiry {
+                self.remove_expiry(*expiry, hash);
+            }
+            self.utxo_tree.remove(hash);
+        }
+
+        // Restore spent outputs.
+        let mut restored = Vec::<Output>::with_capacity(spent.len());
+        for SpentOutput {
+            hash,
+            key,
+            siblings,
+            output,
+        } in spent
+        {
+            info!("Restore UXTO: hash={}", hash);
+            let output_epoch = if let Block::MonetaryBlock(MonetaryBlock { header, body }) =
+                &mut self.blocks[key.block_id]
+            {
+                body.outputs
+                    .restore(&key.path, Box::new(output.clone()), &siblings)
+                    .expect("undo data is consistent");
+                header.base.epoch
+            } else {
+                unreachable!();
+            };
+            if let Some(_) = self.output_by_hash.insert(hash, key) {
+                unreachable!();
+            }
+            if let Some(expiry) = output_expiry(output_epoch, &output) {
+                self.insert_expiry(expiry, hash);
+            }
+            self.utxo_tree.insert(&hash);
+            restored.push(output);
+        }
+
+        self.undo.remove(&block_id);
+        self.expired.remove(&block_id);
+        if let None = self.block_by_hash.remove(&block_hash) {
+            unreachable!();
+        }
+        let block = self.blocks.pop().unwrap();
+
+        Ok((block, restored))
     }
 
-    #[test]
-    fn basic() {
-        use simple_logger;
-        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+    /// Returns a snapshot of the main chain, see restore_snapshot().
+    pub fn snapshot(&self) -> Snapshot {
+        let blocks = self.blocks.clone();
+        let expired = self.expired.clone();
+        Snapshot { blocks, expired }
+    }
 
-        let keychains = [
-            KeyChain::new_mem(),
-            KeyChain::new_mem(),
-            KeyChain::new_mem(),
-        ];
+    /// Extend the main chain by a snapshot received from a peer.
+    ///
+    /// The snapshot must start with blocks of the main chain and include the trusted
+    /// `checkpoint` block. A snapshot which is not better according to the fork-choice rule
+    /// is ignored. Linkage of blocks, witnesses of key blocks and Merkle Trees of monetary
+    /// blocks are checked, and unspent outputs of the snapshot must match the UTXO root
+    /// of the last monetary block.
+    ///
+    /// Blocks of the main chain are kept as is, outputs spent by the rest of the snapshot
+    /// are pruned. Blocks of the snapshot are appended without undo data, so they can't be
+    /// reverted later.
+    /// Returns changes of the main chain.
+    ///
+    pub fn restore_snapshot(
+        &mut self,
+        snapshot: Snapshot,
+        checkpoint: &Hash,
+    ) -> Result<Vec<ChainEvent>, Error> {
+        let mut blocks = snapshot.blocks;
+        let epoch = match blocks.last() {
+            Some(last_block) => last_block.base_header().epoch,
+            None => return Ok(Vec::new()),
+        };
+        if !self.blocks.is_empty() && !self.is_better_chain(epoch, blocks.len()) {
+            info!("Ignored snapshot: epoch={}, height={}", epoch, blocks.len());
+            return Ok(Vec::new());
+        }
 
-        let (key_block, monetary_block) = genesis(&keychains);
+        // The snapshot must extend the main chain.
+        for (block_id, block) in self.blocks.iter().enumerate() {
+            let block_hash = Hash::digest(block);
+            match blocks.get(block_id) {
+                Some(snapshot_block) if Hash::digest(snapshot_block) == block_hash => {}
+                _ => return Err(BlockchainError::SnapshotMismatch(block_hash).into()),
+            }
+        }
+        if !blocks
+            .iter()
+            .any(|block| Hash::digest(block) == *checkpoint)
+        {
+            return Err(BlockchainError::MissingCheckpoint(*checkpoint).into());
+        }
+        let first_block_id = self.blocks.len();
+        let local_expired = snapshot.expired.range(..first_block_id);
+        if !local_expired.eq(self.expired.iter()) {
+            let block_hash = Hash::digest(self.last_block());
+            return Err(BlockchainError::SnapshotMismatch(block_hash).into());
+        }
+        let expired: BTreeMap<BlockId, Pt> = snapshot
+            .expired
+            .range(first_block_id..blocks.len())
+            .map(|(block_id, commitment)| (*block_id, *commitment))
+            .collect();
 
-        let mut blockchain = Blockchain::new();
-        blockchain.register_key_block(key_block).unwrap();
-        blockchain.register_monetary_block(monetary_block).unwrap();
+        // Check blocks and collect unspent outputs.
+        let mut utxo_tree = UtxoTree::new();
+        let mut utxo_root: Option<(Hash, Hash)> = None;
+        let mut unspent = HashSet::<Hash>::new();
+        let mut created = Vec::<(Hash, OutputKey)>::new();
+        let mut previous: Option<&Block> = None;
+        let mut previous_key_block: Option<&KeyBlockHeader> = None;
+        let now = Utc::now().timestamp() as u64;
+        for (block_id, block) in blocks.iter().enumerate() {
+            let block_hash = Hash::digest(block);
+            let previous_header = previous.map(|p| (Hash::digest(p), p.base_header()));
+            validate_linkage(previous_header, &block_hash, block.base_header())?;
+            let median_time =
+                median_time_past(blocks[..block_id].iter().rev().map(|b| b.base_header()));
+            validate_timestamp(&block_hash, block.base_header(), median_time, now)?;
+            match block {
+                Block::KeyBlock(key_block) => {
+                    validate_key_block(&block_hash, &key_block.header, previous_key_block)?;
+                    previous_key_block = Some(&key_block.header);
+                }
+                Block::MonetaryBlock(monetary_block) => {
+                    // Money is minted only by the monetary block of the genesis.
+                    let adjustment = monetary_block.header.monetary_adjustment;
+                    if adjustment != 0 && block_id != 1 {
+                        return Err(BlockchainError::InvalidMonetaryAdjustment(
+                            block_hash, adjustment,
+                        )
+                        .into());
+                    }
+                    monetary_block.validate_limits()?;
+                    let MonetaryBlock { header, body } = monetary_block;
+                    if body.outputs.validate().is_err() {
+                        return Err(BlockchainError::InvalidOutputsTree(block_hash).into());
+                    }
+                    for (output, path) in body.outputs.leafs() {
+                        let output_hash = Hash::digest(output);
+                        if !utxo_tree.insert(&output_hash) {
+                            return Err(BlockchainError::OutputHashCollision(output_hash).into());
+                        }
+                        unspent.insert(output_hash);
+                        let key = OutputKey { block_id, path };
+                        if block_id >= self.blocks.len() {
+                            created.push((output_hash, key));
+                            continue;
+                        }
+                        // Outputs of local blocks must be unspent locally.
+                        match self.output_by_hash.get(&output_hash) {
+                            Some(local_key)
+                                if local_key.block_id == block_id && local_key.path == path => {}
+                            _ => return Err(BlockchainError::SnapshotMismatch(block_hash).into()),
+                        }
+                    }
+                    utxo_root = Some((block_hash, header.utxo_root));
+                }
+            }
+            previous = Some(block);
+        }
+        if let Some((block_hash, utxo_root)) = utxo_root {
+            if utxo_root != utxo_tree.roothash() {
+                return Err(BlockchainError::InvalidUtxoRoot(
+                    block_hash,
+                    utxo_tree.roothash(),
+                    utxo_root,
+                )
+                .into());
+            }
+        }
 
-        assert!(blockchain.blocks().len() > 0);
-        iterate(&mut blockchain).unwrap();
-        iterate(&mut blockchain).unwrap();
-        iterate(&mut blockchain).unwrap();
+        // Local outputs which are spent or expired by the rest of the snapshot.
+        let spent: Vec<Hash> = self
+            .output_by_hash
+            .keys()
+            .filter(|hash| !unspent.contains(hash))
+            .cloned()
+            .collect();
+        let blocks = blocks.split_off(first_block_id);
+
+        info!(
+            "Restore snapshot: epoch={}, prune={}, apply={}",
+            epoch,
+            spent.len(),
+            blocks.len()
+        );
+
+        if let Some(ref mut store) = self.store {
+            store.commit_snapshot(first_block_id, &blocks, &spent, &created, &expired)?;
+        }
+
+        let mut pruned = HashMap::<Hash, Output>::with_capacity(spent.len());
+        for hash in spent {
+            info!("Prune UXTO: hash={}", hash);
+            let key = self.output_by_hash.remove(&hash).expect("unspent output");
+            let (output_epoch, output) = match &mut self.blocks[key.block_id] {
+                Block::MonetaryBlock(MonetaryBlock { header, body }) => {
+                    let output = body.outputs.prune(&key.path).expect("unspent output");
+                    (header.base.epoch, output)
+                }
+                Block::KeyBlock(_) => unreachable!(),
+            };
+            if let Some(expiry) = output_expiry(output_epoch, &output) {
+                self.remove_expiry(expiry, &hash);
+            }
+            self.utxo_tree.remove(&hash);
+            pruned.insert(hash, *output);
+        }
+        self.expired.extend(expired);
+
+        let mut events = Vec::<ChainEvent>::with_capacity(blocks.len());
+        for block in blocks {
+            let inputs: Vec<Output> = match block {
+                Block::MonetaryBlock(ref monetary_block) => monetary_block
+                    .body
+                    .inputs
+                    .iter()
+                    .filter_map(|hash| pruned.remove(hash))
+                    .collect(),
+                Block::KeyBlock(_) => Vec::new(),
+            };
+            self.register_pruned_block(block.clone());
+            events.push(ChainEvent::Registered {
+                block,
+                pruned: inputs,
+                expired: Vec::new(),
+            });
+        }
+        // The rest of pruned outputs is expired.
+        if let Some(ChainEvent::Registered { expired, .. }) = events.last_mut() {
+            expired.extend(pruned.into_iter().map(|(_hash, output)| output));
+        }
+        assert_eq!(self.utxo_tree.roothash(), utxo_tree.roothash());
+
+        Ok(events)
+    }
+
+    /// Check the monetary balance of the whole main chain without revealing amounts.
+    ///
+    /// Each monetary block satisfies \sum C_i - \sum C_o + monetary_adjustment * A = gamma * G,
+    /// so the sum over all blocks is
+    ///
+    /// ```text
+    /// \sum C_unspent + \sum C_expired = supply * A - \sum gamma * G
+    /// ```
+    ///
+    /// where supply is the sum of monetary adjustments. Fees are paid to outputs of the same
+    /// block, so they are covered by gamma. Commitments of expired data outputs are summed
+    /// by blocks when outputs expire and are restored from snapshots.
+    ///
+    /// Returns the total supply.
+    ///
+    pub fn audit_supply(&self) -> Result<i64, Error> {
+        let mut supply: i64 = 0;
+        let mut gamma = Fr::zero();
+        for block in &self.blocks {
+            if let Block::MonetaryBlock(MonetaryBlock { header, body: _ }) = block {
+                supply += header.monetary_adjustment;
+                gamma += header.gamma;
+            }
+        }
+
+        let mut expired = ECp::inf();
+        for commitment in self.expired.values() {
+            let commitment: ECp = Pt::decompress(*commitment)?;
+            expired += commitment;
+        }
+
+        let mut unspent = ECp::inf();
+        for output_hash in self.output_by_hash.keys() {
+            let output = self
+                .output_by_hash(output_hash)
+                .expect("unspent output exists");
+            let pedersen_commitment = match output {
+                Output::MonetaryOutput(o) => o.proof.vcmt,
+                Output::DataOutput(o) => o.vcmt,
+            };
+            let pedersen_commitment: ECp = Pt::decompress(pedersen_commitment)?;
+            unspent += pedersen_commitment;
+        }
+
+        if unspent + expired + gamma * (*G) != fee_a(supply) {
+            return Err(BlockchainError::SupplyMismatch(supply).into());
+        }
+
+        Ok(supply)
+    }
+
+    //----------------------------------------------------------------------------------------------
+
+    /// Returns the header of the last key block of the main chain.
+    fn last_key_block(&self) -> Option<&KeyBlockHeader> {
+        self.blocks
+            .iter()
+            .rev()
+            .filter_map(|block| match block {
+                Block::KeyBlock(key_block) => Some(&key_block.header),
+                Block::MonetaryBlock(_) => None,
+            })
+            .next()
+    }
+
+    /// Returns the header of the last key block of a side branch.
+    ///
+    /// `branch` are hashes of side blocks after the fork point `fork_id`.
+    ///
+    fn branch_key_block(&self, fork_id: BlockId, branch: &[Hash]) -> Option<&KeyBlockHeader> {
+        let side_blocks = branch.iter().rev().map(|hash| &self.side_blocks[hash]);
+        let main_blocks = self.blocks[..=fork_id].iter().rev();
+        side_blocks
+            .chain(main_blocks)
+            .filter_map(|block| match block {
+                Block::KeyBlock(key_block) => Some(&key_block.header),
+                Block::MonetaryBlock(_) => None,
+            })
+            .next()
+    }
+
+    /// Collect undo information for an unspent output.
+    fn spent_output(&self, output_hash: &Hash) -> Option<SpentOutput> {
+        let key = self.output_by_hash.get(output_hash)?;
+        assert!(key.block_id < self.blocks.len());
+        let block = &self.blocks[key.block_id];
+        if let Block::MonetaryBlock(MonetaryBlock { header: _, body }) = block {
+            if let Some(output) = body.outputs.lookup(&key.path) {
+                // Check that hash is the same.
+                assert_eq!(Hash::digest(output), *output_hash);
+                // Save the position in Merkle Tree for unregister_last_block().
+                let siblings = body.outputs.siblings(&key.path).expect("exists");
+                Some(SpentOutput {
+                    hash: *output_hash,
+                    key: *key,
+                    siblings,
+                    output: (**output).clone(),
+                })
+            } else {
+                // Internal database inconsistency - missing UTXO in block.
+                unreachable!();
+            }
+        } else {
+            // Internal database inconsistency - invalid block type.
+            unreachable!();
+        }
+    }
+
+    /// Returns the set of unspent outputs after the next monetary block of the epoch.
+    ///
+    /// Inputs and data outputs expired at the epoch of the block are removed,
+    /// created outputs are added.
+    ///
+    fn utxo_tree_after(&self, epoch: u64, inputs: &[Hash], outputs: &[Hash]) -> UtxoTree {
+        let mut utxo_tree = self.utxo_tree.clone();
+        for input_hash in inputs {
+            utxo_tree.remove(input_hash);
+        }
+        for (_expiry, hashes) in self.expiry.range(..=epoch) {
+            for hash in hashes {
+                utxo_tree.remove(hash);
+            }
+        }
+        for output_hash in outputs {
+            utxo_tree.insert(output_hash);
+        }
+        utxo_tree
+    }
+
+    /// Schedule expiration of a data output.
+    fn insert_expiry(&mut self, expiry: u64, output_hash: Hash) {
+        self.expiry
+            .entry(expiry)
+            .or_insert_with(HashSet::new)
+            .insert(output_hash);
+    }
+
+    /// Cancel expiration of a data output.
+    fn remove_expiry(&mut self, expiry: u64, output_hash: &Hash) {
+        let is_empty = match self.expiry.get_mut(&expiry) {
+            Some(hashes) => {
+                hashes.remove(output_hash);
+                hashes.is_empty()
+            }
+            None => false,
+        };
+        if is_empty {
+            self.expiry.remove(&expiry);
+        }
+    }
+
+    /// Check a block which doesn't extend the main chain.
+    ///
+    /// Blocks are not signed yet, so anyone can create a side branch. The damage is limited
+    /// by allowing only the next epoch, reorganizations up to MAX_REORG_DEPTH blocks and
+    /// key blocks led by witnesses of the current epoch of the branch.
+    ///
+    /// `branch` are hashes of side blocks between the fork point `fork_id` and the block.
+    ///
+    /// TODO: check CoSi multisignatures of blocks instead.
+    fn validate_side_block(
+        &self,
+        block_hash: &Hash,
+        block: &Block,
+        fork_id: BlockId,
+        branch: &[Hash],
+    ) -> Result<(), Error> {
+        let max_epoch = self.last_block().base_header().epoch + 1;
+        let epoch = block.base_header().epoch;
+        if epoch > max_epoch {
+            return Err(
+                BlockchainError::SideBlockEpochTooFar(*block_hash, max_epoch, epoch).into(),
+            );
+        }
+
+        let depth = self.blocks.len() - fork_id - 1;
+        if depth > MAX_REORG_DEPTH {
+            return Err(BlockchainError::ReorganizationTooDeep(
+                *block_hash,
+                depth,
+                MAX_REORG_DEPTH,
+            )
+            .into());
+        }
+
+        if let Block::KeyBlock(key_block) = block {
+            if let Some(current) = self.branch_key_block(fork_id, branch) {
+                if current
+                    .witnesses
+                    .binary_search(&key_block.header.leader)
+                    .is_err()
+                {
+                    return Err(BlockchainError::LeaderIsNotCurrentWitness(*block_hash).into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a block to side branches.
+    fn insert_side_block(&mut self, block_hash: Hash, block: Block) {
+        self.side_blocks.insert(block_hash, block);
+        self.side_queue.push_back(block_hash);
+    }
+
+    /// Remove a block from side branches.
+    fn remove_side_block(&mut self, block_hash: &Hash) -> Option<Block> {
+        let block = self.side_blocks.remove(block_hash)?;
+        // The queue is short, see MAX_SIDE_BLOCKS.
+        self.side_queue.retain(|hash| hash != block_hash);
+        Some(block)
+    }
+
+    /// The fork-choice rule.
+    ///
+    /// A chain is better than the main chain if its last block has a greater epoch,
+    /// or the same epoch and a greater number of blocks.
+    ///
+    /// TODO: take into account CoSi multisignatures of blocks.
+    fn is_better_chain(&self, epoch: u64, height: usize) -> bool {
+        let last_epoch = self.last_block().base_header().epoch;
+        (epoch, height) > (last_epoch, self.blocks.len())
+    }
+
+    /// Switch the main chain to a side branch.
+    ///
+    /// If a block of the branch is invalid, the original main chain is restored
+    /// and the error is returned. Panics if the main chain can't be reverted or restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `fork_id` - The last common block of the main chain and the branch.
+    /// * `branch` - Hashes of side blocks, starting from the fork point.
+    ///
+    fn reorganize(&mut self, fork_id: BlockId, branch: &[Hash]) -> Result<Vec<ChainEvent>, Error> {
+        info!(
+            "Reorganize: fork={}, revert={}, apply={}",
+            Hash::digest(&self.blocks[fork_id]),
+            self.blocks.len() - fork_id - 1,
+            branch.len()
+        );
+
+        // Check that all blocks can be reverted before touching anything.
+        for block_id in fork_id + 1..self.blocks.len() {
+            if let Block::MonetaryBlock(_) = self.blocks[block_id] {
+                if !self.undo.contains_key(&block_id) {
+                    let block_hash = Hash::digest(&self.blocks[block_id]);
+                    return Err(BlockchainError::MissingUndoData(block_hash).into());
+                }
+            }
+        }
+
+        let mut events = Vec::<ChainEvent>::new();
+
+        // Revert the main chain up to the fork point.
+        // Undo data is checked above, a failure here leaves the chain half-reverted.
+        let mut reverted = Vec::<Hash>::new();
+        while self.blocks.len() > fork_id + 1 {
+            let (block, restored) = match self.unregister_last_block() {
+                Ok(r) => r,
+                Err(e) => panic!("Failed to revert the main chain: error={}", e),
+            };
+            let block_hash = Hash::digest(&block);
+            self.insert_side_block(block_hash, block.clone());
+            reverted.push(block_hash);
+            events.push(ChainEvent::Reverted { block, restored });
+        }
+
+        // Apply the branch.
+        for (applied, block_hash) in branch.iter().enumerate() {
+            let block = self.remove_side_block(block_hash).expect("side block");
+            match self.apply_block(block.clone()) {
+                Ok((pruned, expired)) => events.push(ChainEvent::Registered {
+                    block,
+                    pruned,
+                    expired,
+                }),
+                Err(e) => {
+                    error!(
+                        "Invalid block in side branch: hash={}, error={}",
+                        block_hash, e
+                    );
+                    self.remove_side_branch(block_hash);
+
+                    // Return back to the original chain.
+                    // These blocks were registered before, so a failure leaves the chain
+                    // in an inconsistent state which can't be recovered.
+                    for _ in 0..applied {
+                        let (block, _restored) = match self.unregister_last_block() {
+                            Ok(r) => r,
+                            Err(e) => panic!("Failed to revert a side branch: error={}", e),
+                        };
+                        self.insert_side_block(Hash::digest(&block), block);
+                    }
+                    for block_hash in reverted.iter().rev() {
+                        let block = self.remove_side_block(block_hash).expect("side block");
+                        if let Err(e) = self.register_block(block) {
+                            panic!(
+                                "Failed to restore the main chain: block={}, error={}",
+                                block_hash, e
+                            );
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Remove descendants of a block from side branches.
+    fn remove_side_branch(&mut self, block_hash: &Hash) {
+        let mut removed = vec![*block_hash];
+        while let Some(parent) = removed.pop() {
+            let children: Vec<Hash> = self
+                .side_blocks
+                .iter()
+                .filter(|(_hash, block)| block.base_header().previous == parent)
+                .map(|(hash, _block)| *hash)
+                .collect();
+            for child in children {
+                debug!("Remove side block: hash={}", child);
+                self.remove_side_block(&child);
+                removed.push(child);
+            }
+        }
+    }
+
+    /// Validate and register a block.
+    fn apply_block(&mut self, block: Block) -> Result<(Vec<Output>, Vec<Output>), Error> {
+        self.validate_block(&block)?;
+        self.register_block(block)
+    }
+
+    /// Register a block of a snapshot, which is checked and stored by restore_snapshot().
+    fn register_pruned_block(&mut self, block: Block) {
+        let block_id = self.blocks.len();
+        let block_hash = Hash::digest(&block);
+        let epoch = block.base_header().epoch;
+        let created: Vec<(Hash, MerklePath, Option<u64>)> = match block {
+            Block::MonetaryBlock(MonetaryBlock {
+                header: _,
+                ref body,
+            }) => body
+                .outputs
+                .leafs()
+                .iter()
+                .map(|(o, path)| (Hash::digest(*o), *path, output_expiry(epoch, o)))
+                .collect(),
+            Block::KeyBlock(_) => Vec::new(),
+        };
+
+        info!("Register Snapshot Block: hash={}", block_hash);
+
+        for (hash, path, expiry) in created {
+            info!("Register UXTO: hash={}", &hash);
+            let output_key = OutputKey { block_id, path };
+            if let Some(_) = self.output_by_hash.insert(hash, output_key) {
+                unreachable!();
+            }
+            if let Some(expiry) = expiry {
+                self.insert_expiry(expiry, hash);
+            }
+            self.utxo_tree.insert(&hash);
+        }
+        if let Some(_) = self.block_by_hash.insert(block_hash, block_id) {
+            unreachable!();
+        }
+        self.blocks.push(block);
+    }
+
+    /// Register a block of any type.
+    fn register_block(&mut self, block: Block) -> Result<(Vec<Output>, Vec<Output>), Error> {
+        match block {
+            Block::KeyBlock(key_block) => {
+                self.register_key_block(key_block)?;
+                Ok((Vec::new(), Vec::new()))
+            }
+            Block::MonetaryBlock(monetary_block) => self.register_monetary_block(monetary_block),
+        }
+    }
+}
+
+/// Check that a block follows the previous block of the chain.
+///
+/// `previous` is the hash and the header of the previous block, None for the genesis.
+///
+pub(crate) fn validate_linkage(
+    previous: Option<(Hash, &BaseBlockHeader)>,
+    block_hash: &Hash,
+    header: &BaseBlockHeader,
+) -> Result<(), Error> {
+    let (previous_hash, previous_header) = match previous {
+        Some(previous) => previous,
+        None => return Ok(()),
+    };
+    if header.previous != previous_hash {
+        return Err(BlockchainError::PreviousHashMismatch(previous_hash, header.previous).into());
+    }
+    if header.epoch < previous_header.epoch {
+        return Err(BlockchainError::OutOfOrderEpoch(
+            *block_hash,
+            previous_header.epoch,
+            header.epoch,
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Returns the median timestamp of the last MEDIAN_TIME_PAST_BLOCKS blocks, 0 for no blocks.
+///
+/// `headers` are headers of the chain in the reverse order, starting from the last block.
+///
+pub(crate) fn median_time_past<'a, I>(headers: I) -> u64
+where
+    I: Iterator<Item = &'a BaseBlockHeader>,
+{
+    let mut timestamps: Vec<u64> = headers
+        .take(MEDIAN_TIME_PAST_BLOCKS)
+        .map(|header| header.timestamp)
+        .collect();
+    if timestamps.is_empty() {
+        return 0;
+    }
+    timestamps.sort();
+    timestamps[timestamps.len() / 2]
+}
+
+/// Check that the timestamp of a block is not before the median time past of previous blocks
+/// and is not too far in the future from the local time.
+///
+/// Blocks can have the same timestamp, e.g. genesis blocks.
+///
+pub(crate) fn validate_timestamp(
+    block_hash: &Hash,
+    header: &BaseBlockHeader,
+    median_time: u64,
+    now: u64,
+) -> Result<(), Error> {
+    if header.timestamp < median_time {
+        return Err(BlockchainError::TimestampBeforeMedianTime(
+            *block_hash,
+            median_time,
+            header.timestamp,
+        )
+        .into());
+    }
+    let max_time = now + MAX_FUTURE_DRIFT;
+    if header.timestamp > max_time {
+        return Err(
+            BlockchainError::TimestampInFuture(*block_hash, max_time, header.timestamp).into(),
+        );
+    }
+    Ok(())
+}
+
+/// Check that witnesses of a key block are sorted and include the leader,
+/// and that the leader is elected from witnesses of the previous key block.
+///
+/// `previous` is the header of the previous key block, None for the genesis.
+///
+pub(crate) fn validate_key_block(
+    block_hash: &Hash,
+    header: &KeyBlockHeader,
+    previous: Option<&KeyBlockHeader>,
+) -> Result<(), Error> {
+    let witnesses = &header.witnesses;
+    if witnesses.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(BlockchainError::InvalidWitnesses(*block_hash).into());
+    }
+    if witnesses.binary_search(&header.leader).is_err() {
+        return Err(BlockchainError::LeaderIsNotWitness(*block_hash).into());
+    }
+    if let Some(previous) = previous {
+        if previous.witnesses.binary_search(&header.leader).is_err() {
+            return Err(BlockchainError::LeaderIsNotPreviousWitness(*block_hash).into());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the sum of Pedersen commitments of outputs.
+fn commitments_sum<'a, I: Iterator<Item = &'a Output>>(outputs: I) -> Result<Pt, Error> {
+    let mut sum = ECp::inf();
+    for output in outputs {
+        let pedersen_commitment = match output {
+            Output::MonetaryOutput(o) => o.proof.vcmt,
+            Output::DataOutput(o) => o.vcmt,
+        };
+        let pedersen_commitment: ECp = Pt::decompress(pedersen_commitment)?;
+        sum += pedersen_commitment;
+    }
+    Ok(Pt::compress(sum))
+}
+
+/// Returns the epoch at which a data output expires, None for other outputs.
+///
+/// A data output registered in a block of the epoch `epoch` is kept for `ttl` epochs
+/// and pruned by the first monetary block of the epoch `epoch + ttl` or later.
+fn output_expiry(epoch: u64, output: &Output) -> Option<u64> {
+    match output {
+        Output::DataOutput(o) => Some(epoch.saturating_add(cmp::max(o.ttl, 1))),
+        Output::MonetaryOutput(_) => None,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use chrono::prelude::Utc;
+
+    use crate::genesis::genesis;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use stegos_crypto::curve1174::cpt::make_random_keys;
+    use stegos_keychain::KeyChain;
+
+    /// A codec which keeps blocks in memory and stores only hashes.
+    #[derive(Clone)]
+    pub struct MemoryCodec {
+        blocks: Arc<Mutex<HashMap<Hash, Block>>>,
+        /// The number of zero bytes appended to encoded blocks.
+        padding: usize,
+    }
+
+    impl MemoryCodec {
+        pub fn new() -> MemoryCodec {
+            MemoryCodec::with_padding(0)
+        }
+
+        pub fn with_padding(padding: usize) -> MemoryCodec {
+            let blocks = Arc::new(Mutex::new(HashMap::new()));
+            MemoryCodec { blocks, padding }
+        }
+    }
+
+    impl BlockCodec for MemoryCodec {
+        fn encode(&self, block: &Block) -> Vec<u8> {
+            let hash = Hash::digest(block);
+            self.blocks.lock().unwrap().insert(hash, block.clone());
+            let mut data = hash.base_vector().to_vec();
+            data.resize(data.len() + self.padding, 0);
+            data
+        }
+
+        fn decode(&self, data: &[u8]) -> Result<Block, Error> {
+            let hash = Hash::try_from_bytes(&data[..data.len() - self.padding])?;
+            let blocks = self.blocks.lock().unwrap();
+            let block = blocks.get(&hash).expect("encoded before").clone();
+            Ok(block)
+        }
+    }
+
+    /// Returns a path to a fresh database file.
+    pub fn temp_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("stegos-blockchain-{}-{}", std::process::id(), name));
+        std::fs::remove_file(&path).ok();
+        path
+    }
+
+    /// Returns the sorted list of UTXO.
+    fn sorted_unspent(blockchain: &Blockchain) -> Vec<Hash> {
+        let mut unspent = blockchain.unspent();
+        unspent.sort_by(|a, b| a.base_vector().cmp(b.base_vector()));
+        unspent
+    }
+
+    pub fn iterate(blockchain: &mut Blockchain) -> Result<(), Error> {
+        let version = 1;
+        let timestamp = Utc::now().timestamp() as u64;
+        let (epoch, previous) = {
+            let last = blockchain.last_block();
+            let base_header = last.base_header();
+            let epoch = base_header.epoch + 1;
+            let previous = Hash::digest(last);
+            (epoch, previous)
+        };
+
+        let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
+
+        let (skey, pkey, _sig) = make_random_keys();
+
+        let output_hash = blockchain.output_by_hash.keys().next().unwrap().clone();
+        let input = output_hash.clone();
+        let inputs = [input];
+
+        let amount: i64 = 112;
+        let (output, gamma) =
+            Output::new_monetary(timestamp, &skey, &pkey, amount).expect("tests have valid keys");
+        let outputs = [output];
+
+        let utxo_root = blockchain.next_utxo_root(epoch, &inputs, &outputs);
+        let block = MonetaryBlock::new(base, gamma, &inputs, &outputs, utxo_root, 0);
+
+        blockchain.register_monetary_block(block)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn basic() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [
+            KeyChain::new_mem(),
+            KeyChain::new_mem(),
+            KeyChain::new_mem(),
+        ];
+
+        let (key_block, monetary_block) = genesis(&keychains);
+
+        let mut blockchain = Blockchain::new();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+
+        assert!(blockchain.blocks().len() > 0);
+        iterate(&mut blockchain).unwrap();
+        iterate(&mut blockchain).unwrap();
+        iterate(&mut blockchain).unwrap();
+    }
+
+    #[test]
+    fn persistent() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem()];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let codec = MemoryCodec::new();
+        let path = temp_path("persistent");
+
+        let (last_hash, unspent) = {
+            let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+            assert!(blockchain.blocks().is_empty());
+            blockchain.register_key_block(key_block).unwrap();
+            blockchain.register_monetary_block(monetary_block).unwrap();
+            iterate(&mut blockchain).unwrap();
+            iterate(&mut blockchain).unwrap();
+            (
+                Hash::digest(blockchain.last_block()),
+                sorted_unspent(&blockchain),
+            )
+        };
+
+        // Resume from the last block.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(blockchain.blocks().len(), 4);
+        assert_eq!(Hash::digest(blockchain.last_block()), last_hash);
+        assert_eq!(sorted_unspent(&blockchain), unspent);
+        for hash in &unspent {
+            let output = blockchain.output_by_hash(hash).unwrap();
+            assert_eq!(Hash::digest(output), *hash);
+        }
+        iterate(&mut blockchain).unwrap();
+        let last_hash = Hash::digest(blockchain.last_block());
+        let unspent = sorted_unspent(&blockchain);
+        drop(blockchain);
+
+        let blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(blockchain.blocks().len(), 5);
+        assert_eq!(Hash::digest(blockchain.last_block()), last_hash);
+        assert_eq!(sorted_unspent(&blockchain), unspent);
+        drop(blockchain);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Create a valid monetary block which moves the output to a new output of the same owner.
+    ///
+    /// The new output is expected to be the only unspent output after the block,
+    /// see set_utxo_root() for other cases.
+    fn transfer(previous: Hash, epoch: u64, input: &Output, keys: &KeyChain) -> Block {
+        let timestamp = Utc::now().timestamp() as u64;
+        let base = BaseBlockHeader::new(1, previous, epoch, timestamp);
+        let (gamma0, amount) = match input {
+            Output::MonetaryOutput(o) => {
+                let (_delta, gamma, amount) = o.decrypt_payload(&keys.wallet_skey).unwrap();
+                (gamma, amount)
+            }
+            _ => panic!(),
+        };
+        let (output, gamma1) =
+            Output::new_monetary(timestamp, &keys.wallet_skey, &keys.wallet_pkey, amount).unwrap();
+        let utxo_root = UtxoTree::from_hashes(&[Hash::digest(&output)]).roothash();
+        let inputs = [Hash::digest(input)];
+        let block = MonetaryBlock::new(base, gamma0 - gamma1, &inputs, &[output], utxo_root, 0);
+        Block::MonetaryBlock(block)
+    }
+
+    /// Create a valid monetary block which moves the output and creates a data output.
+    fn transfer_with_data(
+        previous: Hash,
+        epoch: u64,
+        input: &Output,
+        keys: &KeyChain,
+        ttl: u64,
+    ) -> Block {
+        let timestamp = Utc::now().timestamp() as u64;
+        let base = BaseBlockHeader::new(1, previous, epoch, timestamp);
+        let (gamma0, amount) = match input {
+            Output::MonetaryOutput(o) => {
+                let (_delta, gamma, amount) = o.decrypt_payload(&keys.wallet_skey).unwrap();
+                (gamma, amount)
+            }
+            _ => panic!(),
+        };
+        let skey = &keys.wallet_skey;
+        let pkey = &keys.wallet_pkey;
+        let (output, gamma1) = Output::new_monetary(timestamp, skey, pkey, amount).unwrap();
+        let (data, gamma2) = Output::new_data(timestamp, skey, pkey, ttl, b"hello").unwrap();
+        let gamma = gamma0 - gamma1 - gamma2;
+        let utxo_root = UtxoTree::from_hashes(&[Hash::digest(&output), Hash::digest(&data)]);
+        let utxo_root = utxo_root.roothash();
+        let inputs = [Hash::digest(input)];
+        let block = MonetaryBlock::new(base, gamma, &inputs, &[output, data], utxo_root, 0);
+        Block::MonetaryBlock(block)
+    }
+
+    /// Change the set of unspent outputs committed by a monetary block.
+    fn set_utxo_root(block: &mut Block, unspent: &[Hash]) {
+        match block {
+            Block::MonetaryBlock(ref mut block) => {
+                block.header.utxo_root = UtxoTree::from_hashes(unspent).roothash();
+            }
+            _ => panic!(),
+        }
+    }
+
+    /// Returns the first output of a monetary block.
+    fn first_output(block: &Block) -> Output {
+        match block {
+            Block::MonetaryBlock(MonetaryBlock { header: _, body }) => {
+                (**body.outputs.leafs()[0].0).clone()
+            }
+            _ => panic!(),
+        }
+    }
+
+    /// Returns (registered, block hash, output hashes) for every event.
+    fn events_summary(events: &[ChainEvent]) -> Vec<(bool, Hash, Vec<Hash>)> {
+        let hashes = |outputs: &[Output]| outputs.iter().map(|o| Hash::digest(o)).collect();
+        events
+            .iter()
+            .map(|event| match event {
+                ChainEvent::Registered { block, pruned, .. } => {
+                    (true, Hash::digest(block), hashes(pruned))
+                }
+                ChainEvent::Reverted { block, restored } => {
+                    (false, Hash::digest(block), hashes(restored))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reorganize() {
+        use simple_logger;
+        use stegos_crypto::curve1174::fields::Fr;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem()];
+        let keys = &keychains[0];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let genesis_hash = Hash::digest(&monetary_block);
+        let genesis_output = first_output(&Block::MonetaryBlock(monetary_block.clone()));
+        let codec = MemoryCodec::new();
+        let path = temp_path("reorganize");
+
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+
+        // The main chain: genesis -> a2.
+        let a2 = transfer(genesis_hash, 2, &genesis_output, keys);
+        let a2_hash = Hash::digest(&a2);
+        let a2_output = first_output(&a2);
+        let events = blockchain.push_block(a2.clone()).unwrap();
+        assert_eq!(
+            events_summary(&events),
+            vec![(true, a2_hash, vec![Hash::digest(&genesis_output)])]
+        );
+
+        // A side branch with the same epoch and height: genesis -> b2.
+        let b2 = transfer(genesis_hash, 2, &genesis_output, keys);
+        let b2_hash = Hash::digest(&b2);
+        assert!(blockchain.push_block(b2.clone()).unwrap().is_empty());
+        assert!(blockchain.push_block(b2.clone()).is_err());
+        assert!(blockchain.contains_block(&b2_hash));
+        assert!(blockchain.block_by_hash(&b2_hash).is_none());
+        assert_eq!(Hash::digest(blockchain.last_block()), a2_hash);
+        assert_eq!(blockchain.unspent(), vec![Hash::digest(&a2_output)]);
+
+        // Unknown parent.
+        let unknown = transfer(Hash::digest(&"unknown".to_string()), 3, &a2_output, keys);
+        assert!(blockchain.push_block(unknown).is_err());
+
+        // The side branch becomes better: genesis -> b2 -> b3.
+        let b3 = transfer(b2_hash, 3, &first_output(&b2), keys);
+        let b3_hash = Hash::digest(&b3);
+        let b3_output = first_output(&b3);
+        let events = blockchain.push_block(b3).unwrap();
+        assert_eq!(
+            events_summary(&events),
+            vec![
+                (false, a2_hash, vec![Hash::digest(&genesis_output)]),
+                (true, b2_hash, vec![Hash::digest(&genesis_output)]),
+                (true, b3_hash, vec![Hash::digest(&first_output(&b2))]),
+            ]
+        );
+        assert_eq!(blockchain.blocks().len(), 4);
+        assert_eq!(Hash::digest(blockchain.last_block()), b3_hash);
+        assert_eq!(blockchain.unspent(), vec![Hash::digest(&b3_output)]);
+        assert!(blockchain.contains_block(&a2_hash));
+        assert!(blockchain.block_by_hash(&a2_hash).is_none());
+
+        // A better branch with an invalid block is rejected: genesis -> a2 -> a3.
+        let mut a3 = transfer(a2_hash, 4, &a2_output, keys);
+        if let Block::MonetaryBlock(ref mut block) = a3 {
+            block.header.gamma = Fr::zero();
+        }
+        let a3_hash = Hash::digest(&a3);
+        assert!(blockchain.push_block(a3).is_err());
+        assert!(!blockchain.contains_block(&a3_hash));
+        assert_eq!(blockchain.blocks().len(), 4);
+        assert_eq!(Hash::digest(blockchain.last_block()), b3_hash);
+        assert_eq!(blockchain.unspent(), vec![Hash::digest(&b3_output)]);
+
+        // Switch back to a valid branch: genesis -> a2 -> a3.
+        let a3 = transfer(a2_hash, 4, &a2_output, keys);
+        let a3_hash = Hash::digest(&a3);
+        let a3_output = first_output(&a3);
+        let events = blockchain.push_block(a3).unwrap();
+        assert_eq!(
+            events_summary(&events),
+            vec![
+                (false, b3_hash, vec![Hash::digest(&first_output(&b2))]),
+                (false, b2_hash, vec![Hash::digest(&genesis_output)]),
+                (true, a2_hash, vec![Hash::digest(&genesis_output)]),
+                (true, a3_hash, vec![Hash::digest(&a2_output)]),
+            ]
+        );
+        assert_eq!(Hash::digest(blockchain.last_block()), a3_hash);
+        assert_eq!(blockchain.unspent(), vec![Hash::digest(&a3_output)]);
+        drop(blockchain);
+
+        // Reverted blocks are removed from the disk.
+        let blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(blockchain.blocks().len(), 4);
+        assert_eq!(Hash::digest(blockchain.last_block()), a3_hash);
+        assert_eq!(blockchain.unspent(), vec![Hash::digest(&a3_output)]);
+        drop(blockchain);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Check limits of side branches.
+    #[test]
+    fn side_branches() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem(), KeyChain::new_mem()];
+        let keys = &keychains[0];
+        let (key_block, monetary_block) = genesis(&keychains[0..1]);
+        let genesis_hash = Hash::digest(&monetary_block);
+        let genesis_output = first_output(&Block::MonetaryBlock(monetary_block.clone()));
+        let mut blockchain = Blockchain::new();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+        let a2 = transfer(genesis_hash, 2, &genesis_output, keys);
+        blockchain.push_block(a2).unwrap();
+
+        let timestamp = Utc::now().timestamp() as u64;
+        let key_block = |previous: Hash, epoch: u64, timestamp: u64, leader: &KeyChain| {
+            let base = BaseBlockHeader::new(1, previous, epoch, timestamp);
+            let leader = leader.cosi_pkey.clone();
+            Block::KeyBlock(KeyBlock::new(base, leader, &[leader]))
+        };
+        let push_error = |blockchain: &mut Blockchain, block: Block| -> BlockchainError {
+            let e = blockchain.push_block(block).unwrap_err();
+            e.downcast::<BlockchainError>().unwrap()
+        };
+
+        // The epoch is too far ahead.
+        let block = key_block(genesis_hash, 4, timestamp, keys);
+        match push_error(&mut blockchain, block) {
+            BlockchainError::SideBlockEpochTooFar(_, max, got) => assert_eq!((max, got), (3, 4)),
+            e => panic!("{}", e),
+        }
+
+        // The leader is not a witness of the current epoch.
+        let block = key_block(genesis_hash, 3, timestamp, &keychains[1]);
+        let block_hash = Hash::digest(&block);
+        match push_error(&mut blockchain, block) {
+            BlockchainError::LeaderIsNotCurrentWitness(hash) => assert_eq!(hash, block_hash),
+            e => panic!("{}", e),
+        }
+        assert!(!blockchain.contains_block(&block_hash));
+
+        // The oldest side blocks are evicted.
+        let mut side_hashes = Vec::new();
+        for i in 0..MAX_SIDE_BLOCKS + 1 {
+            let block = key_block(genesis_hash, 2, timestamp + i as u64, keys);
+            side_hashes.push(Hash::digest(&block));
+            assert!(blockchain.push_block(block).unwrap().is_empty());
+        }
+        assert!(!blockchain.contains_block(&side_hashes[0]));
+        for side_hash in &side_hashes[1..] {
+            assert!(blockchain.contains_block(side_hash));
+        }
+
+        // The reorganization is too deep.
+        for _ in 0..MAX_REORG_DEPTH {
+            let last = blockchain.last_block();
+            let (previous, epoch) = (Hash::digest(last), last.base_header().epoch + 1);
+            let block = key_block(previous, epoch, timestamp, keys);
+            blockchain.push_block(block).unwrap();
+        }
+        let epoch = blockchain.last_block().base_header().epoch;
+        let fork = blockchain.blocks()[1 + 1].clone();
+        let block = key_block(Hash::digest(&fork), epoch, timestamp, keys);
+        assert!(blockchain.push_block(block).unwrap().is_empty());
+        let block = key_block(genesis_hash, epoch, timestamp, keys);
+        match push_error(&mut blockchain, block) {
+            BlockchainError::ReorganizationTooDeep(_, depth, max) => {
+                assert_eq!((depth, max), (MAX_REORG_DEPTH + 1, MAX_REORG_DEPTH))
+            }
+            e => panic!("{}", e),
+        }
+    }
+
+    /// Check that leaders of side key blocks are checked against the key block of the branch.
+    #[test]
+    fn side_key_blocks() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem(), KeyChain::new_mem()];
+        let keys = &keychains[0];
+        let (key_block, monetary_block) = genesis(&keychains[0..1]);
+        let genesis_hash = Hash::digest(&monetary_block);
+        let mut output = first_output(&Block::MonetaryBlock(monetary_block.clone()));
+        let mut blockchain = Blockchain::new();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+
+        // The main chain: genesis -> a2 -> a3 -> a4.
+        for _ in 0..3 {
+            let previous = Hash::digest(blockchain.last_block());
+            let block = transfer(previous, 2, &output, keys);
+            output = first_output(&block);
+            blockchain.push_block(block).unwrap();
+        }
+        let main_hashes: Vec<Hash> = blockchain
+            .blocks()
+            .iter()
+            .map(|b| Hash::digest(b))
+            .collect();
+
+        let timestamp = Utc::now().timestamp() as u64;
+        let key_block = |previous: Hash, leader: &KeyChain, witnesses: &[&KeyChain]| {
+            let base = BaseBlockHeader::new(1, previous, 2, timestamp);
+            let mut witnesses: Vec<_> = witnesses
+                .iter()
+                .map(|keys| keys.cosi_pkey.clone())
+                .collect();
+            witnesses.sort();
+            Block::KeyBlock(KeyBlock::new(base, leader.cosi_pkey.clone(), &witnesses))
+        };
+
+        // A side branch which hands over to another witness: genesis -> b2 -> b3.
+        let b2 = key_block(genesis_hash, &keychains[0], &[&keychains[0], &keychains[1]]);
+        let b2_hash = Hash::digest(&b2);
+        assert!(blockchain.push_block(b2).unwrap().is_empty());
+        let b3 = key_block(b2_hash, &keychains[1], &[&keychains[1]]);
+        let b3_hash = Hash::digest(&b3);
+        assert!(blockchain.push_block(b3).unwrap().is_empty());
+
+        // The leader is a witness of the main chain, but not of the branch.
+        let block = key_block(b3_hash, &keychains[0], &[&keychains[0]]);
+        let block_hash = Hash::digest(&block);
+        match blockchain.push_block(block).unwrap_err().downcast() {
+            Ok(BlockchainError::LeaderIsNotCurrentWitness(hash)) => assert_eq!(hash, block_hash),
+            e => panic!("{:?}", e),
+        }
+        assert!(!blockchain.contains_block(&block_hash));
+
+        // The branch becomes better: genesis -> b2 -> b3 -> b4 -> b5.
+        let b4 = key_block(b3_hash, &keychains[1], &[&keychains[1]]);
+        let b4_hash = Hash::digest(&b4);
+        assert!(blockchain.push_block(b4).unwrap().is_empty());
+        let b5 = key_block(b4_hash, &keychains[1], &[&keychains[1]]);
+        let b5_hash = Hash::digest(&b5);
+        let events = blockchain.push_block(b5).unwrap();
+        assert_eq!(events.len(), 3 + 4);
+        let hashes: Vec<Hash> = blockchain
+            .blocks()
+            .iter()
+            .map(|b| Hash::digest(b))
+            .collect();
+        assert_eq!(&hashes[..2], &main_hashes[..2]);
+        assert_eq!(&hashes[2..], &[b2_hash, b3_hash, b4_hash, b5_hash]);
+    }
+
+    /// Check that an interrupted registration is rolled back on the next start.
+    #[test]
+    fn recovery() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem()];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let codec = MemoryCodec::new();
+        let path = temp_path("recovery");
+
+        let (last_hash, unspent, len) = {
+            let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+            blockchain.register_key_block(key_block).unwrap();
+            blockchain.register_monetary_block(monetary_block).unwrap();
+            iterate(&mut blockchain).unwrap();
+            let last_hash = Hash::digest(blockchain.last_block());
+            let unspent = sorted_unspent(&blockchain);
+            let len = std::fs::metadata(&path).unwrap().len();
+            iterate(&mut blockchain).unwrap();
+            (last_hash, unspent, len)
+        };
+
+        // Simulate a crash in the middle of writing of the last block.
+        let new_len = std::fs::metadata(&path).unwrap().len();
+        assert!(new_len > len);
+        for cut in &[new_len - 1, (len + new_len) / 2, len + 1] {
+            let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_len(*cut).unwrap();
+            drop(file);
+
+            let blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+            assert_eq!(blockchain.blocks().len(), 3);
+            assert_eq!(Hash::digest(blockchain.last_block()), last_hash);
+            assert_eq!(sorted_unspent(&blockchain), unspent);
+        }
+
+        // Continue from the recovered tip.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        iterate(&mut blockchain).unwrap();
+        assert_eq!(blockchain.blocks().len(), 4);
+        drop(blockchain);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Returns hashes and paths of unpruned outputs of a monetary block.
+    fn sorted_leafs(block: &Block) -> Vec<(Hash, u64)> {
+        let mut leafs: Vec<(Hash, u64)> = match block {
+            Block::MonetaryBlock(MonetaryBlock { header: _, body }) => body
+                .outputs
+                .leafs()
+                .iter()
+                .map(|(o, path)| (Hash::digest(*o), path.0 as u64))
+                .collect(),
+            Block::KeyBlock(_) => Vec::new(),
+        };
+        leafs.sort_by_key(|(_hash, path)| *path);
+        leafs
+    }
+
+    #[test]
+    fn unregister() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [
+            KeyChain::new_mem(),
+            KeyChain::new_mem(),
+            KeyChain::new_mem(),
+        ];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let codec = MemoryCodec::new();
+        let path = temp_path("unregister");
+
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+        let genesis_leafs = sorted_leafs(&blockchain.blocks()[1]);
+        let genesis_unspent = sorted_unspent(&blockchain);
+
+        let mut states = Vec::new();
+        for _ in 0..3 {
+            states.push((
+                Hash::digest(blockchain.last_block()),
+                sorted_unspent(&blockchain),
+            ));
+            iterate(&mut blockchain).unwrap();
+        }
+        assert_ne!(sorted_leafs(&blockchain.blocks()[1]), genesis_leafs);
+
+        // Revert one block in memory.
+        let (block, restored) = blockchain.unregister_last_block().unwrap();
+        assert_eq!(restored.len(), 1);
+        assert!(!blockchain.contains_block(&Hash::digest(&block)));
+        let (last_hash, unspent) = states.pop().unwrap();
+        assert_eq!(Hash::digest(blockchain.last_block()), last_hash);
+        assert_eq!(sorted_unspent(&blockchain), unspent);
+        drop(blockchain);
+
+        // Undo data survives restarts.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(blockchain.blocks().len(), 4);
+        while let Some((last_hash, unspent)) = states.pop() {
+            blockchain.unregister_last_block().unwrap();
+            assert_eq!(Hash::digest(blockchain.last_block()), last_hash);
+            assert_eq!(sorted_unspent(&blockchain), unspent);
+            for hash in &unspent {
+                let output = blockchain.output_by_hash(hash).unwrap();
+                assert_eq!(Hash::digest(output), *hash);
+            }
+        }
+
+        // Spent outputs are restored into their original positions.
+        assert_eq!(blockchain.blocks().len(), 2);
+        assert_eq!(sorted_leafs(&blockchain.blocks()[1]), genesis_leafs);
+        assert_eq!(sorted_unspent(&blockchain), genesis_unspent);
+        drop(blockchain);
+
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(sorted_leafs(&blockchain.blocks()[1]), genesis_leafs);
+        blockchain.unregister_last_block().unwrap();
+        blockchain.unregister_last_block().unwrap();
+        assert!(blockchain.blocks().is_empty());
+        assert!(blockchain.unspent().is_empty());
+        drop(blockchain);
+
+        let blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert!(blockchain.blocks().is_empty());
+        drop(blockchain);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Returns the UTXO root committed by the last monetary block.
+    fn last_utxo_root(blockchain: &Blockchain) -> Hash {
+        for block in blockchain.blocks().iter().rev() {
+            if let Block::MonetaryBlock(block) = block {
+                return block.header.utxo_root;
+            }
+        }
+        panic!()
+    }
+
+    #[test]
+    fn utxo() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem(), KeyChain::new_mem()];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let codec = MemoryCodec::new();
+        let path = temp_path("utxo");
+
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+        let genesis_unspent = sorted_unspent(&blockchain);
+        let genesis_root = last_utxo_root(&blockchain);
+        assert_eq!(blockchain.utxo_root(), genesis_root);
+        iterate(&mut blockchain).unwrap();
+        iterate(&mut blockchain).unwrap();
+        let root = last_utxo_root(&blockchain);
+        assert_eq!(blockchain.utxo_root(), root);
+        drop(blockchain);
+
+        // The set of unspent outputs is checked on restart.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(blockchain.utxo_root(), root);
+
+        // Membership and non-membership proofs.
+        let unspent = sorted_unspent(&blockchain);
+        for hash in &unspent {
+            let proof = blockchain.utxo_proof(hash);
+            assert_eq!(proof.verify(&root, hash).unwrap(), true);
+        }
+        for hash in &genesis_unspent {
+            let proof = blockchain.utxo_proof(hash);
+            assert_eq!(proof.verify(&root, hash).unwrap(), unspent.contains(hash));
+            assert!(proof.verify(&genesis_root, hash).is_err());
+        }
+
+        // Revert.
+        blockchain.unregister_last_block().unwrap();
+        assert_eq!(blockchain.utxo_root(), last_utxo_root(&blockchain));
+        blockchain.unregister_last_block().unwrap();
+        assert_eq!(blockchain.utxo_root(), genesis_root);
+        drop(blockchain);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn snapshot() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem(), KeyChain::new_mem()];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let codec = MemoryCodec::new();
+        let path = temp_path("snapshot");
+
+        let mut source = Blockchain::new();
+        source.register_key_block(key_block.clone()).unwrap();
+        source
+            .register_monetary_block(monetary_block.clone())
+            .unwrap();
+        iterate(&mut source).unwrap();
+        let old_snapshot = source.snapshot();
+        iterate(&mut source).unwrap();
+        iterate(&mut source).unwrap();
+        let snapshot = source.snapshot();
+        assert_eq!(snapshot.epoch(), source.last_block().base_header().epoch);
+        let checkpoint = Hash::digest(source.last_block());
+
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+
+        // Unspent outputs must match the UTXO root.
+        let mut invalid = snapshot.clone();
+        if let Block::MonetaryBlock(ref mut block) = invalid.blocks[4] {
+            let (_output, path) = block.body.outputs.leafs()[0];
+            block.body.outputs.prune(&path).unwrap();
+        }
+        match blockchain.restore_snapshot(invalid, &checkpoint) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::InvalidUtxoRoot(..) => {}
+                e => panic!("{}", e),
+            },
+            Ok(_) => panic!(),
+        }
+
+        // Money is minted only by the genesis.
+        let mut invalid = snapshot.clone();
+        if let Block::MonetaryBlock(ref mut block) = invalid.blocks[4] {
+            block.header.monetary_adjustment = 1;
+        }
+        let previous = Hash::digest(&invalid.blocks[3]);
+        match blockchain.restore_snapshot(invalid, &previous) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::InvalidMonetaryAdjustment(_, adjustment) => {
+                    assert_eq!(adjustment, 1)
+                }
+                e => panic!("{}", e),
+            },
+            Ok(_) => panic!(),
+        }
+
+        // The snapshot must start with the local chain.
+        let other = genesis(&keychains[0..1]).0;
+        let mut invalid = snapshot.clone();
+        invalid.blocks[0] = Block::KeyBlock(other);
+        match blockchain.restore_snapshot(invalid, &checkpoint) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::SnapshotMismatch(..) => {}
+                e => panic!("{}", e),
+            },
+            Ok(_) => panic!(),
+        }
+
+        // The snapshot must include the checkpoint.
+        let unknown = Hash::digest(&"unknown".to_string());
+        match blockchain.restore_snapshot(snapshot.clone(), &unknown) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::MissingCheckpoint(hash) => assert_eq!(hash, unknown),
+                e => panic!("{}", e),
+            },
+            Ok(_) => panic!(),
+        }
+        assert_eq!(blockchain.blocks().len(), 2);
+
+        // Restore.
+        let events = blockchain
+            .restore_snapshot(snapshot.clone(), &checkpoint)
+            .unwrap();
+        let summary: Vec<bool> = events_summary(&events).iter().map(|e| e.0).collect();
+        assert_eq!(summary, vec![true, true, true]);
+        // The local chain is kept with undo data.
+        assert!(blockchain.undo.contains_key(&1));
+        // Spent outputs of the local chain are kept in the storage.
+        let genesis_hash = Hash::digest(&blockchain.blocks()[1]);
+        match blockchain.full_block_by_hash(&genesis_hash).unwrap() {
+            Some(Block::MonetaryBlock(block)) => block.validate_range_hashes().unwrap(),
+            _ => panic!(),
+        }
+        match blockchain.block_by_hash(&genesis_hash) {
+            Some(Block::MonetaryBlock(block)) => assert!(block.validate_range_hashes().is_err()),
+            _ => panic!(),
+        }
+        assert_eq!(
+            Hash::digest(blockchain.last_block()),
+            Hash::digest(source.last_block())
+        );
+        assert_eq!(sorted_unspent(&blockchain), sorted_unspent(&source));
+        assert_eq!(blockchain.utxo_root(), source.utxo_root());
+        for hash in &sorted_unspent(&blockchain) {
+            let output = blockchain.output_by_hash(hash).unwrap();
+            assert_eq!(Hash::digest(output), *hash);
+        }
+
+        // Old snapshots are ignored.
+        assert!(blockchain
+            .restore_snapshot(old_snapshot, &checkpoint)
+            .unwrap()
+            .is_empty());
+        drop(blockchain);
+
+        // Restored blocks are persistent and can't be reverted.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(sorted_unspent(&blockchain), sorted_unspent(&source));
+        match blockchain.unregister_last_block() {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::MissingUndoData(..) => {}
+                e => panic!("{}", e),
+            },
+            Ok(_) => panic!(),
+        }
+
+        // Follow new blocks.
+        iterate(&mut source).unwrap();
+        blockchain.register_block(source.last_block().clone()).unwrap();
+        assert_eq!(sorted_unspent(&blockchain), sorted_unspent(&source));
+
+        // Restore a newer snapshot after restart.
+        iterate(&mut source).unwrap();
+        iterate(&mut source).unwrap();
+        let events = blockchain
+            .restore_snapshot(source.snapshot(), &checkpoint)
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            Hash::digest(blockchain.last_block()),
+            Hash::digest(source.last_block())
+        );
+        assert_eq!(sorted_unspent(&blockchain), sorted_unspent(&source));
+        assert_eq!(blockchain.utxo_root(), source.utxo_root());
+        drop(blockchain);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Returns the error of block validation.
+    fn validation_error(blockchain: &Blockchain, block: &Block) -> BlockchainError {
+        let e = blockchain.validate_block(block).unwrap_err();
+        e.downcast::<BlockchainError>().unwrap()
+    }
+
+    #[test]
+    fn block_size() {
+        let keychains = [KeyChain::new_mem()];
+        let (key_block, _monetary_block) = genesis(&keychains);
+        let key_block = Block::KeyBlock(key_block);
+        let hash_size = Hash::digest(&key_block).base_vector().len();
+
+        let path = temp_path("block_size_max");
+        let codec = MemoryCodec::with_padding(MAX_BLOCK_SIZE - hash_size);
+        let blockchain = Blockchain::open(&path, Box::new(codec)).unwrap();
+        blockchain.validate_block(&key_block).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let path = temp_path("block_size_too_large");
+        let codec = MemoryCodec::with_padding(MAX_BLOCK_SIZE - hash_size + 1);
+        let blockchain = Blockchain::open(&path, Box::new(codec)).unwrap();
+        match validation_error(&blockchain, &key_block) {
+            BlockchainError::BlockTooLarge(size, max) => {
+                assert_eq!((size, max), (MAX_BLOCK_SIZE + 1, MAX_BLOCK_SIZE))
+            }
+            e => panic!("{}", e),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn validate_block() {
+        use simple_logger;
+        use stegos_crypto::curve1174::fields::Fr;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem(), KeyChain::new_mem()];
+        let keys = &keychains[0];
+        let (key_block, monetary_block) = genesis(&keychains[0..1]);
+        let genesis_output = first_output(&Block::MonetaryBlock(monetary_block.clone()));
+
+        let mut blockchain = Blockchain::new();
+        let key_block = Block::KeyBlock(key_block);
+        blockchain.validate_block(&key_block).unwrap();
+        blockchain.register_block(key_block).unwrap();
+        let monetary_block = Block::MonetaryBlock(monetary_block);
+        blockchain.validate_block(&monetary_block).unwrap();
+        blockchain.register_block(monetary_block).unwrap();
+        let last_hash = Hash::digest(blockchain.last_block());
+        let last_epoch = blockchain.last_block().base_header().epoch;
+        let last_timestamp = blockchain.last_block().base_header().timestamp;
+
+        // Valid block.
+        let block = transfer(last_hash, last_epoch + 1, &genesis_output, keys);
+        blockchain.validate_block(&block).unwrap();
+
+        // Invalid previous hash.
+        let block = transfer(
+            Hash::digest(&"unknown".to_string()),
+            3,
+            &genesis_output,
+            keys,
+        );
+        match validation_error(&blockchain, &block) {
+            BlockchainError::PreviousHashMismatch(expected, _got) => {
+                assert_eq!(expected, last_hash)
+            }
+            e => panic!("{}", e),
+        }
+
+        // Epoch goes back.
+        let block = transfer(last_hash, last_epoch - 1, &genesis_output, keys);
+        match validation_error(&blockchain, &block) {
+            BlockchainError::OutOfOrderEpoch(_, previous, got) => {
+                assert_eq!((previous, got), (last_epoch, last_epoch - 1))
+            }
+            e => panic!("{}", e),
+        }
+
+        // Timestamp is before the median time past.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        if let Block::MonetaryBlock(ref mut block) = block {
+            block.header.base.timestamp = last_timestamp - 1;
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::TimestampBeforeMedianTime(_, median, got) => {
+                assert_eq!((median, got), (last_timestamp, last_timestamp - 1))
+            }
+            e => panic!("{}", e),
+        }
+
+        // Timestamp is too far in the future.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        let timestamp = Utc::now().timestamp() as u64 + MAX_FUTURE_DRIFT + 60;
+        if let Block::MonetaryBlock(ref mut block) = block {
+            block.header.base.timestamp = timestamp;
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::TimestampInFuture(_, _max, got) => assert_eq!(got, timestamp),
+            e => panic!("{}", e),
+        }
+
+        // Header doesn't match body.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        if let Block::MonetaryBlock(ref mut block) = block {
+            block.header.inputs_range_hash = Hash::digest(&"inputs".to_string());
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::InvalidInputsRangeHash(..) => {}
+            e => panic!("{}", e),
+        }
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        if let Block::MonetaryBlock(ref mut block) = block {
+            block.header.outputs_range_hash = Hash::digest(&"outputs".to_string());
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::InvalidOutputsRangeHash(..) => {}
+            e => panic!("{}", e),
+        }
+
+        // Too many inputs.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        if let Block::MonetaryBlock(ref mut block) = block {
+            block.body.inputs = vec![Hash::digest(&genesis_output); MAX_BLOCK_INPUTS + 1];
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::TooManyInputs(_, count, _max) => {
+                assert_eq!(count, MAX_BLOCK_INPUTS + 1)
+            }
+            e => panic!("{}", e),
+        }
+
+        // The same input twice.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        if let Block::MonetaryBlock(ref mut b) = block {
+            let inputs = [Hash::digest(&genesis_output); 2];
+            let (base, gamma, utxo_root) =
+                (b.header.base.clone(), b.header.gamma, b.header.utxo_root);
+            let outputs: Vec<Output> = b
+                .body
+                .outputs
+                .leafs()
+                .iter()
+                .map(|(o, _)| Output::clone(o))
+                .collect();
+            *b = MonetaryBlock::new(base, gamma, &inputs, &outputs, utxo_root, 0);
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::DuplicateInput(_, hash) => {
+                assert_eq!(hash, Hash::digest(&genesis_output))
+            }
+            e => panic!("{}", e),
+        }
+
+        // Missing input.
+        let (output, _gamma) =
+            Output::new_monetary(last_timestamp, &keys.wallet_skey, &keys.wallet_pkey, 1).unwrap();
+        let block = transfer(last_hash, last_epoch, &output, keys);
+        match validation_error(&blockchain, &block) {
+            BlockchainError::MissingUTXO(hash) => assert_eq!(hash, Hash::digest(&output)),
+            e => panic!("{}", e),
+        }
+
+        // Invalid commitment to UTXO.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        set_utxo_root(&mut block, &[Hash::digest(&genesis_output)]);
+        match validation_error(&blockchain, &block) {
+            BlockchainError::InvalidUtxoRoot(_, expected, _got) => {
+                let output_hash = Hash::digest(&first_output(&block));
+                assert_eq!(expected, UtxoTree::from_hashes(&[output_hash]).roothash());
+            }
+            e => panic!("{}", e),
+        }
+        if let Block::MonetaryBlock(block) = block {
+            let e = blockchain.register_monetary_block(block).unwrap_err();
+            match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::InvalidUtxoRoot(..) => {}
+                e => panic!("{}", e),
+            }
+        }
+
+        // Invalid monetary balance.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        if let Block::MonetaryBlock(ref mut block) = block {
+            block.header.gamma = Fr::zero();
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::InvalidBlockBalance => {}
+            e => panic!("{}", e),
+        }
+
+        // Money is minted after the genesis.
+        let mut block = transfer(last_hash, last_epoch, &genesis_output, keys);
+        if let Block::MonetaryBlock(ref mut block) = block {
+            block.header.monetary_adjustment = 1;
+        }
+        match validation_error(&blockchain, &block) {
+            BlockchainError::InvalidMonetaryAdjustment(_, adjustment) => assert_eq!(adjustment, 1),
+            e => panic!("{}", e),
+        }
+
+        // Leader is not a witness.
+        let base = BaseBlockHeader::new(1, last_hash, last_epoch + 1, last_timestamp);
+        let leader = keychains[1].cosi_pkey.clone();
+        let block = Block::KeyBlock(KeyBlock::new(base.clone(), leader, &[keys.cosi_pkey]));
+        match validation_error(&blockchain, &block) {
+            BlockchainError::LeaderIsNotWitness(..) => {}
+            e => panic!("{}", e),
+        }
+        let witnesses = [keys.cosi_pkey, keychains[1].cosi_pkey];
+        let block = Block::KeyBlock(KeyBlock::new(base.clone(), keys.cosi_pkey, &witnesses));
+        blockchain.validate_block(&block).unwrap();
+
+        // Leader is not a witness of the previous key block.
+        let leader = keychains[1].cosi_pkey.clone();
+        let block = Block::KeyBlock(KeyBlock::new(base, leader, &witnesses));
+        match validation_error(&blockchain, &block) {
+            BlockchainError::LeaderIsNotPreviousWitness(..) => {}
+            e => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn median_time_past() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem()];
+        let keys = &keychains[0];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let mut blockchain = Blockchain::new();
+        assert_eq!(blockchain.median_time_past(), 0);
+        blockchain.push_block(Block::KeyBlock(key_block)).unwrap();
+        blockchain
+            .push_block(Block::MonetaryBlock(monetary_block))
+            .unwrap();
+        let genesis_timestamp = blockchain.last_block().base_header().timestamp;
+        assert_eq!(blockchain.median_time_past(), genesis_timestamp);
+
+        let key_block = |blockchain: &Blockchain, timestamp: u64| {
+            let last = blockchain.last_block();
+            let epoch = last.base_header().epoch + 1;
+            let base = BaseBlockHeader::new(1, Hash::digest(last), epoch, timestamp);
+            Block::KeyBlock(KeyBlock::new(base, keys.cosi_pkey, &[keys.cosi_pkey]))
+        };
+
+        // Timestamps: genesis, genesis, genesis + 1, ..., genesis + 4.
+        for i in 1..5 {
+            let block = key_block(&blockchain, genesis_timestamp + i);
+            blockchain.push_block(block).unwrap();
+        }
+        assert_eq!(blockchain.median_time_past(), genesis_timestamp + 2);
+
+        // A block can be older than the previous block, but not older than the median.
+        let block = key_block(&blockchain, genesis_timestamp + 2);
+        blockchain.validate_block(&block).unwrap();
+        let block = key_block(&blockchain, genesis_timestamp + 1);
+        match validation_error(&blockchain, &block) {
+            BlockchainError::TimestampBeforeMedianTime(_, median, got) => {
+                assert_eq!(
+                    (median, got),
+                    (genesis_timestamp + 2, genesis_timestamp + 1)
+                )
+            }
+            e => panic!("{}", e),
+        }
+
+        // Only the last blocks are used.
+        for _ in 0..MEDIAN_TIME_PAST_BLOCKS {
+            let block = key_block(&blockchain, genesis_timestamp + 10);
+            blockchain.push_block(block).unwrap();
+        }
+        assert_eq!(blockchain.median_time_past(), genesis_timestamp + 10);
+    }
+
+    /// Returns hashes of outputs expired by registered blocks.
+    fn expired_summary(events: &[ChainEvent]) -> Vec<Hash> {
+        let mut hashes = Vec::new();
+        for event in events {
+            if let ChainEvent::Registered { expired, .. } = event {
+                hashes.extend(expired.iter().map(|o| Hash::digest(o)));
+            }
+        }
+        hashes
+    }
+
+    #[test]
+    fn expiry() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem()];
+        let keys = &keychains[0];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let genesis_output = first_output(&Block::MonetaryBlock(monetary_block.clone()));
+        let codec = MemoryCodec::new();
+        let path = temp_path("expiry");
+
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+        let last_hash = Hash::digest(blockchain.last_block());
+
+        // The data output is kept for two epochs.
+        let a = transfer_with_data(last_hash, 2, &genesis_output, keys, 2);
+        let a_hash = Hash::digest(&a);
+        let data_hash = match a {
+            Block::MonetaryBlock(ref block) => Hash::digest(block.body.outputs.leafs()[1].0),
+            _ => unreachable!(),
+        };
+        let events = blockchain.push_block(a.clone()).unwrap();
+        assert!(expired_summary(&events).is_empty());
+        let mut b = transfer(a_hash, 3, &first_output(&a), keys);
+        let b_output_hash = Hash::digest(&first_output(&b));
+        set_utxo_root(&mut b, &[b_output_hash, data_hash]);
+        let b_hash = Hash::digest(&b);
+        let events = blockchain.push_block(b.clone()).unwrap();
+        assert!(expired_summary(&events).is_empty());
+        assert!(blockchain.output_by_hash(&data_hash).is_some());
+
+        // Another block of the same epoch doesn't expire the output.
+        let mut b = transfer(b_hash, 3, &first_output(&b), keys);
+        let b_output_hash = Hash::digest(&first_output(&b));
+        set_utxo_root(&mut b, &[b_output_hash, data_hash]);
+        let b_hash = Hash::digest(&b);
+        let events = blockchain.push_block(b.clone()).unwrap();
+        assert!(expired_summary(&events).is_empty());
+        assert!(blockchain.output_by_hash(&data_hash).is_some());
+        drop(blockchain);
+
+        // Expiration is resumed after restart.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        let c = transfer(b_hash, 4, &first_output(&b), keys);
+        let events = blockchain.push_block(c.clone()).unwrap();
+        assert_eq!(expired_summary(&events), vec![data_hash]);
+        assert!(blockchain.output_by_hash(&data_hash).is_none());
+        assert_eq!(blockchain.audit_supply().unwrap(), 1_000_000);
+        assert_eq!(blockchain.unspent(), vec![Hash::digest(&first_output(&c))]);
+        drop(blockchain);
+
+        // Expired outputs are restored by revert.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert!(blockchain.output_by_hash(&data_hash).is_none());
+        let (_block, restored) = blockchain.unregister_last_block().unwrap();
+        let mut restored: Vec<Hash> = restored.iter().map(|o| Hash::digest(o)).collect();
+        restored.sort_by(|a, b| a.base_vector().cmp(b.base_vector()));
+        let mut expected = vec![Hash::digest(&first_output(&b)), data_hash];
+        expected.sort_by(|a, b| a.base_vector().cmp(b.base_vector()));
+        assert_eq!(restored, expected);
+        assert_eq!(
+            Hash::digest(blockchain.output_by_hash(&data_hash).unwrap()),
+            data_hash
+        );
+        drop(blockchain);
+
+        // And expire again.
+        let mut blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert!(blockchain.output_by_hash(&data_hash).is_some());
+        let events = blockchain.push_block(c.clone()).unwrap();
+        assert_eq!(expired_summary(&events), vec![data_hash]);
+        drop(blockchain);
+
+        // Expired outputs are audited after restart.
+        let blockchain = Blockchain::open(&path, Box::new(codec.clone())).unwrap();
+        assert_eq!(blockchain.audit_supply().unwrap(), 1_000_000);
+
+        // Expired outputs are audited after fast sync.
+        let snapshot = blockchain.snapshot();
+        let checkpoint = Hash::digest(blockchain.last_block());
+        let mut restored = Blockchain::new();
+        restored
+            .restore_snapshot(snapshot.clone(), &checkpoint)
+            .unwrap();
+        assert_eq!(restored.audit_supply().unwrap(), 1_000_000);
+        let mut invalid = snapshot;
+        invalid.expired.clear();
+        let mut restored = Blockchain::new();
+        restored.restore_snapshot(invalid, &checkpoint).unwrap();
+        let e = restored.audit_supply().unwrap_err();
+        match e.downcast::<BlockchainError>().unwrap() {
+            BlockchainError::SupplyMismatch(supply) => assert_eq!(supply, 1_000_000),
+            e => panic!("{}", e),
+        }
+        drop(blockchain);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn audit_supply() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem()];
+        let keys = &keychains[0];
+        let (key_block, monetary_block) = genesis(&keychains);
+        let genesis_output = first_output(&Block::MonetaryBlock(monetary_block.clone()));
+
+        let mut blockchain = Blockchain::new();
+        blockchain.push_block(Block::KeyBlock(key_block)).unwrap();
+        blockchain
+            .push_block(Block::MonetaryBlock(monetary_block))
+            .unwrap();
+        assert_eq!(blockchain.audit_supply().unwrap(), 1_000_000);
+        let last_hash = Hash::digest(blockchain.last_block());
+        let block = transfer(last_hash, 2, &genesis_output, keys);
+        blockchain.push_block(block).unwrap();
+        assert_eq!(blockchain.audit_supply().unwrap(), 1_000_000);
+
+        // Blocks of a snapshot don't have undo data, but can be audited.
+        let mut restored = Blockchain::new();
+        let checkpoint = Hash::digest(blockchain.last_block());
+        restored
+            .restore_snapshot(blockchain.snapshot(), &checkpoint)
+            .unwrap();
+        assert_eq!(restored.audit_supply().unwrap(), 1_000_000);
+
+        // Money created out of thin air is detected.
+        let input = first_output(blockchain.last_block());
+        let timestamp = Utc::now().timestamp() as u64;
+        let base = BaseBlockHeader::new(1, Hash::digest(blockchain.last_block()), 3, timestamp);
+        let (_delta, gamma0, amount) = match input {
+            Output::MonetaryOutput(ref o) => o.decrypt_payload(&keys.wallet_skey).unwrap(),
+            _ => unreachable!(),
+        };
+        let skey = &keys.wallet_skey;
+        let pkey = &keys.wallet_pkey;
+        let (output, gamma1) = Output::new_monetary(timestamp, skey, pkey, amount + 100).unwrap();
+        let utxo_root = UtxoTree::from_hashes(&[Hash::digest(&output)]).roothash();
+        let inputs = [Hash::digest(&input)];
+        let block = MonetaryBlock::new(base, gamma0 - gamma1, &inputs, &[output], utxo_root, 0);
+        // register_monetary_block() doesn't check the monetary balance.
+        blockchain.register_monetary_block(block).unwrap();
+        let e = blockchain.audit_supply().unwrap_err();
+        match e.downcast::<BlockchainError>().unwrap() {
+            BlockchainError::SupplyMismatch(supply) => assert_eq!(supply, 1_000_000),
+            e => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn headers_by_epoch() {
+        use simple_logger;
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [KeyChain::new_mem()];
+        let (key_block, monetary_block) = genesis(&keychains);
+
+        let mut blockchain = Blockchain::new();
+        blockchain.register_key_block(key_block).unwrap();
+        blockchain.register_monetary_block(monetary_block).unwrap();
+        iterate(&mut blockchain).unwrap();
+        iterate(&mut blockchain).unwrap();
+        iterate(&mut blockchain).unwrap();
+
+        let hashes = |headers: Vec<BlockHeader>| -> Vec<Hash> {
+            headers.iter().map(|h| Hash::digest(h)).collect()
+        };
+        let block_hashes = |range: std::ops::Range<usize>| -> Vec<Hash> {
+            blockchain.blocks()[range]
+                .iter()
+                .map(|b| Hash::digest(b))
+                .collect()
+        };
+
+        // Both genesis blocks have epoch 1.
+        assert_eq!(
+            hashes(blockchain.headers_by_epoch(1, 1, 10)),
+            block_hashes(0..2)
+        );
+        assert_eq!(
+            hashes(blockchain.headers_by_epoch(3, 4, 10)),
+            block_hashes(3..5)
+        );
+        assert_eq!(
+            hashes(blockchain.headers_by_epoch(0, 100, 3)),
+            block_hashes(0..3)
+        );
+        assert!(blockchain.headers_by_epoch(5, 100, 10).is_empty());
+        assert!(blockchain.headers_by_epoch(4, 3, 10).is_empty());
     }
 }
@@ -38,12 +38,118 @@ pub enum BlockchainError {
     OutputHashCollision(Hash),
     #[fail(display = "Missing UXTO {}.", _0)]
     MissingUTXO(Hash),
+    #[fail(display = "UXTO is spent twice: block={}, utxo={}.", _0, _1)]
+    DuplicateInput(Hash, Hash),
     #[fail(display = "Invalid transaction signature.")]
     InvalidTransactionSignature,
     #[fail(display = "Invalid transaction monetary balance.")]
     InvalidTransactionBalance,
     #[fail(display = "Invalid block monetary balance.")]
     InvalidBlockBalance,
+    #[fail(
+        display = "Only the genesis block can adjust the money supply: block={}, adjustment={}.",
+        _0,
+        _1
+    )]
+    InvalidMonetaryAdjustment(Hash, i64),
+    #[fail(display = "Unspent outputs don't match the total supply {}.", _0)]
+    SupplyMismatch(i64),
+    #[fail(
+        display = "Too many inputs: hash={}, count={}, max={}.",
+        _0,
+        _1,
+        _2
+    )]
+    TooManyInputs(Hash, usize, usize),
+    #[fail(
+        display = "Too many outputs: hash={}, count={}, max={}.",
+        _0,
+        _1,
+        _2
+    )]
+    TooManyOutputs(Hash, usize, usize),
+    #[fail(display = "Block is too large: size={}, max={}.", _0, _1)]
+    BlockTooLarge(usize, usize),
+    #[fail(display = "Transaction is too large: size={}, max={}.", _0, _1)]
+    TransactionTooLarge(usize, usize),
     #[fail(display = "Invalid UTXO bulletproof.")]
     InvalidBulletProof,
+    #[fail(
+        display = "Out-of-order epoch: block={}, previous={}, got={}.",
+        _0,
+        _1,
+        _2
+    )]
+    OutOfOrderEpoch(Hash, u64, u64),
+    #[fail(
+        display = "Epoch of a side block is too far ahead: block={}, max={}, got={}.",
+        _0,
+        _1,
+        _2
+    )]
+    SideBlockEpochTooFar(Hash, u64, u64),
+    #[fail(
+        display = "Reorganization is too deep: block={}, depth={}, max={}.",
+        _0,
+        _1,
+        _2
+    )]
+    ReorganizationTooDeep(Hash, usize, usize),
+    #[fail(
+        display = "Timestamp is before the median time past: block={}, median={}, got={}.",
+        _0,
+        _1,
+        _2
+    )]
+    TimestampBeforeMedianTime(Hash, u64, u64),
+    #[fail(
+        display = "Timestamp is too far in the future: block={}, max={}, got={}.",
+        _0,
+        _1,
+        _2
+    )]
+    TimestampInFuture(Hash, u64, u64),
+    #[fail(
+        display = "Invalid inputs range hash: block={}, expected={}, got={}.",
+        _0,
+        _1,
+        _2
+    )]
+    InvalidInputsRangeHash(Hash, Hash, Hash),
+    #[fail(
+        display = "Invalid outputs range hash: block={}, expected={}, got={}.",
+        _0,
+        _1,
+        _2
+    )]
+    InvalidOutputsRangeHash(Hash, Hash, Hash),
+    #[fail(display = "Invalid Merkle Tree of outputs: block={}.", _0)]
+    InvalidOutputsTree(Hash),
+    #[fail(display = "Leader is not a witness: block={}.", _0)]
+    LeaderIsNotWitness(Hash),
+    #[fail(display = "Leader is not a witness of the previous epoch: block={}.", _0)]
+    LeaderIsNotPreviousWitness(Hash),
+    #[fail(display = "Leader is not a witness of the current epoch: block={}.", _0)]
+    LeaderIsNotCurrentWitness(Hash),
+    #[fail(display = "Unsorted or duplicate witnesses: block={}.", _0)]
+    InvalidWitnesses(Hash),
+    #[fail(
+        display = "Invalid UTXO root: block={}, expected={}, got={}.",
+        _0,
+        _1,
+        _2
+    )]
+    InvalidUtxoRoot(Hash, Hash, Hash),
+    #[fail(display = "Invalid UTXO proof for {}.", _0)]
+    InvalidUtxoProof(Hash),
+    #[fail(display = "Snapshot doesn't match the local block {}.", _0)]
+    SnapshotMismatch(Hash),
+    #[fail(display = "Snapshot doesn't include the checkpoint {}.", _0)]
+    MissingCheckpoint(Hash),
+    #[fail(display = "Missing undo data for block {}.", _0)]
+    MissingUndoData(Hash),
+    #[fail(display = "Corrupted storage: {}.", _0)]
+    CorruptedStorage(String),
+    #[fail(display = "Invalid chain specification: {}.", _0)]
+    InvalidChainSpec(String),
 }
@@ -23,57 +23,312 @@
 
 use chrono::prelude::Utc;
 use crate::block::*;
+use crate::error::*;
 use crate::output::*;
+use crate::utxo::*;
+use failure::Error;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use stegos_crypto::curve1174::cpt::{PublicKey, SecretKey};
+use stegos_crypto::curve1174::fields::Fr;
 use stegos_crypto::hash::Hash;
 use stegos_crypto::pbc::secure as cosi_keys;
 use stegos_keychain::KeyChain;
 
-/// Genesis blocks.
+/// Initial money of a recipient, see ChainSpec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    /// Wallet public key of the recipient.
+    pub recipient: PublicKey,
+    /// Amount of money.
+    pub amount: i64,
+}
+
+/// Chain Specification, the parameters of genesis blocks.
+///
+/// The same specification always produces the same genesis blocks, see genesis().
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainSpec {
+    /// Version of genesis blocks.
+    pub version: u64,
+    /// Timestamp of genesis blocks.
+    pub timestamp: u64,
+    /// CoSi public keys of initial witnesses, the first one is the leader.
+    pub witnesses: Vec<cosi_keys::PublicKey>,
+    /// Initial money supply.
+    pub allocations: Vec<Allocation>,
+}
+
+/// Chain specification file, keys are in hex.
+#[derive(Serialize, Deserialize, Debug)]
+struct ChainSpecFile {
+    version: u64,
+    timestamp: u64,
+    witnesses: Vec<String>,
+    allocations: Vec<AllocationFile>,
+}
+
+/// Allocation in chain specification file.
+#[derive(Serialize, Deserialize, Debug)]
+struct AllocationFile {
+    recipient: String,
+    amount: i64,
+}
+
+impl ChainSpec {
+    /// Parse a chain specification in TOML format.
+    pub fn from_toml(s: &str) -> Result<ChainSpec, Error> {
+        let file: ChainSpecFile =
+            toml::from_str(s).map_err(|e| BlockchainError::InvalidChainSpec(e.to_string()))?;
+
+        let mut witnesses = Vec::with_capacity(file.witnesses.len());
+        for witness in &file.witnesses {
+            let witness = cosi_keys::PublicKey::try_from_hex(witness)
+                .map_err(|e| BlockchainError::InvalidChainSpec(e.to_string()))?;
+            witnesses.push(witness);
+        }
+
+        let mut allocations = Vec::with_capacity(file.allocations.len());
+        for allocation in &file.allocations {
+            let recipient = PublicKey::try_from_hex(&allocation.recipient)
+                .map_err(|e| BlockchainError::InvalidChainSpec(e.to_string()))?;
+            let amount = allocation.amount;
+            allocations.push(Allocation { recipient, amount });
+        }
+
+        let spec = ChainSpec {
+            version: file.version,
+            timestamp: file.timestamp,
+            witnesses,
+            allocations,
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// Load a chain specification from a file in TOML format.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ChainSpec, Error> {
+        let contents = fs::read_to_string(path)?;
+        ChainSpec::from_toml(&contents)
+    }
+
+    /// Serialize the chain specification to TOML format.
+    pub fn to_toml(&self) -> String {
+        let file = ChainSpecFile {
+            version: self.version,
+            timestamp: self.timestamp,
+            witnesses: self.witnesses.iter().map(|w| w.into_hex()).collect(),
+            allocations: self
+                .allocations
+                .iter()
+                .map(|a| AllocationFile {
+                    recipient: a.recipient.into_hex(),
+                    amount: a.amount,
+                })
+                .collect(),
+        };
+        toml::to_string(&file).expect("chain spec is serializable")
+    }
+
+    /// Check that witnesses are unique, amounts are positive and recipients are unique.
+    pub fn validate(&self) -> Result<(), BlockchainError> {
+        if self.witnesses.is_empty() {
+            let msg = "no witnesses".to_string();
+            return Err(BlockchainError::InvalidChainSpec(msg));
+        }
+        let mut witnesses = self.witnesses.clone();
+        witnesses.sort();
+        witnesses.dedup();
+        if witnesses.len() != self.witnesses.len() {
+            let msg = "duplicate witnesses".to_string();
+            return Err(BlockchainError::InvalidChainSpec(msg));
+        }
+
+        // Outputs for the same recipient would have the same hash.
+        let mut recipients = HashSet::<PublicKey>::new();
+        let mut supply: i64 = 0;
+        for allocation in &self.allocations {
+            if !recipients.insert(allocation.recipient) {
+                let msg = format!("duplicate recipient {}", allocation.recipient);
+                return Err(BlockchainError::InvalidChainSpec(msg));
+            }
+            supply = match supply.checked_add(allocation.amount) {
+                Some(supply) if allocation.amount > 0 => supply,
+                _ => {
+                    let msg = format!("invalid amount {}", allocation.amount);
+                    return Err(BlockchainError::InvalidChainSpec(msg));
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// Create genesis blocks.
+    ///
+    /// Blocks don't depend on PRNG and the current time, so all nodes which use
+    /// the same specification get the same genesis blocks.
+    ///
+    pub fn genesis(&self) -> Result<(KeyBlock, MonetaryBlock), Error> {
+        self.validate()?;
+
+        // Both block are created at the same time in the same epoch.
+        let version = self.version;
+        let epoch: u64 = 1;
+        let timestamp = self.timestamp;
+
+        //
+        // Create initial Key Block.
+        //
+        let block1 = {
+            let previous = Hash::digest(&"genesis".to_string());
+            let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
+            let leader = self.witnesses[0].clone();
+            KeyBlock::new(base, leader, &self.witnesses)
+        };
+
+        //
+        // Create initial Monetary Block.
+        //
+        let block2 = {
+            let previous = Hash::digest(&block1);
+            let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
+
+            // Genesis doesn't have inputs
+            let inputs = Vec::<Hash>::new();
+
+            // Money is sent by a well-known key derived from the key block.
+            let sender_skey = Fr::synthetic_random("Genesis", &version, &previous);
+            let sender_skey = SecretKey::from(sender_skey);
+
+            // The initial supply is minted by the block, see MonetaryBlock::validate().
+            let mut gamma = Fr::zero();
+            let mut monetary_adjustment: i64 = 0;
+            let mut outputs = Vec::<Output>::with_capacity(self.allocations.len());
+            for allocation in &self.allocations {
+                let (output, output_gamma) = MonetaryOutput::new_deterministic(
+                    timestamp,
+                    &sender_skey,
+                    &allocation.recipient,
+                    allocation.amount,
+                )?;
+                gamma -= output_gamma;
+                monetary_adjustment += allocation.amount;
+                outputs.push(Output::MonetaryOutput(output));
+            }
+
+            let output_hashes: Vec<Hash> = outputs.iter().map(|o| Hash::digest(o)).collect();
+            let utxo_root = UtxoTree::from_hashes(&output_hashes).roothash();
+
+            MonetaryBlock::new(
+                base,
+                gamma,
+                &inputs,
+                &outputs,
+                utxo_root,
+                monetary_adjustment,
+            )
+        };
+
+        Ok((block1, block2))
+    }
+}
+
+/// Genesis blocks for tests and development networks.
+///
+/// Keychains are initial witnesses and the first one gets all money.
+///
 pub fn genesis(keychains: &[KeyChain]) -> (KeyBlock, MonetaryBlock) {
-    // Both block are created at the same time in the same epoch.
-    let version: u64 = 1;
-    let epoch: u64 = 1;
-    let timestamp = Utc::now().timestamp() as u64;;
-
-    //
-    // Create initial Key Block.
-    //
-    let block1 = {
-        let previous = Hash::digest(&"genesis".to_string());
-        let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
-
-        let witnesses = keychains
-            .iter()
-            .map(|p| p.cosi_pkey.clone())
-            .collect::<Vec<cosi_keys::PublicKey>>();
-        let leader = witnesses[0].clone();
-
-        KeyBlock::new(base, leader, &witnesses)
+    let spec = ChainSpec {
+        version: 1,
+        timestamp: Utc::now().timestamp() as u64,
+        witnesses: keychains.iter().map(|k| k.cosi_pkey.clone()).collect(),
+        allocations: vec![Allocation {
+            recipient: keychains[0].wallet_pkey,
+            amount: 1_000_000,
+        }],
     };
+    spec.genesis().expect("keychains are valid")
+}
 
-    //
-    // Create initial Monetary Block.
-    //
-    let block2 = {
-        let previous = Hash::digest(&block1);
-        let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
-        let amount: i64 = 1_000_000;
+#[cfg(test)]
+pub mod tests {
+    use super::*;
 
-        // Genesis doesn't have inputs
-        let inputs = Vec::<Hash>::new();
+    #[test]
+    fn chain_spec() {
+        let keychains = [KeyChain::new_mem(), KeyChain::new_mem()];
+        let spec = ChainSpec {
+            version: 1,
+            timestamp: 1_546_300_800,
+            witnesses: vec![keychains[1].cosi_pkey, keychains[0].cosi_pkey],
+            allocations: vec![
+                Allocation {
+                    recipient: keychains[0].wallet_pkey,
+                    amount: 1_000_000,
+                },
+                Allocation {
+                    recipient: keychains[1].wallet_pkey,
+                    amount: 500,
+                },
+            ],
+        };
 
-        // Genesis block have one hard-coded output.
+        let spec2 = ChainSpec::from_toml(&spec.to_toml()).unwrap();
+        assert_eq!(spec, spec2);
 
-        // Send money to yourself.
-        let sender_skey = &keychains[0].wallet_skey;
-        let recipient_pkey = &keychains[0].wallet_pkey;
+        // Genesis blocks are deterministic.
+        let (key_block, monetary_block) = spec.genesis().unwrap();
+        let (key_block2, monetary_block2) = spec2.genesis().unwrap();
+        assert_eq!(Hash::digest(&key_block), Hash::digest(&key_block2));
+        assert_eq!(
+            Hash::digest(&monetary_block),
+            Hash::digest(&monetary_block2)
+        );
+        assert_eq!(
+            monetary_block.body.outputs.roothash(),
+            monetary_block2.body.outputs.roothash()
+        );
+        assert_eq!(key_block.header.leader, keychains[1].cosi_pkey);
+        assert_eq!(key_block.header.base.timestamp, spec.timestamp);
 
-        let (output, gamma) = Output::new_monetary(timestamp, sender_skey, recipient_pkey, amount)
-            .expect("genesis has valid public keys");
-        let outputs = [output];
+        // The initial supply is minted by the monetary block.
+        assert_eq!(monetary_block.header.monetary_adjustment, 1_000_500);
+        monetary_block.validate(&[]).unwrap();
 
-        MonetaryBlock::new(base, gamma, &inputs, &outputs)
-    };
+        // Recipients get their money.
+        let outputs = monetary_block.body.outputs.leafs();
+        assert_eq!(outputs.len(), 2);
+        for (allocation, keychain) in spec.allocations.iter().zip(keychains.iter()) {
+            let amount = outputs
+                .iter()
+                .filter_map(|(output, _path)| match ***output {
+                    Output::MonetaryOutput(ref o) => o.decrypt_payload(&keychain.wallet_skey).ok(),
+                    Output::DataOutput(_) => None,
+                })
+                .map(|(_delta, _gamma, amount)| amount)
+                .next()
+                .unwrap();
+            assert_eq!(amount, allocation.amount);
+        }
 
-    (block1, block2)
+        // Invalid specifications.
+        let mut invalid = spec.clone();
+        invalid.witnesses.clear();
+        assert!(invalid.genesis().is_err());
+        let mut invalid = spec.clone();
+        invalid.witnesses.push(keychains[0].cosi_pkey);
+        assert!(invalid.genesis().is_err());
+        let mut invalid = spec.clone();
+        invalid.allocations[1].recipient = keychains[0].wallet_pkey;
+        assert!(invalid.genesis().is_err());
+        let mut invalid = spec.clone();
+        invalid.allocations[1].amount = 0;
+        assert!(invalid.genesis().is_err());
+        let mut invalid = spec.clone();
+        invalid.allocations[1].amount = i64::max_value();
+        assert!(invalid.genesis().is_err());
+        assert!(ChainSpec::from_toml("version = 1").is_err());
+    }
 }
@@ -25,16 +25,22 @@ mod block;
 mod blockchain;
 mod error;
 mod genesis;
+mod light;
 mod merkle;
 mod output;
+mod store;
 mod transaction;
+mod utxo;
 
 pub use crate::block::*;
 pub use crate::blockchain::*;
 pub use crate::error::*;
 pub use crate::genesis::*;
+pub use crate::light::*;
 pub use crate::merkle::*;
 pub use crate::output::*;
+pub use crate::store::BlockCodec;
 pub use crate::transaction::*;
+pub use crate::utxo::*;
 
 use log;
@@ -0,0 +1,334 @@
+//! Light Client.
+
+//
+// Copyright (c) 2018 Stegos
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::block::*;
+use crate::blockchain::{
+    median_time_past, validate_key_block, validate_linkage, validate_timestamp,
+};
+use crate::error::*;
+use crate::output::*;
+use crate::utxo::*;
+use chrono::Utc;
+use failure::Error;
+use log::*;
+use std::collections::HashMap;
+use stegos_crypto::hash::Hash;
+use stegos_crypto::pbc::secure::PublicKey as SecurePublicKey;
+
+/// The main chain of block headers for clients which don't keep the full blockchain.
+///
+/// Headers are checked in the same way as blocks are checked by Blockchain, except checks
+/// which need bodies of monetary blocks. Unspent outputs are checked using UtxoProof
+/// from a full node against the UTXO root of the last monetary block.
+///
+pub struct LightChain {
+    /// Headers of the main chain, starting from the genesis key block.
+    headers: Vec<BlockHeader>,
+    /// Position of headers by hash.
+    header_by_hash: HashMap<Hash, usize>,
+    /// The position of the last key block.
+    last_key_block: usize,
+    /// The position of the last monetary block, None if there are no monetary blocks.
+    last_monetary_block: Option<usize>,
+}
+
+impl LightChain {
+    /// Create a chain from the trusted genesis key block.
+    pub fn new(genesis: KeyBlockHeader) -> Result<LightChain, Error> {
+        let header = BlockHeader::KeyBlockHeader(genesis);
+        let header_hash = Hash::digest(&header);
+        if let BlockHeader::KeyBlockHeader(ref genesis) = header {
+            validate_key_block(&header_hash, genesis, None)?;
+        }
+        let mut header_by_hash = HashMap::new();
+        header_by_hash.insert(header_hash, 0);
+        Ok(LightChain {
+            headers: vec![header],
+            header_by_hash,
+            last_key_block: 0,
+            last_monetary_block: None,
+        })
+    }
+
+    /// Returns the number of headers in the chain.
+    pub fn height(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Returns the epoch of the last header.
+    pub fn epoch(&self) -> u64 {
+        self.last_header().base_header().epoch
+    }
+
+    /// Returns headers of the main chain.
+    pub fn headers(&self) -> &[BlockHeader] {
+        &self.headers
+    }
+
+    /// Returns the last header.
+    pub fn last_header(&self) -> &BlockHeader {
+        self.headers.last().expect("the chain has the genesis")
+    }
+
+    /// Return a header by hash.
+    pub fn header_by_hash(&self, header_hash: &Hash) -> Option<&BlockHeader> {
+        let id = self.header_by_hash.get(header_hash)?;
+        Some(&self.headers[*id])
+    }
+
+    /// Check that a header is in the main chain.
+    pub fn contains_header(&self, header_hash: &Hash) -> bool {
+        self.header_by_hash.contains_key(header_hash)
+    }
+
+    /// Returns the current leader.
+    pub fn leader(&self) -> &SecurePublicKey {
+        &self.last_key_block_header().leader
+    }
+
+    /// Returns the current list of witnesses.
+    pub fn witnesses(&self) -> &[SecurePublicKey] {
+        &self.last_key_block_header().witnesses
+    }
+
+    /// Returns the UTXO root of the last monetary block, Hash::zero() if there are none.
+    pub fn utxo_root(&self) -> Hash {
+        match self.last_monetary_block {
+            Some(id) => match self.headers[id] {
+                BlockHeader::MonetaryBlockHeader(ref header) => header.utxo_root,
+                BlockHeader::KeyBlockHeader(_) => unreachable!(),
+            },
+            None => Hash::zero(),
+        }
+    }
+
+    /// Add a header to the end of the main chain.
+    ///
+    /// Checks the linkage with the last header, epoch and timestamp,
+    /// witnesses of key blocks and the transition of the leader.
+    ///
+    pub fn push_header(&mut self, header: BlockHeader) -> Result<(), Error> {
+        let header_hash = Hash::digest(&header);
+        if self.header_by_hash.contains_key(&header_hash) {
+            return Err(BlockchainError::BlockHashCollision(header_hash).into());
+        }
+
+        let last = self.last_header();
+        let previous = Some((Hash::digest(last), last.base_header()));
+        validate_linkage(previous, &header_hash, header.base_header())?;
+        let median_time = median_time_past(self.headers.iter().rev().map(|h| h.base_header()));
+        let now = Utc::now().timestamp() as u64;
+        validate_timestamp(&header_hash, header.base_header(), median_time, now)?;
+
+        // TODO: check CoSi multisignature when it is added to BaseBlockHeader.
+
+        let id = self.headers.len();
+        match header {
+            BlockHeader::KeyBlockHeader(ref key_block_header) => {
+                let previous = self.last_key_block_header();
+                validate_key_block(&header_hash, key_block_header, Some(previous))?;
+                info!(
+                    "Register Key Block Header: hash={}, leader={}",
+                    header_hash, key_block_header.leader
+                );
+                self.last_key_block = id;
+            }
+            BlockHeader::MonetaryBlockHeader(_) => {
+                info!("Register Monetary Block Header: hash={}", header_hash);
+                self.last_monetary_block = Some(id);
+            }
+        }
+
+        self.header_by_hash.insert(header_hash, id);
+        self.headers.push(header);
+        Ok(())
+    }
+
+    /// Remove the last header from the main chain, e.g. to switch to another fork.
+    ///
+    /// Returns None if only the genesis is left.
+    ///
+    pub fn pop_header(&mut self) -> Option<BlockHeader> {
+        if self.headers.len() == 1 {
+            return None;
+        }
+        let header = self.headers.pop().expect("the chain is not empty");
+        let header_hash = Hash::digest(&header);
+        info!("Revert Block Header: hash={}", header_hash);
+        self.header_by_hash.remove(&header_hash);
+
+        let headers = &self.headers;
+        self.last_key_block = (0..headers.len())
+            .rev()
+            .find(|id| match headers[*id] {
+                BlockHeader::KeyBlockHeader(_) => true,
+                BlockHeader::MonetaryBlockHeader(_) => false,
+            })
+            .expect("the genesis is a key block");
+        self.last_monetary_block = (0..headers.len()).rev().find(|id| match headers[*id] {
+            BlockHeader::KeyBlockHeader(_) => false,
+            BlockHeader::MonetaryBlockHeader(_) => true,
+        });
+        Some(header)
+    }
+
+    /// Check that an output is unspent, see Blockchain::utxo_proof().
+    ///
+    /// Returns true if the output is unspent, false if the output is spent or unknown
+    /// and an error if the proof doesn't match the last monetary block.
+    ///
+    pub fn verify_output(&self, output: &Output, proof: &UtxoProof) -> Result<bool, Error> {
+        let output_hash = Hash::digest(output);
+        Ok(proof.verify(&self.utxo_root(), &output_hash)?)
+    }
+
+    /// Returns the header of the last key block.
+    fn last_key_block_header(&self) -> &KeyBlockHeader {
+        match self.headers[self.last_key_block] {
+            BlockHeader::KeyBlockHeader(ref header) => header,
+            BlockHeader::MonetaryBlockHeader(_) => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::genesis::genesis;
+    use simple_logger;
+    use stegos_crypto::curve1174::cpt::make_random_keys;
+    use stegos_keychain::KeyChain;
+
+    #[test]
+    fn light_chain() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let keychains = [
+            KeyChain::new_mem(),
+            KeyChain::new_mem(),
+            KeyChain::new_mem(),
+        ];
+        let (key_block, monetary_block) = genesis(&keychains[0..2]);
+        let mut blockchain = Blockchain::new();
+        blockchain.register_key_block(key_block.clone()).unwrap();
+        blockchain
+            .register_monetary_block(monetary_block.clone())
+            .unwrap();
+
+        let mut chain = LightChain::new(key_block.header.clone()).unwrap();
+        assert_eq!(chain.height(), 1);
+        assert_eq!(chain.utxo_root(), Hash::zero());
+        assert_eq!(chain.leader(), &keychains[0].cosi_pkey);
+
+        // Monetary block.
+        let header = Block::MonetaryBlock(monetary_block).header();
+        let header_hash = Hash::digest(&header);
+        chain.push_header(header.clone()).unwrap();
+        assert!(chain.contains_header(&header_hash));
+        assert_eq!(chain.utxo_root(), blockchain.utxo_root());
+        match chain.push_header(header) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::BlockHashCollision(hash) => assert_eq!(hash, header_hash),
+                e => panic!("{}", e),
+            },
+            _ => panic!(),
+        }
+
+        // Unspent outputs.
+        for output_hash in blockchain.unspent() {
+            let output = blockchain.output_by_hash(&output_hash).unwrap();
+            let proof = blockchain.utxo_proof(&output_hash);
+            assert!(chain.verify_output(output, &proof).unwrap());
+        }
+        let timestamp = Utc::now().timestamp() as u64;
+        let (skey, pkey, _sig) = make_random_keys();
+        let (output, _gamma) = Output::new_monetary(timestamp, &skey, &pkey, 1).unwrap();
+        let proof = blockchain.utxo_proof(&Hash::digest(&output));
+        assert!(!chain.verify_output(&output, &proof).unwrap());
+        let proof = UtxoTree::from_hashes(&[Hash::digest(&output)]).proof(&Hash::digest(&output));
+        match chain.verify_output(&output, &proof) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::InvalidUtxoProof(_) => {}
+                e => panic!("{}", e),
+            },
+            _ => panic!(),
+        }
+
+        // Linkage.
+        let previous = Hash::digest(chain.last_header());
+        let epoch = chain.epoch() + 1;
+        let base = BaseBlockHeader::new(1, Hash::digest(&"unknown".to_string()), epoch, timestamp);
+        let leader = keychains[1].cosi_pkey;
+        let mut witnesses = vec![keychains[1].cosi_pkey, keychains[2].cosi_pkey];
+        witnesses.sort();
+        let header = KeyBlock::new(base, leader, &witnesses).header;
+        match chain.push_header(BlockHeader::KeyBlockHeader(header)) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::PreviousHashMismatch(..) => {}
+                e => panic!("{}", e),
+            },
+            _ => panic!(),
+        }
+
+        // The leader must be a witness of the previous epoch.
+        let base = BaseBlockHeader::new(1, previous, epoch, timestamp);
+        let leader = keychains[2].cosi_pkey;
+        let header = KeyBlock::new(base.clone(), leader, &witnesses).header;
+        match chain.push_header(BlockHeader::KeyBlockHeader(header)) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::LeaderIsNotPreviousWitness(..) => {}
+                e => panic!("{}", e),
+            },
+            _ => panic!(),
+        }
+        let leader = keychains[1].cosi_pkey;
+        let header = KeyBlock::new(base.clone(), leader, &[keychains[2].cosi_pkey]).header;
+        match chain.push_header(BlockHeader::KeyBlockHeader(header)) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::LeaderIsNotWitness(..) => {}
+                e => panic!("{}", e),
+            },
+            _ => panic!(),
+        }
+
+        // A valid transition.
+        let header = KeyBlock::new(base, leader, &witnesses).header;
+        chain
+            .push_header(BlockHeader::KeyBlockHeader(header))
+            .unwrap();
+        assert_eq!(chain.height(), 3);
+        assert_eq!(chain.leader(), &keychains[1].cosi_pkey);
+        assert_eq!(chain.witnesses(), &witnesses[..]);
+        assert_eq!(chain.utxo_root(), blockchain.utxo_root());
+
+        // Revert.
+        chain.pop_header().unwrap();
+        assert_eq!(chain.leader(), &keychains[0].cosi_pkey);
+        assert_eq!(Hash::digest(chain.last_header()), previous);
+        chain.pop_header().unwrap();
+        assert_eq!(chain.utxo_root(), Hash::zero());
+        assert!(chain.pop_header().is_none());
+        assert_eq!(chain.height(), 1);
+    }
+}
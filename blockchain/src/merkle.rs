@@ -60,6 +60,51 @@ struct Node<T> {
     value: Option<T>,
 }
 
+impl<T> Node<T> {
+    /// Create an empty leaf or a pruned subtree with the given hash.
+    fn empty(hash: Hash) -> Node<T> {
+        Node {
+            hash,
+            left: None,
+            right: None,
+            value: None,
+        }
+    }
+}
+
+/// Calculate the hash of an inner node.
+fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Hasher::new();
+    left.hash(&mut hasher);
+    right.hash(&mut hasher);
+    hasher.result()
+}
+
+/// Calculate hashes of nodes on the path from the root to a leaf, hashes[0] is the root.
+///
+/// Returns None if the path goes to the right from a node which has only a left subtree
+/// or if the path is too long.
+///
+fn path_hashes(path: &MerklePath, leaf_hash: Hash, siblings: &[Option<Hash>]) -> Option<Vec<Hash>> {
+    let height = siblings.len();
+    if height > MAX_HEIGHT {
+        return None;
+    }
+    let mut hashes = vec![Hash::zero(); height + 1];
+    hashes[height] = leaf_hash;
+    for h in (0..height).rev() {
+        let left_direction = (path.0 >> h) & 1 == 0;
+        let child = &hashes[h + 1];
+        hashes[h] = match (left_direction, &siblings[h]) {
+            (true, Some(right)) => hash_pair(child, right),
+            (true, None) => hash_pair(child, child),
+            (false, Some(left)) => hash_pair(left, child),
+            (false, None) => return None,
+        };
+    }
+    Some(hashes)
+}
+
 /// Serialized Merkle Tree Node.
 /// See serialize().
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -74,6 +119,20 @@ pub struct SerializedNode<T> {
     pub value: Option<T>,
 }
 
+/// Compact Merkle Tree Node.
+/// See serialize_compact().
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompactNode<T> {
+    /// An inner node with both subtrees, followed by the left and the right subtrees.
+    Full,
+    /// An inner node with only a left subtree, followed by the left subtree.
+    Left,
+    /// A leaf.
+    Leaf(T),
+    /// A pruned subtree or an empty tree.
+    Pruned(Hash),
+}
+
 #[derive(Debug, Fail)]
 pub enum MerkleError {
     /// Invalid serialized representation
@@ -84,8 +143,8 @@ pub enum MerkleError {
     ValidationError(Hash, Hash),
 }
 
-/// 2**256 is more than anyone needed.
-type Height = u8;
+/// The height of a tree, limited by the number of bits in Path.
+type Height = usize;
 
 pub struct Merkle<T: Hashable> {
     root: Box<Node<T>>,
@@ -93,15 +152,36 @@ pub struct Merkle<T: Hashable> {
 
 // -------------------------------------
 
-/// 2**32 is the maximal number of elements.
-type Path = u32;
+/// 2**64 is the maximal number of elements.
+pub(crate) type Path = u64;
+
+/// The maximal height of a tree.
+const MAX_HEIGHT: Height = 8 * std::mem::size_of::<Path>();
 
 /// Bit vector of path in Merkle Tree.
 /// 0 bit - go to the left subtree
 /// 1 bit - go to the right subtree
 /// Stored in inverted order - from leaf to root
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct MerklePath(Path);
+pub struct MerklePath(pub(crate) Path);
+
+/// Proof that a leaf is included into a tree with the given root hash.
+/// See Merkle::proof() and verify_proof().
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MerkleProof {
+    /// The path from the root to the leaf.
+    pub path: MerklePath,
+    /// Hashes of siblings on the path, see Merkle::siblings().
+    pub siblings: Vec<Option<Hash>>,
+}
+
+/// Check that a leaf with the given hash is included into a tree with the given root hash.
+pub fn verify_proof(root: &Hash, leaf_hash: &Hash, proof: &MerkleProof) -> bool {
+    match path_hashes(&proof.path, *leaf_hash, &proof.siblings) {
+        Some(hashes) => hashes[0] == *root,
+        None => false,
+    }
+}
 
 // -------------------------------------
 
@@ -189,65 +269,11 @@ impl<T: Hashable + Clone + fmt::Debug + fmt::Display> Merkle<T> {
     /// Returns the new tree.
     ///
     pub fn from_array(src: &[T]) -> Merkle<T> {
-        assert!(src.len() <= Path::max_value() as usize);
-
-        // Special case - empty tree.
-        if src.len() == 0 {
-            let root = Box::new(Node {
-                hash: Hash::zero(),
-                left: None,
-                right: None,
-                value: None,
-            });
-
-            return Merkle { root };
-        }
-
-        let mut nodes: Vec<Box<Node<T>>> = Vec::with_capacity(src.len() + 1);
-        let mut heights: Vec<Height> = Vec::with_capacity(src.len() + 1);
-
+        let mut builder = MerkleBuilder::new();
         for value in src.iter() {
-            let mut hasher = Hasher::new();
-            value.hash(&mut hasher);
-            let hash = hasher.result();
-
-            // Create a leaf
-            let node = Box::new(Node {
-                hash,
-                left: None,
-                right: None,
-                value: Some(value.clone()),
-            });
-
-            nodes.push(node);
-            heights.push(0);
-
-            // Create parent nodes
-            while nodes.len() > 1 && heights[heights.len() - 2] == heights[heights.len() - 1] {
-                Merkle::pull(&mut nodes, &mut heights);
-            }
+            builder.push(value.clone());
         }
-
-        // Executed only if the number of elements is not power of two
-        while nodes.len() > 1 {
-            if heights[heights.len() - 2] == heights[heights.len() - 1] {
-                // Create full inner nodes
-                Merkle::pull(&mut nodes, &mut heights);
-            } else {
-                // Create a left inner nodes
-                Merkle::pull_left(&mut nodes, &mut heights);
-            }
-        }
-
-        assert_eq!(nodes.len(), 1);
-        assert_eq!(heights.len(), 1);
-
-        let root = nodes.pop().unwrap();
-        let height = heights.pop().unwrap();
-
-        assert_eq!(height, expected_height(src.len()));
-
-        Merkle { root }
+        builder.build()
     }
 
     /// Lookup an element by path
@@ -368,6 +394,122 @@ impl<T: Hashable + Clone + fmt::Debug + fmt::Display> Merkle<T> {
         Merkle::prune_r(&mut self.root, path)
     }
 
+    /// Returns hashes of siblings on the path from the root to a leaf.
+    ///
+    /// None is used for the missing right sibling of a node paired with itself.
+    /// These hashes are needed to restore() the leaf after prune().
+    ///
+    pub fn siblings(&self, path: &MerklePath) -> Option<Vec<Option<Hash>>> {
+        let mut node = &self.root;
+        let mut path = path.0;
+        let mut siblings = Vec::<Option<Hash>>::new();
+
+        loop {
+            // true - go left, false - go right
+            let left_direction = (path & 1) == 0;
+            path >>= 1;
+
+            node = match **node {
+                Node {
+                    left: Some(ref left),
+                    ref right,
+                    value: None,
+                    ..
+                } if left_direction => {
+                    siblings.push(right.as_ref().map(|right| right.hash));
+                    left
+                }
+                Node {
+                    left: Some(ref left),
+                    right: Some(ref right),
+                    value: None,
+                    ..
+                } => {
+                    siblings.push(Some(left.hash));
+                    right
+                }
+                Node {
+                    left: None,
+                    right: None,
+                    value: Some(_),
+                    ..
+                } => return Some(siblings),
+                _ => return None, // missing subtree
+            };
+        }
+    }
+
+    /// Create an inclusion proof for a leaf, see verify_proof().
+    ///
+    /// Returns None if the leaf is missing or pruned.
+    ///
+    pub fn proof(&self, path: &MerklePath) -> Option<MerkleProof> {
+        let siblings = self.siblings(path)?;
+        Some(MerkleProof {
+            path: *path,
+            siblings,
+        })
+    }
+
+    /// Put a pruned element back to its original position.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the element.
+    /// * `value` - the element returned by prune().
+    /// * `siblings` - hashes returned by siblings() before prune().
+    ///
+    pub fn restore(
+        &mut self,
+        path: &MerklePath,
+        value: T,
+        siblings: &[Option<Hash>],
+    ) -> Result<(), MerkleError> {
+        let height = siblings.len();
+        let hashes = match path_hashes(path, Hash::digest(&value), siblings) {
+            Some(hashes) => hashes,
+            None => return Err(MerkleError::InvalidStructure),
+        };
+        if hashes[0] != self.root.hash {
+            return Err(MerkleError::ValidationError(self.root.hash, hashes[0]));
+        }
+
+        // Re-create pruned inner nodes on the path.
+        let mut node = &mut self.root;
+        for h in 0..height {
+            if node.value.is_some() {
+                return Err(MerkleError::InvalidStructure);
+            }
+            let left_direction = (path.0 >> h) & 1 == 0;
+            if node.left.is_none() && node.right.is_none() {
+                let child = Some(Box::new(Node::empty(hashes[h + 1])));
+                let sibling = siblings[h].map(|hash| Box::new(Node::empty(hash)));
+                if left_direction {
+                    node.left = child;
+                    node.right = sibling;
+                } else {
+                    node.left = sibling;
+                    node.right = child;
+                }
+            }
+            let child = if left_direction {
+                node.left.as_mut()
+            } else {
+                node.right.as_mut()
+            };
+            node = match child {
+                Some(child) => child,
+                None => return Err(MerkleError::InvalidStructure),
+            };
+        }
+
+        if node.left.is_some() || node.right.is_some() || node.value.is_some() {
+            return Err(MerkleError::InvalidStructure);
+        }
+        node.value = Some(value);
+        Ok(())
+    }
+
     /// A recursive helper for leafs().
     fn leafs_r<'a>(r: &mut Vec<(&'a T, MerklePath)>, node: &'a Node<T>, path: Path, h: Height) {
         match node {
@@ -649,6 +791,116 @@ impl<T: Hashable + Clone + fmt::Debug + fmt::Display> Merkle<T> {
         Ok(tree)
     }
 
+    /// A recursive helper for serialize_compact().
+    fn serialize_compact_r(r: &mut Vec<CompactNode<T>>, node: &Node<T>) {
+        match node {
+            // An inner node with both subtrees
+            Node {
+                left: Some(ref left),
+                right: Some(ref right),
+                value: None,
+                ..
+            } => {
+                r.push(CompactNode::Full);
+                Merkle::serialize_compact_r(r, &left);
+                Merkle::serialize_compact_r(r, &right);
+            }
+            // An inner node with only a left subtree
+            Node {
+                left: Some(ref left),
+                right: None,
+                value: None,
+                ..
+            } => {
+                r.push(CompactNode::Left);
+                Merkle::serialize_compact_r(r, &left);
+            }
+            // A leaf
+            Node {
+                left: None,
+                right: None,
+                value: Some(ref value),
+                ..
+            } => r.push(CompactNode::Leaf(value.clone())),
+            // A pruned subtree or an empty tree
+            Node {
+                hash,
+                left: None,
+                right: None,
+                value: None,
+            } => r.push(CompactNode::Pruned(*hash)),
+            _ => unreachable!(), // No more cases
+        }
+    }
+
+    /// Serialize the tree in pre-order, omitting hashes which can be recalculated.
+    ///
+    /// Only hashes of pruned subtrees and values of leafs are kept.
+    ///
+    pub fn serialize_compact(&self) -> Vec<CompactNode<T>> {
+        let mut r = Vec::<CompactNode<T>>::new();
+        Merkle::serialize_compact_r(&mut r, &self.root);
+        r
+    }
+
+    /// A recursive helper for deserialize_compact().
+    fn deserialize_compact_r(
+        cnodes: &[CompactNode<T>],
+        pos: &mut usize,
+        h: Height,
+    ) -> Result<Box<Node<T>>, MerkleError> {
+        if h > MAX_HEIGHT || *pos >= cnodes.len() {
+            return Err(MerkleError::InvalidStructure);
+        }
+        let cnode = &cnodes[*pos];
+        *pos += 1;
+
+        let node = match cnode {
+            CompactNode::Full => {
+                let left = Merkle::deserialize_compact_r(cnodes, pos, h + 1)?;
+                let right = Merkle::deserialize_compact_r(cnodes, pos, h + 1)?;
+                Node {
+                    hash: hash_pair(&left.hash, &right.hash),
+                    left: Some(left),
+                    right: Some(right),
+                    value: None,
+                }
+            }
+            CompactNode::Left => {
+                let left = Merkle::deserialize_compact_r(cnodes, pos, h + 1)?;
+                Node {
+                    hash: hash_pair(&left.hash, &left.hash),
+                    left: Some(left),
+                    right: None,
+                    value: None,
+                }
+            }
+            CompactNode::Leaf(value) => Node {
+                hash: Hash::digest(value),
+                left: None,
+                right: None,
+                value: Some(value.clone()),
+            },
+            CompactNode::Pruned(hash) => Node::empty(*hash),
+        };
+        Ok(Box::new(node))
+    }
+
+    /// Create a Merkle Tree from compact representation, see serialize_compact().
+    ///
+    /// All hashes except hashes of pruned subtrees are recalculated, so the caller
+    /// should compare roothash() with the expected value.
+    ///
+    pub fn deserialize_compact(cnodes: &[CompactNode<T>]) -> Result<Merkle<T>, MerkleError> {
+        let mut pos: usize = 0;
+        let root = Merkle::deserialize_compact_r(cnodes, &mut pos, 0)?;
+        // Check that all nodes are processed.
+        if pos != cnodes.len() {
+            return Err(MerkleError::InvalidStructure);
+        }
+        Ok(Merkle { root })
+    }
+
     /// A recursive helper for fmt().
     fn fmt_r(f: &mut fmt::Formatter<'_>, node: &Node<T>, h: usize) -> fmt::Result {
         match node {
@@ -719,6 +971,113 @@ impl<T: Hashable + Clone + fmt::Debug + fmt::Display> Clone for Merkle<T> {
     }
 }
 
+/// Incremental construction of a Merkle Tree.
+///
+/// Leafs are appended one by one, so values can be streamed into the tree
+/// without collecting them into an array first. The resulting tree is the same
+/// as created by Merkle::from_array() from all appended values.
+pub struct MerkleBuilder<T: Hashable> {
+    /// Roots of complete subtrees, from the left to the right.
+    nodes: Vec<Box<Node<T>>>,
+    /// Heights of subtrees in nodes.
+    heights: Vec<Height>,
+    /// The number of appended leafs.
+    len: usize,
+}
+
+impl<T: Hashable + Clone + fmt::Debug + fmt::Display> MerkleBuilder<T> {
+    /// Create a builder for an empty tree.
+    pub fn new() -> MerkleBuilder<T> {
+        MerkleBuilder {
+            nodes: Vec::new(),
+            heights: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of appended leafs.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no leafs are appended.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append a leaf.
+    pub fn push(&mut self, value: T) {
+        assert!((self.len as Path) < Path::max_value());
+        let hash = Hash::digest(&value);
+
+        // Create a leaf
+        let node = Box::new(Node {
+            hash,
+            left: None,
+            right: None,
+            value: Some(value),
+        });
+
+        self.nodes.push(node);
+        self.heights.push(0);
+        self.len += 1;
+
+        // Create parent nodes
+        let (nodes, heights) = (&mut self.nodes, &mut self.heights);
+        while nodes.len() > 1 && heights[heights.len() - 2] == heights[heights.len() - 1] {
+            Merkle::pull(nodes, heights);
+        }
+    }
+
+    /// Create the tree from appended leafs.
+    pub fn build(self) -> Merkle<T> {
+        let MerkleBuilder {
+            mut nodes,
+            mut heights,
+            len,
+        } = self;
+
+        // Special case - empty tree.
+        if len == 0 {
+            let root = Box::new(Node {
+                hash: Hash::zero(),
+                left: None,
+                right: None,
+                value: None,
+            });
+
+            return Merkle { root };
+        }
+
+        // Executed only if the number of elements is not power of two
+        while nodes.len() > 1 {
+            if heights[heights.len() - 2] == heights[heights.len() - 1] {
+                // Create full inner nodes
+                Merkle::pull(&mut nodes, &mut heights);
+            } else {
+                // Create a left inner nodes
+                Merkle::pull_left(&mut nodes, &mut heights);
+            }
+        }
+
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(heights.len(), 1);
+
+        let root = nodes.pop().unwrap();
+        let height = heights.pop().unwrap();
+
+        assert_eq!(height, expected_height(len));
+
+        Merkle { root }
+    }
+}
+
+impl<T: Hashable + Clone + fmt::Debug + fmt::Display> Default for MerkleBuilder<T> {
+    fn default() -> MerkleBuilder<T> {
+        MerkleBuilder::new()
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -727,13 +1086,14 @@ pub mod tests {
     use rand::seq::SliceRandom;
     use simple_logger;
 
-    /// Reverse the order of bits in a byte
-    fn reverse_u32(mut n: u32) -> u32 {
-        n = (n >> 1) & 0x55555555 | (n << 1) & 0xaaaaaaaa;
-        n = (n >> 2) & 0x33333333 | (n << 2) & 0xcccccccc;
-        n = (n >> 4) & 0x0f0f0f0f | (n << 4) & 0xf0f0f0f0;
-        n = (n >> 8) & 0x00ff00ff | (n << 8) & 0xff00ff00;
-        n = (n >> 16) & 0x0000ffff | (n << 16) & 0xffff0000;
+    /// Reverse the order of bits in a path
+    fn reverse_path(mut n: Path) -> Path {
+        n = (n >> 1) & 0x5555555555555555 | (n << 1) & 0xaaaaaaaaaaaaaaaa;
+        n = (n >> 2) & 0x3333333333333333 | (n << 2) & 0xcccccccccccccccc;
+        n = (n >> 4) & 0x0f0f0f0f0f0f0f0f | (n << 4) & 0xf0f0f0f0f0f0f0f0;
+        n = (n >> 8) & 0x00ff00ff00ff00ff | (n << 8) & 0xff00ff00ff00ff00;
+        n = (n >> 16) & 0x0000ffff0000ffff | (n << 16) & 0xffff0000ffff0000;
+        n = (n >> 32) & 0x00000000ffffffff | (n << 32) & 0xffffffff00000000;
         n
     }
 
@@ -749,7 +1109,10 @@ pub mod tests {
         // in order to find that leaf.
         //
         (0..count)
-            .map(|x| MerklePath(reverse_u32((x << (32 - height)) as Path)))
+            .map(|x| match height {
+                0 => MerklePath(0),
+                _ => MerklePath(reverse_path((x as Path) << (MAX_HEIGHT - height))),
+            })
             .collect::<Vec<MerklePath>>()
     }
 
@@ -1150,6 +1513,174 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn restore() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut rng = thread_rng();
+        for size in [1usize, 2, 3, 5, 8, 13]
+            .iter()
+            .cloned()
+            .chain(Some(rng.gen_range(100, 300)))
+        {
+            let data: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+            let mut tree = Merkle::from_array(&data);
+            let roothash = tree.roothash().clone();
+            let paths = tree
+                .leafs()
+                .iter()
+                .map(|(_elem, path)| *path)
+                .collect::<Vec<MerklePath>>();
+
+            // Prune all elements in random order.
+            let mut indexes: Vec<usize> = (0..size).collect();
+            indexes.shuffle(&mut rng);
+            let mut pruned = Vec::new();
+            for i in &indexes {
+                let siblings = tree.siblings(&paths[*i]).unwrap();
+                let value = tree.prune(&paths[*i]).unwrap();
+                assert_eq!(tree.siblings(&paths[*i]), None);
+                pruned.push((*i, value, siblings));
+            }
+
+            // Restore them in another random order.
+            pruned.shuffle(&mut rng);
+            for (i, value, siblings) in pruned.drain(..) {
+                // Wrong position.
+                if size > 1 {
+                    let other = paths[(i + 1) % size];
+                    if tree.lookup(&other).is_none() {
+                        assert!(tree.restore(&other, value, &siblings).is_err());
+                    }
+                }
+                tree.restore(&paths[i], value, &siblings).unwrap();
+                tree.validate().unwrap();
+                assert_eq!(*tree.lookup(&paths[i]).unwrap(), data[i]);
+                // Already restored.
+                assert!(tree.restore(&paths[i], value, &siblings).is_err());
+            }
+
+            assert_eq!(*tree.roothash(), roothash);
+            let leafs = tree.leafs();
+            assert_eq!(leafs.len(), size);
+            for ((left, path), right) in leafs.iter().zip(data.iter()) {
+                assert_eq!(**left, *right);
+                assert_eq!(
+                    tree.siblings(path).unwrap().len(),
+                    expected_height(size) as usize
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn proofs() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut rng = thread_rng();
+        for size in [1usize, 2, 3, 5, 8, 13]
+            .iter()
+            .cloned()
+            .chain(Some(rng.gen_range(100, 300)))
+        {
+            let data: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+            let mut tree = Merkle::from_array(&data);
+            let roothash = tree.roothash().clone();
+            let paths = tree
+                .leafs()
+                .iter()
+                .map(|(_elem, path)| *path)
+                .collect::<Vec<MerklePath>>();
+
+            for (value, path) in data.iter().zip(&paths) {
+                let leaf_hash = Hash::digest(value);
+                let proof = tree.proof(path).unwrap();
+                assert_eq!(proof.path, *path);
+                assert!(verify_proof(&roothash, &leaf_hash, &proof));
+
+                // Wrong leaf or root.
+                let other = Hash::digest(&value.wrapping_add(1));
+                assert!(!verify_proof(&roothash, &other, &proof));
+                assert!(!verify_proof(&other, &leaf_hash, &proof));
+
+                // Wrong path.
+                if size > 1 {
+                    let mut invalid = proof.clone();
+                    invalid.path = MerklePath(path.0 ^ 1);
+                    assert!(!verify_proof(&roothash, &leaf_hash, &invalid));
+                }
+
+                // Wrong siblings.
+                let mut invalid = proof.clone();
+                invalid.siblings.push(Some(other));
+                assert!(!verify_proof(&roothash, &leaf_hash, &invalid));
+            }
+
+            // Proofs are still valid after pruning, but can't be created for pruned leafs.
+            let proof = tree.proof(&paths[0]).unwrap();
+            tree.prune(&paths[0]).unwrap();
+            assert_eq!(tree.proof(&paths[0]), None);
+            assert!(verify_proof(&roothash, &Hash::digest(&data[0]), &proof));
+        }
+    }
+
+    #[test]
+    fn builder() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut rng = thread_rng();
+        for size in [0usize, 1, 2, 3, 5, 8, 13]
+            .iter()
+            .cloned()
+            .chain(Some(rng.gen_range(100, 300)))
+        {
+            let data: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+            let tree = Merkle::from_array(&data);
+
+            let mut builder = MerkleBuilder::new();
+            assert!(builder.is_empty());
+            for value in &data {
+                builder.push(*value);
+            }
+            assert_eq!(builder.len(), size);
+            let tree2 = builder.build();
+            tree2.validate().unwrap();
+            assert_eq!(tree.roothash(), tree2.roothash());
+            assert_eq!(tree.serialize(), tree2.serialize());
+            assert_eq!(tree.leafs(), tree2.leafs());
+        }
+    }
+
+    #[test]
+    fn long_paths() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        // A proof for a leaf of a tree with more than 2**32 leafs.
+        let mut rng = thread_rng();
+        let height = 40;
+        let leaf_hash = Hash::digest(&rng.gen::<u64>());
+        let path = MerklePath((1 << (height - 1)) | rng.gen_range(0, 1 << 32));
+        let siblings: Vec<Option<Hash>> = (0..height)
+            .map(|_| Some(Hash::digest(&rng.gen::<u64>())))
+            .collect();
+        let mut root = leaf_hash;
+        for h in (0..height).rev() {
+            let sibling = siblings[h].unwrap();
+            root = if (path.0 >> h) & 1 == 0 {
+                hash_pair(&root, &sibling)
+            } else {
+                hash_pair(&sibling, &root)
+            };
+        }
+        let proof = MerkleProof { path, siblings };
+        assert!(verify_proof(&root, &leaf_hash, &proof));
+
+        // Paths can't be longer than MAX_HEIGHT.
+        let mut invalid = proof.clone();
+        invalid.siblings = vec![None; MAX_HEIGHT + 1];
+        assert!(!verify_proof(&root, &leaf_hash, &invalid));
+    }
+
     #[test]
     fn serialize_errors() {
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
@@ -1210,6 +1741,98 @@ pub mod tests {
         assert_eq!(leafs, leafs2);
     }
 
+    fn check_compact_rt(tree: &Merkle<u64>) {
+        let compact = tree.serialize_compact();
+        let tree2 = Merkle::deserialize_compact(&compact).unwrap();
+        tree2.validate().unwrap();
+        assert_eq!(tree.roothash(), tree2.roothash());
+        assert_eq!(tree.leafs(), tree2.leafs());
+        assert_eq!(tree.serialize(), tree2.serialize());
+        let tree3 = Merkle::deserialize(&tree.serialize()).unwrap();
+        assert_eq!(compact, tree3.serialize_compact());
+        assert_eq!(compact.len(), tree.serialize().len());
+    }
+
+    #[test]
+    fn serialize_compact() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let mut rng = thread_rng();
+        for size in [0usize, 1, 2, 3, 5, 8, 13]
+            .iter()
+            .cloned()
+            .chain(Some(rng.gen_range(100, 300)))
+        {
+            let data: Vec<u64> = (0..size).map(|_| rng.gen()).collect();
+            let mut tree = Merkle::from_array(&data);
+            check_compact_rt(&tree);
+
+            // Prune elements in random order.
+            let mut paths = tree
+                .leafs()
+                .iter()
+                .map(|(_elem, path)| *path)
+                .collect::<Vec<MerklePath>>();
+            paths.shuffle(&mut rng);
+            for path in paths {
+                tree.prune(&path).unwrap();
+                check_compact_rt(&tree);
+            }
+        }
+    }
+
+    #[test]
+    fn serialize_compact_errors() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+
+        let data: [u64; 5] = [1, 2, 3, 4, 5];
+        let tree = Merkle::from_array(&data);
+
+        // Zero elements
+        let compact: Vec<CompactNode<u64>> = Vec::new();
+        match Merkle::deserialize_compact(&compact) {
+            Err(MerkleError::InvalidStructure) => {}
+            _ => unreachable!(),
+        };
+
+        // Missing elements
+        let mut compact = tree.serialize_compact();
+        compact.pop();
+        match Merkle::deserialize_compact(&compact) {
+            Err(MerkleError::InvalidStructure) => {}
+            _ => unreachable!(),
+        };
+
+        // Extra elements
+        let mut compact = tree.serialize_compact();
+        compact.push(CompactNode::Leaf(6u64));
+        match Merkle::deserialize_compact(&compact) {
+            Err(MerkleError::InvalidStructure) => {}
+            _ => unreachable!(),
+        };
+
+        // Too deep
+        let mut compact = vec![CompactNode::Left; MAX_HEIGHT + 1];
+        compact.push(CompactNode::Leaf(1u64));
+        match Merkle::deserialize_compact(&compact) {
+            Err(MerkleError::InvalidStructure) => {}
+            _ => unreachable!(),
+        };
+        compact.remove(0);
+        Merkle::deserialize_compact(&compact).unwrap();
+
+        // Modified values change the root hash
+        let mut compact = tree.serialize_compact();
+        for node in compact.iter_mut() {
+            if let CompactNode::Leaf(ref mut value) = node {
+                *value = 0;
+                break;
+            }
+        }
+        let tree2 = Merkle::deserialize_compact(&compact).unwrap();
+        assert_ne!(tree.roothash(), tree2.roothash());
+    }
+
     #[test]
     fn serialize_single_item() {
         simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
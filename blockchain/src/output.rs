@@ -24,7 +24,9 @@
 use failure::{Error, Fail};
 use std::fmt;
 use std::mem::transmute;
-use stegos_crypto::bulletproofs::{make_range_proof, pedersen_commitment, BulletProof};
+use stegos_crypto::bulletproofs::{
+    make_deterministic_range_proof, make_range_proof, pedersen_commitment, BulletProof,
+};
 use stegos_crypto::curve1174::cpt::{
     aes_decrypt, aes_encrypt, EncryptedPayload, Pt, PublicKey, SecretKey,
 };
@@ -87,7 +89,7 @@ pub struct DataOutput {
     /// Pedersen commitment to zero.
     pub vcmt: Pt,
 
-    /// The number of blocks for which this UTXO should be kept on the blockchain since
+    /// The number of epochs for which this UTXO should be kept on the blockchain since
     /// it has been added to it.
     pub ttl: u64,
 
@@ -159,6 +161,34 @@ impl MonetaryOutput {
         Ok((output, gamma))
     }
 
+    /// Constructor for monetary UTXO which doesn't use PRNG.
+    ///
+    /// Blinding factors are derived from the sender's secret key, so the same
+    /// arguments always produce the same output. Used to create genesis blocks.
+    pub fn new_deterministic(
+        timestamp: u64,
+        sender_skey: &SecretKey,
+        recipient_pkey: &PublicKey,
+        amount: i64,
+    ) -> Result<(Self, Fr), Error> {
+        // Clock recipient public key
+        let (cloaked_pkey, delta) = cloak_key(sender_skey, recipient_pkey, timestamp)?;
+
+        // Create range proofs.
+        let seed = Hash::digest_chain(&[&delta, &amount]);
+        let (proof, gamma) = make_deterministic_range_proof(amount, &seed);
+
+        let payload = Self::encrypt_payload(delta, gamma, amount, recipient_pkey)?;
+
+        let output = MonetaryOutput {
+            recipient: cloaked_pkey,
+            proof,
+            payload,
+        };
+
+        Ok((output, gamma))
+    }
+
     /// Create a new monetary transaction.
     fn encrypt_payload(
         delta: Fr,
@@ -0,0 +1,375 @@
+//! Persistent Block Store.
+
+//
+// Copyright (c) 2018 Stegos
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::block::*;
+use crate::blockchain::{BlockId, OutputKey, SpentOutput};
+use crate::error::*;
+use crate::merkle::MerklePath;
+use failure::Error;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::path::Path;
+use stegos_crypto::curve1174::cpt::Pt;
+use stegos_crypto::hash::*;
+use stegos_storage::{Storage, WriteBatch};
+
+/// Prefix for block_id => block.
+const BLOCK_PREFIX: u8 = b'b';
+/// Prefix for block hash => block_id.
+const BLOCK_HASH_PREFIX: u8 = b'h';
+/// Prefix for output hash => (block_id, path).
+const OUTPUT_PREFIX: u8 = b'o';
+/// Prefix for block_id => undo records.
+const UNDO_PREFIX: u8 = b'u';
+/// Prefix for block_id => sum of commitments of outputs expired by the block.
+const EXPIRED_PREFIX: u8 = b'e';
+
+/// Undo record of a spent output: the hash, the original position and
+/// hashes of siblings in Merkle Tree. The output itself is taken from the stored block.
+pub(crate) type UndoRecord = (Hash, OutputKey, Vec<Option<Hash>>);
+
+/// Block serialization used by the persistent storage.
+///
+/// The wire format of blocks is defined outside of this crate.
+/// The size of serialized blocks is limited by MAX_BLOCK_SIZE, see Blockchain::validate_block().
+pub trait BlockCodec: Send {
+    /// Serialize a block.
+    fn encode(&self, block: &Block) -> Vec<u8>;
+    /// Deserialize a block.
+    fn decode(&self, data: &[u8]) -> Result<Block, Error>;
+}
+
+/// Blocks and indexes stored on the disk.
+///
+/// Blocks are stored as they were registered, i.e. without pruned outputs,
+/// except blocks restored from a snapshot, which are stored pruned and without undo records.
+pub(crate) struct BlockStore {
+    storage: Storage,
+    codec: Box<dyn BlockCodec>,
+}
+
+impl BlockStore {
+    pub fn open<P: AsRef<Path>>(path: P, codec: Box<dyn BlockCodec>) -> Result<BlockStore, Error> {
+        let storage = Storage::open(path)?;
+        Ok(BlockStore { storage, codec })
+    }
+
+    /// Returns the size of the serialized block.
+    pub fn encoded_size(&self, block: &Block) -> usize {
+        self.codec.encode(block).len()
+    }
+
+    /// Load block by its identifier.
+    pub fn block(&self, block_id: BlockId) -> Result<Option<Block>, Error> {
+        match self.storage.get(&block_key(block_id))? {
+            Some(data) => Ok(Some(self.codec.decode(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load the block by hash index.
+    pub fn block_by_hash(&self) -> Result<HashMap<Hash, BlockId>, Error> {
+        let mut block_by_hash = HashMap::new();
+        for key in self.storage.keys_with_prefix(&[BLOCK_HASH_PREFIX]) {
+            let hash = Hash::try_from_bytes(&key[1..])?;
+            let value = self.storage.get(&key)?.expect("key exists");
+            if value.len() != 8 {
+                let msg = format!("invalid block_id for block {}", hash);
+                return Err(BlockchainError::CorruptedStorage(msg).into());
+            }
+            let block_id = get_u64(&value) as BlockId;
+            block_by_hash.insert(hash, block_id);
+        }
+        Ok(block_by_hash)
+    }
+
+    /// Load the unspent outputs index.
+    pub fn output_by_hash(&self) -> Result<HashMap<Hash, OutputKey>, Error> {
+        let mut output_by_hash = HashMap::new();
+        for key in self.storage.keys_with_prefix(&[OUTPUT_PREFIX]) {
+            let hash = Hash::try_from_bytes(&key[1..])?;
+            let value = self.storage.get(&key)?.expect("key exists");
+            if value.len() != 16 {
+                let msg = format!("invalid key for UTXO {}", hash);
+                return Err(BlockchainError::CorruptedStorage(msg).into());
+            }
+            let block_id = get_u64(&value[0..8]) as BlockId;
+            let path = MerklePath(get_u64(&value[8..16]) as _);
+            output_by_hash.insert(hash, OutputKey { block_id, path });
+        }
+        Ok(output_by_hash)
+    }
+
+    /// Load sums of commitments of expired outputs by block.
+    pub fn expired(&self) -> Result<BTreeMap<BlockId, Pt>, Error> {
+        let mut expired = BTreeMap::new();
+        for key in self.storage.keys_with_prefix(&[EXPIRED_PREFIX]) {
+            if key.len() != 9 {
+                let msg = "invalid key of expired outputs".to_string();
+                return Err(BlockchainError::CorruptedStorage(msg).into());
+            }
+            let block_id = get_u64(&key[1..9]) as BlockId;
+            let value = self.storage.get(&key)?.expect("key exists");
+            let commitment = match Pt::try_from_bytes(&value) {
+                Ok(commitment) => commitment,
+                Err(_) => {
+                    let msg = format!("invalid expired outputs for block_id {}", block_id);
+                    return Err(BlockchainError::CorruptedStorage(msg).into());
+                }
+            };
+            expired.insert(block_id, commitment);
+        }
+        Ok(expired)
+    }
+
+    /// Load undo records of a monetary block.
+    pub fn undo(&self, block_id: BlockId) -> Result<Option<Vec<UndoRecord>>, Error> {
+        match self.storage.get(&undo_key(block_id))? {
+            Some(data) => match decode_undo(&data) {
+                Some(records) => Ok(Some(records)),
+                None => {
+                    let msg = format!("invalid undo data for block_id {}", block_id);
+                    Err(BlockchainError::CorruptedStorage(msg).into())
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Atomically store a new block together with changes of the unspent outputs index.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_id` - The identifier of the block.
+    /// * `block` - The block.
+    /// * `spent` - Outputs spent by this block.
+    /// * `created` - Hashes and paths of outputs created by this block.
+    /// * `expired` - The sum of commitments of outputs expired by this block.
+    ///
+    pub fn commit_block(
+        &mut self,
+        block_id: BlockId,
+        block: &Block,
+        spent: &[SpentOutput],
+        created: &[(Hash, MerklePath)],
+        expired: Option<Pt>,
+    ) -> Result<(), Error> {
+        let hash = Hash::digest(block);
+        let mut batch = WriteBatch::new();
+        batch.put(&block_key(block_id), &self.codec.encode(block));
+        batch.put(
+            &hash_key(BLOCK_HASH_PREFIX, &hash),
+            &u64_bytes(block_id as u64),
+        );
+        if let Block::MonetaryBlock(_) = block {
+            batch.put(&undo_key(block_id), &encode_undo(spent));
+        }
+        if let Some(expired) = expired {
+            batch.put(&expired_key(block_id), &expired.into_bytes());
+        }
+        for spent_output in spent {
+            batch.delete(&hash_key(OUTPUT_PREFIX, &spent_output.hash));
+        }
+        for (output_hash, path) in created {
+            let output_key = OutputKey {
+                block_id,
+                path: *path,
+            };
+            batch.put(
+                &hash_key(OUTPUT_PREFIX, output_hash),
+                &output_key_bytes(&output_key),
+            );
+        }
+        self.storage.write(batch)
+    }
+
+    /// Atomically append blocks of a snapshot, without undo records.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_id` - The identifier of the first block.
+    /// * `blocks` - Blocks with pruned outputs.
+    /// * `spent` - Hashes of stored outputs spent by these blocks.
+    /// * `created` - Hashes and keys of unspent outputs of these blocks.
+    /// * `expired` - Sums of commitments of outputs expired by these blocks.
+    ///
+    pub fn commit_snapshot(
+        &mut self,
+        block_id: BlockId,
+        blocks: &[Block],
+        spent: &[Hash],
+        created: &[(Hash, OutputKey)],
+        expired: &BTreeMap<BlockId, Pt>,
+    ) -> Result<(), Error> {
+        let mut batch = WriteBatch::new();
+        for (offset, block) in blocks.iter().enumerate() {
+            let hash = Hash::digest(block);
+            batch.put(&block_key(block_id + offset), &self.codec.encode(block));
+            batch.put(
+                &hash_key(BLOCK_HASH_PREFIX, &hash),
+                &u64_bytes((block_id + offset) as u64),
+            );
+        }
+        for output_hash in spent {
+            batch.delete(&hash_key(OUTPUT_PREFIX, output_hash));
+        }
+        for (output_hash, output_key) in created {
+            batch.put(
+                &hash_key(OUTPUT_PREFIX, output_hash),
+                &output_key_bytes(output_key),
+            );
+        }
+        for (block_id, commitment) in expired {
+            batch.put(&expired_key(*block_id), &commitment.into_bytes());
+        }
+        self.storage.write(batch)
+    }
+
+    /// Atomically remove the last block and restore outputs spent by it.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_id` - The identifier of the block.
+    /// * `block_hash` - The hash of the block.
+    /// * `created` - Hashes of outputs created by this block.
+    /// * `restored` - Outputs spent by this block.
+    ///
+    pub fn revert_block(
+        &mut self,
+        block_id: BlockId,
+        block_hash: &Hash,
+        created: &[Hash],
+        restored: &[(Hash, OutputKey)],
+    ) -> Result<(), Error> {
+        let mut batch = WriteBatch::new();
+        batch.delete(&block_key(block_id));
+        batch.delete(&hash_key(BLOCK_HASH_PREFIX, block_hash));
+        batch.delete(&undo_key(block_id));
+        batch.delete(&expired_key(block_id));
+        for output_hash in created {
+            batch.delete(&hash_key(OUTPUT_PREFIX, output_hash));
+        }
+        for (output_hash, output_key) in restored {
+            batch.put(
+                &hash_key(OUTPUT_PREFIX, output_hash),
+                &output_key_bytes(output_key),
+            );
+        }
+        self.storage.write(batch)
+    }
+}
+
+fn block_key(block_id: BlockId) -> Vec<u8> {
+    let mut key = vec![BLOCK_PREFIX];
+    key.extend_from_slice(&u64_bytes(block_id as u64));
+    key
+}
+
+fn undo_key(block_id: BlockId) -> Vec<u8> {
+    let mut key = vec![UNDO_PREFIX];
+    key.extend_from_slice(&u64_bytes(block_id as u64));
+    key
+}
+
+fn expired_key(block_id: BlockId) -> Vec<u8> {
+    let mut key = vec![EXPIRED_PREFIX];
+    key.extend_from_slice(&u64_bytes(block_id as u64));
+    key
+}
+
+fn hash_key(prefix: u8, hash: &Hash) -> Vec<u8> {
+    let mut key = vec![prefix];
+    key.extend_from_slice(hash.base_vector());
+    key
+}
+
+fn output_key_bytes(output_key: &OutputKey) -> Vec<u8> {
+    let mut value = u64_bytes(output_key.block_id as u64);
+    value.extend_from_slice(&u64_bytes(output_key.path.0 as u64));
+    value
+}
+
+/// Serialize undo records as
+/// [count u64] ([hash] [block_id u64] [path u64] [count u64] ([0] | [1] [hash])*)*.
+fn encode_undo(spent: &[SpentOutput]) -> Vec<u8> {
+    let mut value = u64_bytes(spent.len() as u64);
+    for spent_output in spent {
+        value.extend_from_slice(spent_output.hash.base_vector());
+        value.extend_from_slice(&output_key_bytes(&spent_output.key));
+        value.extend_from_slice(&u64_bytes(spent_output.siblings.len() as u64));
+        for sibling in &spent_output.siblings {
+            match sibling {
+                Some(hash) => {
+                    value.push(1);
+                    value.extend_from_slice(hash.base_vector());
+                }
+                None => value.push(0),
+            }
+        }
+    }
+    value
+}
+
+/// Deserialize undo records, see encode_undo().
+fn decode_undo(mut value: &[u8]) -> Option<Vec<UndoRecord>> {
+    let value = &mut value;
+    let count = get_u64(take(value, 8)?);
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let hash = Hash::try_from_bytes(take(value, HASH_SIZE)?).ok()?;
+        let block_id = get_u64(take(value, 8)?) as BlockId;
+        let path = MerklePath(get_u64(take(value, 8)?) as _);
+        let siblings_count = get_u64(take(value, 8)?);
+        let mut siblings = Vec::new();
+        for _ in 0..siblings_count {
+            match take(value, 1)?[0] {
+                0 => siblings.push(None),
+                1 => siblings.push(Some(Hash::try_from_bytes(take(value, HASH_SIZE)?).ok()?)),
+                _ => return None,
+            }
+        }
+        records.push((hash, OutputKey { block_id, path }, siblings));
+    }
+    if !value.is_empty() {
+        return None;
+    }
+    Some(records)
+}
+
+/// Split off the first n bytes.
+fn take<'a>(value: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if value.len() < n {
+        return None;
+    }
+    let (head, tail) = value.split_at(n);
+    *value = tail;
+    Some(head)
+}
+
+fn u64_bytes(v: u64) -> Vec<u8> {
+    (0..8).rev().map(|i| (v >> (i * 8)) as u8).collect()
+}
+
+fn get_u64(buf: &[u8]) -> u64 {
+    buf[0..8].iter().fold(0u64, |v, b| (v << 8) | (*b as u64))
+}
@@ -0,0 +1,375 @@
+//! Commitment to the set of unspent outputs.
+
+//
+// Copyright (c) 2018 Stegos
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::error::*;
+use std::sync::Arc;
+use stegos_crypto::hash::{Hash, Hashable, Hasher, HASH_SIZE};
+
+///
+/// A sparse Merkle Tree over hashes of unspent outputs.
+///
+/// Hashes of outputs are used as paths in the tree, from the most significant bit.
+/// A subtree with a single output is replaced by the leaf, so the tree has
+/// the same shape and the same root regardless of the order of updates.
+///
+/// ```text
+///                root = h(n0 + l3)
+///              /                  \
+///      n0 = h(l1 + l2)        l3 = h(o3)      o3 = 1...
+///     /              \
+/// l1 = h(o1)    l2 = h(o2)                    o1 = 00..., o2 = 01...
+/// ```
+///
+/// Nodes are shared between copies of the tree, so clone() is cheap
+/// and updates of a copy don't affect the original tree.
+///
+#[derive(Clone, Debug)]
+pub struct UtxoTree {
+    root: Arc<Node>,
+}
+
+/// UTXO Tree Node.
+#[derive(Debug)]
+enum Node {
+    /// An empty subtree.
+    Empty,
+    /// A subtree with the single output.
+    Leaf(Hash),
+    /// A subtree with two or more outputs.
+    Inner {
+        hash: Hash,
+        left: Arc<Node>,
+        right: Arc<Node>,
+    },
+}
+
+/// A proof that an output is present or absent in UtxoTree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtxoProof {
+    /// Hashes of siblings from the root to the last node on the path.
+    pub siblings: Vec<Hash>,
+    /// The output stored in the last node on the path, None if the node is empty.
+    pub leaf: Option<Hash>,
+}
+
+impl Node {
+    fn hash(&self) -> Hash {
+        match self {
+            Node::Empty => Hash::zero(),
+            Node::Leaf(output_hash) => leaf_hash(output_hash),
+            Node::Inner { hash, .. } => *hash,
+        }
+    }
+
+    fn inner(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+        let hash = inner_hash(&left.hash(), &right.hash());
+        Arc::new(Node::Inner { hash, left, right })
+    }
+}
+
+impl UtxoTree {
+    /// Create an empty tree.
+    pub fn new() -> UtxoTree {
+        let root = Arc::new(Node::Empty);
+        UtxoTree { root }
+    }
+
+    /// Create a tree from hashes of outputs.
+    pub fn from_hashes<'a, I: IntoIterator<Item = &'a Hash>>(output_hashes: I) -> UtxoTree {
+        let mut tree = UtxoTree::new();
+        for output_hash in output_hashes {
+            tree.insert(output_hash);
+        }
+        tree
+    }
+
+    /// Returns the root hash, Hash::zero() for an empty tree.
+    pub fn roothash(&self) -> Hash {
+        self.root.hash()
+    }
+
+    /// Add an output. Returns false if the output is already present.
+    pub fn insert(&mut self, output_hash: &Hash) -> bool {
+        match UtxoTree::insert_r(&self.root, output_hash, 0) {
+            Some(root) => {
+                self.root = root;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove an output. Returns false if the output is not present.
+    pub fn remove(&mut self, output_hash: &Hash) -> bool {
+        match UtxoTree::remove_r(&self.root, output_hash, 0) {
+            Some(root) => {
+                self.root = root;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Create a proof that the output is present or absent, see UtxoProof::verify().
+    pub fn proof(&self, output_hash: &Hash) -> UtxoProof {
+        let mut siblings = Vec::new();
+        let mut node = &self.root;
+        loop {
+            match **node {
+                Node::Empty => {
+                    return UtxoProof {
+                        siblings,
+                        leaf: None,
+                    }
+                }
+                Node::Leaf(leaf) => {
+                    let leaf = Some(leaf);
+                    return UtxoProof { siblings, leaf };
+                }
+                Node::Inner {
+                    hash: _,
+                    ref left,
+                    ref right,
+                } => {
+                    if bit(output_hash, siblings.len()) {
+                        siblings.push(left.hash());
+                        node = right;
+                    } else {
+                        siblings.push(right.hash());
+                        node = left;
+                    }
+                }
+            }
+        }
+    }
+
+    /// A recursive helper for insert().
+    fn insert_r(node: &Arc<Node>, output_hash: &Hash, depth: usize) -> Option<Arc<Node>> {
+        match **node {
+            Node::Empty => Some(Arc::new(Node::Leaf(*output_hash))),
+            Node::Leaf(leaf) => {
+                if leaf == *output_hash {
+                    return None;
+                }
+                Some(UtxoTree::split(leaf, *output_hash, depth))
+            }
+            Node::Inner {
+                hash: _,
+                ref left,
+                ref right,
+            } => {
+                if bit(output_hash, depth) {
+                    let right = UtxoTree::insert_r(right, output_hash, depth + 1)?;
+                    Some(Node::inner(left.clone(), right))
+                } else {
+                    let left = UtxoTree::insert_r(left, output_hash, depth + 1)?;
+                    Some(Node::inner(left, right.clone()))
+                }
+            }
+        }
+    }
+
+    /// Create a subtree with two different outputs.
+    fn split(a: Hash, b: Hash, depth: usize) -> Arc<Node> {
+        let (bit_a, bit_b) = (bit(&a, depth), bit(&b, depth));
+        if bit_a != bit_b {
+            let (left, right) = if bit_a { (b, a) } else { (a, b) };
+            return Node::inner(Arc::new(Node::Leaf(left)), Arc::new(Node::Leaf(right)));
+        }
+        let child = UtxoTree::split(a, b, depth + 1);
+        let empty = Arc::new(Node::Empty);
+        if bit_a {
+            Node::inner(empty, child)
+        } else {
+            Node::inner(child, empty)
+        }
+    }
+
+    /// A recursive helper for remove().
+    fn remove_r(node: &Arc<Node>, output_hash: &Hash, depth: usize) -> Option<Arc<Node>> {
+        match **node {
+            Node::Empty => None,
+            Node::Leaf(leaf) => {
+                if leaf != *output_hash {
+                    return None;
+                }
+                Some(Arc::new(Node::Empty))
+            }
+            Node::Inner {
+                hash: _,
+                ref left,
+                ref right,
+            } => {
+                let (left, right) = if bit(output_hash, depth) {
+                    let right = UtxoTree::remove_r(right, output_hash, depth + 1)?;
+                    (left.clone(), right)
+                } else {
+                    let left = UtxoTree::remove_r(left, output_hash, depth + 1)?;
+                    (left, right.clone())
+                };
+                // Replace a subtree with the single output by the leaf.
+                match (&*left, &*right) {
+                    (Node::Empty, Node::Empty) => Some(Arc::new(Node::Empty)),
+                    (Node::Empty, Node::Leaf(_)) => Some(right),
+                    (Node::Leaf(_), Node::Empty) => Some(left),
+                    _ => Some(Node::inner(left, right)),
+                }
+            }
+        }
+    }
+}
+
+impl UtxoProof {
+    /// Verify the proof against the root hash of UtxoTree.
+    ///
+    /// Returns true if the output is present, false if the output is absent
+    /// and an error if the proof doesn't match the root.
+    ///
+    pub fn verify(&self, root: &Hash, output_hash: &Hash) -> Result<bool, BlockchainError> {
+        let depth = self.siblings.len();
+        if depth > HASH_SIZE * 8 {
+            return Err(BlockchainError::InvalidUtxoProof(*output_hash));
+        }
+
+        // The leaf must be on the path of the output.
+        let mut hash = match self.leaf {
+            Some(ref leaf) => {
+                if (0..depth).any(|i| bit(leaf, i) != bit(output_hash, i)) {
+                    return Err(BlockchainError::InvalidUtxoProof(*output_hash));
+                }
+                leaf_hash(leaf)
+            }
+            None => Hash::zero(),
+        };
+
+        for (i, sibling) in self.siblings.iter().enumerate().rev() {
+            hash = if bit(output_hash, i) {
+                inner_hash(sibling, &hash)
+            } else {
+                inner_hash(&hash, sibling)
+            };
+        }
+
+        if hash != *root {
+            return Err(BlockchainError::InvalidUtxoProof(*output_hash));
+        }
+        Ok(self.leaf == Some(*output_hash))
+    }
+}
+
+/// Returns the bit of hash at the position, from the most significant bit.
+fn bit(hash: &Hash, position: usize) -> bool {
+    let bytes = hash.base_vector();
+    (bytes[position / 8] >> (7 - position % 8)) & 1 == 1
+}
+
+fn leaf_hash(output_hash: &Hash) -> Hash {
+    let mut hasher = Hasher::new();
+    "Leaf".hash(&mut hasher);
+    output_hash.hash(&mut hasher);
+    hasher.result()
+}
+
+fn inner_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Hasher::new();
+    "Node".hash(&mut hasher);
+    left.hash(&mut hasher);
+    right.hash(&mut hasher);
+    hasher.result()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn hashes(count: usize) -> Vec<Hash> {
+        (0..count)
+            .map(|i| Hash::digest(&format!("output{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn insert_remove() {
+        let outputs = hashes(100);
+
+        let mut tree = UtxoTree::new();
+        assert_eq!(tree.roothash(), Hash::zero());
+        let mut roots = vec![tree.roothash()];
+        for output_hash in &outputs {
+            assert!(tree.insert(output_hash));
+            assert!(!tree.insert(output_hash));
+            roots.push(tree.roothash());
+        }
+
+        // The root doesn't depend on the order of updates.
+        let mut reversed = outputs.clone();
+        reversed.reverse();
+        assert_eq!(UtxoTree::from_hashes(&reversed).roothash(), tree.roothash());
+
+        // Copies are independent.
+        let copy = tree.clone();
+        for output_hash in outputs.iter().rev() {
+            assert_eq!(tree.roothash(), roots.pop().unwrap());
+            assert!(tree.remove(output_hash));
+            assert!(!tree.remove(output_hash));
+        }
+        assert_eq!(tree.roothash(), Hash::zero());
+        assert_eq!(copy.roothash(), UtxoTree::from_hashes(&outputs).roothash());
+    }
+
+    #[test]
+    fn proofs() {
+        let outputs = hashes(50);
+        let missing = hashes(60).split_off(50);
+        let tree = UtxoTree::from_hashes(&outputs);
+        let root = tree.roothash();
+
+        for output_hash in &outputs {
+            let proof = tree.proof(output_hash);
+            assert_eq!(proof.verify(&root, output_hash).unwrap(), true);
+            // A proof is valid only for its output.
+            let other = &missing[0];
+            assert!(proof.verify(&root, other).is_err() || !proof.verify(&root, other).unwrap());
+        }
+        for output_hash in &missing {
+            let proof = tree.proof(output_hash);
+            assert_eq!(proof.verify(&root, output_hash).unwrap(), false);
+        }
+
+        // Tampered proofs.
+        let output_hash = &outputs[0];
+        let mut proof = tree.proof(output_hash);
+        proof.siblings[0] = Hash::digest(&"tampered".to_string());
+        assert!(proof.verify(&root, output_hash).is_err());
+        let mut proof = tree.proof(output_hash);
+        proof.leaf = None;
+        assert!(proof.verify(&root, output_hash).is_err());
+        let proof = tree.proof(output_hash);
+        assert!(proof.verify(&Hash::zero(), output_hash).is_err());
+
+        // Empty tree.
+        let tree = UtxoTree::new();
+        let proof = tree.proof(output_hash);
+        assert_eq!(proof.verify(&Hash::zero(), output_hash).unwrap(), false);
+    }
+}
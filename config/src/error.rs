@@ -33,6 +33,8 @@ pub enum ConfigError {
     IOError(io::Error),
     /// Caused by parse errors.
     ParseError(toml::de::Error),
+    /// Caused by invalid values of options.
+    InvalidValue(String),
 }
 
 /// Display implementation for ConfigError.
@@ -42,6 +44,7 @@ impl fmt::Display for ConfigError {
             ConfigError::NotFoundError => write!(f, "Configuration file not found"),
             ConfigError::IOError(e) => write!(f, "Failed to read configuration file: {}", e),
             ConfigError::ParseError(e) => write!(f, "Failed to parse configuration file: {}", e),
+            ConfigError::InvalidValue(msg) => write!(f, "Invalid configuration: {}", msg),
         }
     }
 }
@@ -66,9 +66,23 @@ pub fn from_file<P: AsRef<Path>>(cfg_path: P) -> Result<Config, ConfigError> {
     }
     drop(f);
 
-    // Deserialize TOML and return result
-    match toml::from_str(&contents) {
-        Ok(cfg) => Ok(cfg),
-        Err(e) => Err(ConfigError::ParseError(e)),
+    // Deserialize TOML
+    let cfg: Config = match toml::from_str(&contents) {
+        Ok(cfg) => cfg,
+        Err(e) => return Err(ConfigError::ParseError(e)),
+    };
+
+    validate(&cfg)?;
+    Ok(cfg)
+}
+
+///
+/// Check values of options which can't be represented by types
+///
+fn validate(cfg: &Config) -> Result<(), ConfigError> {
+    if cfg.txpool.max_size == 0 {
+        let msg = "txpool.max_size must be greater than zero".to_string();
+        return Err(ConfigError::InvalidValue(msg));
     }
+    Ok(())
 }
@@ -41,6 +41,10 @@ pub struct Config {
     pub network: ConfigNetwork,
     /// Key Chain configuration.
     pub keychain: ConfigKeyChain,
+    /// Storage configuration.
+    pub storage: ConfigStorage,
+    /// Transaction Pool configuration.
+    pub txpool: ConfigTxPool,
 }
 
 /// Default values for global configuration.
@@ -50,6 +54,8 @@ impl Default for Config {
             general: Default::default(),
             network: Default::default(),
             keychain: Default::default(),
+            storage: Default::default(),
+            txpool: Default::default(),
         }
     }
 }
@@ -60,12 +66,15 @@ impl Default for Config {
 pub struct ConfigGeneral {
     /// Log4RS configuration file
     pub log4rs_config: String,
+    /// Chain specification file, an empty string for the development network.
+    pub chain_spec: String,
 }
 
 impl Default for ConfigGeneral {
     fn default() -> Self {
         ConfigGeneral {
             log4rs_config: "stegos-log4rs.toml".to_string(),
+            chain_spec: "".to_string(),
         }
     }
 }
@@ -89,6 +98,53 @@ impl Default for ConfigKeyChain {
     }
 }
 
+/// Storage Configuration.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfigStorage {
+    /// Path to the blockchain database.
+    pub database_path: String,
+    /// Download a snapshot of unspent outputs from peers instead of the full history.
+    pub fast_sync: bool,
+    /// Hash of a trusted block which a snapshot must include, in hex.
+    pub fast_sync_checkpoint: String,
+}
+
+impl Default for ConfigStorage {
+    fn default() -> Self {
+        ConfigStorage {
+            database_path: "stegos.db".to_string(),
+            fast_sync: false,
+            fast_sync_checkpoint: "".to_string(),
+        }
+    }
+}
+
+/// Transaction Pool Configuration.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfigTxPool {
+    /// The maximal total size of pending transactions in bytes, greater than zero.
+    pub max_size: usize,
+    /// The maximal time to keep a pending transaction in seconds.
+    pub max_age: u64,
+    /// Path to the file with pending transactions.
+    pub path: String,
+    /// The interval to save pending transactions in seconds.
+    pub save_interval: u64,
+}
+
+impl Default for ConfigTxPool {
+    fn default() -> Self {
+        ConfigTxPool {
+            max_size: 64 * 1024 * 1024,
+            max_age: 3600,
+            path: "stegos.txpool".to_string(),
+            save_interval: 60,
+        }
+    }
+}
+
 /// Network configuration.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(default)]
@@ -3,7 +3,7 @@
 set -e
 NUM_KEYS=${1:-3}
 
-rm -f stegos*.pkey stegos*.skey public-key.der private-key.pk8 genesis*.bin
+rm -f stegos*.pkey stegos*.skey public-key.der private-key.pk8 genesis*.bin chain.toml
 
 # Generate wallet keys
 cargo run -p stegos_node --bin bootstrap -- --keys $NUM_KEYS
@@ -28,3 +28,4 @@ done
 # Genesis block
 mkdir -p node/data
 mv genesis0.bin genesis1.bin node/data/
+mv chain.toml testing/
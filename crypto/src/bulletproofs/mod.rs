@@ -135,9 +135,13 @@ fn bits_vec(x: i64) -> ScalarVect {
 }
 
 fn random_vec() -> ScalarVect {
+    random_vec_with(&mut Int::random)
+}
+
+fn random_vec_with(random: &mut dyn FnMut() -> Int) -> ScalarVect {
     let mut v = bpvec!(Int::zero());
     for ix in 0..NBASIS {
-        v[ix] = Int::random().scaled();
+        v[ix] = random().scaled();
     }
     v
 }
@@ -395,6 +399,23 @@ impl BulletProof {
 // ------------------------------------------------------------------
 
 pub fn make_range_proof(v: i64) -> (BulletProof, Int) {
+    make_range_proof_with(v, &mut Int::random)
+}
+
+/// Make a range proof using deterministic randomness derived from the seed.
+///
+/// The same value and seed always produce the same proof and gamma.
+/// Blinding factors can be recovered by anyone who knows the seed,
+/// so the seed must be secret unless the output is public anyway, e.g. in genesis.
+pub fn make_deterministic_range_proof(v: i64, seed: &Hash) -> (BulletProof, Int) {
+    let mut counter: u64 = 0;
+    make_range_proof_with(v, &mut || {
+        counter += 1;
+        Int::synthetic_random("BulletProof", &counter, seed)
+    })
+}
+
+fn make_range_proof_with(v: i64, random: &mut dyn FnMut() -> Int) -> (BulletProof, Int) {
     fn make_lr_dot_proof(
         y: Int,
         mu: Int,
@@ -493,7 +514,8 @@ pub fn make_range_proof(v: i64) -> (BulletProof, Int) {
 
     assert!(*INIT, "Can't happen");
 
-    let (vcmt, gamma) = pedersen_commitment(v);
+    let gamma = random();
+    let vcmt = simple_commit(gamma, Int::from(v));
     let (gpt, gv, hv) = (BP.G, BP.GV, BP.HV);
 
     // Left/Right -- left (H) for values, right (G) for cloaking
@@ -503,14 +525,14 @@ pub fn make_range_proof(v: i64) -> (BulletProof, Int) {
     let mut a_l = bits_vec(v);
     let mut a_r = a_l;
     vec_decr(&mut a_r, Int::one());
-    let alpha = Int::random();
+    let alpha = random();
     // A = alpha*G + [a_r].[G_i] + [a_l].[H_i]
     let acmt = vec_commit(gpt, &gv, &hv, alpha, &a_r, &a_l);
 
     // form blinding factors
-    let s_l = random_vec();
-    let s_r = random_vec();
-    let rho = Int::random();
+    let s_l = random_vec_with(random);
+    let s_r = random_vec_with(random);
+    let rho = random();
     // S = rho*G + [s_r].[G_i] + [s_l].[H_i]
     let scmt = vec_commit(gpt, &gv, &hv, rho, &s_r, &s_l);
 
@@ -547,8 +569,8 @@ pub fn make_range_proof(v: i64) -> (BulletProof, Int) {
     let t1 = poly_t[1];
     let t2 = poly_t[2];
 
-    let tau1 = Int::random();
-    let tau2 = Int::random();
+    let tau1 = random();
+    let tau2 = random();
 
     let t1_cmt = simple_commit(tau1, t1); // T_1 = tau_1 * G + t_1 * H
     let t2_cmt = simple_commit(tau2, t2); // T_2 = tau_2 * G + t_2 * H
@@ -824,6 +846,20 @@ pub mod tests {
         assert!(validate_range_proof(&proof));
     }
 
+    #[test]
+    fn check_deterministic_bulletproofs() {
+        let seed = Hash::digest(&"seed".to_string());
+        let (proof, gamma) = make_deterministic_range_proof(1234567890, &seed);
+        assert!(validate_range_proof(&proof));
+        let (proof2, gamma2) = make_deterministic_range_proof(1234567890, &seed);
+        assert_eq!(proof.vcmt, proof2.vcmt);
+        assert_eq!(proof.tau_x, proof2.tau_x);
+        assert_eq!(gamma, gamma2);
+        let seed = Hash::digest(&"other".to_string());
+        let (proof3, _gamma3) = make_deterministic_range_proof(1234567890, &seed);
+        assert!(proof.vcmt != proof3.vcmt);
+    }
+
     #[test]
     #[should_panic]
     fn check_bad_bulletproofs() {
@@ -56,6 +56,16 @@ impl Broker {
         (service, broker)
     }
 
+    /// Create a Broker which isn't connected to the network, for tests.
+    ///
+    /// Published messages are dropped, subscribers never receive messages.
+    pub fn new_dummy() -> (impl Future<Item = (), Error = ()>, Broker) {
+        let (tx, rx) = mpsc::unbounded();
+        let service = rx.for_each(|_msg: PubsubMessage| Ok(()));
+        let broker = Broker { upstream: tx };
+        (service, broker)
+    }
+
     /// Subscribe to topic, returns Stream<Vec<u8>> of messages incoming to topic
     pub fn subscribe<S>(&self, topic: &S) -> Result<mpsc::UnboundedReceiver<Vec<u8>>, Error>
     where
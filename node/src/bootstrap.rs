@@ -19,13 +19,14 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use chrono::Utc;
 use clap::{crate_version, App, Arg};
 use log::*;
 use protobuf::Message;
 use simple_logger;
 use std::fs;
 use std::process;
-use stegos_blockchain::genesis;
+use stegos_blockchain::{Allocation, ChainSpec};
 use stegos_config::ConfigKeyChain;
 use stegos_keychain::KeyChain;
 use stegos_node::protos::IntoProto;
@@ -84,7 +85,16 @@ fn main() {
     }
 
     info!("Generating genesis blocks...");
-    let (key_block, monetary_block) = genesis(&keychains);
+    let chain_spec = ChainSpec {
+        version: 1,
+        timestamp: Utc::now().timestamp() as u64,
+        witnesses: keychains.iter().map(|k| k.cosi_pkey.clone()).collect(),
+        allocations: vec![Allocation {
+            recipient: keychains[0].wallet_pkey,
+            amount: 1_000_000,
+        }],
+    };
+    let (key_block, monetary_block) = chain_spec.genesis().expect("keys are valid");
     let key_block_data = key_block.into_proto();
     let key_block_data = key_block_data.write_to_bytes().unwrap();
     let monetary_block_data = monetary_block.into_proto();
@@ -93,6 +103,7 @@ fn main() {
     info!("Saving genesis blocks...");
     fs::write("genesis0.bin", &key_block_data).expect("failed to write genesis block");
     fs::write("genesis1.bin", &monetary_block_data).expect("failed to write genesis block");
+    fs::write("chain.toml", chain_spec.to_toml()).expect("failed to write chain spec");
 
     info!("Done");
 }
@@ -21,9 +21,13 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+mod mempool;
+mod orphans;
 pub mod protos;
 
-use crate::protos::{FromProto, IntoProto};
+use crate::mempool::{load_transactions, save_transactions};
+use crate::orphans::OrphanPool;
+use crate::protos::{FromProto, IntoProto, ProtoBlockCodec};
 
 use chrono::Utc;
 use failure::{Error, Fail};
@@ -32,10 +36,11 @@ use futures::{Async, Future, Poll, Stream};
 use log::*;
 use protobuf;
 use protobuf::Message;
+use std::cmp;
 use std::collections::HashMap;
-use std::collections::HashSet;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 use stegos_blockchain::*;
+use stegos_config::{ConfigStorage, ConfigTxPool};
 use stegos_crypto::curve1174::cpt::PublicKey;
 use stegos_crypto::curve1174::fields::Fr;
 use stegos_crypto::hash::Hash;
@@ -43,6 +48,7 @@ use stegos_crypto::pbc::secure::PublicKey as SecurePublicKey;
 use stegos_crypto::pbc::secure::G2;
 use stegos_keychain::KeyChain;
 use stegos_network::Broker;
+use stegos_txpool::TxPool;
 use tokio_timer::Interval;
 
 // ----------------------------------------------------------------
@@ -57,7 +63,13 @@ pub struct Node {
 
 impl Node {
     /// Create a new blockchain node.
+    ///
+    /// Genesis blocks are created from `chain_spec`, built-in blocks are used if it is None.
+    ///
     pub fn new(
+        cfg: &ConfigStorage,
+        txpool_cfg: &ConfigTxPool,
+        chain_spec: Option<&ChainSpec>,
         keys: KeyChain,
         broker: Broker,
     ) -> Result<(impl Future<Item = (), Error = ()>, Node), Error> {
@@ -65,7 +77,15 @@ impl Node {
 
         outbox.unbounded_send(NodeMessage::Init)?;
 
-        let service = NodeService::new(keys, broker, inbox, outbox.clone())?;
+        let service = NodeService::new(
+            cfg,
+            txpool_cfg,
+            chain_spec,
+            keys,
+            broker,
+            inbox,
+            outbox.clone(),
+        )?;
         let handler = Node { outbox };
 
         Ok((service, handler))
@@ -95,6 +115,22 @@ impl Node {
         Ok(rx)
     }
 
+    /// Subscribe to expired data outputs.
+    pub fn subscribe_expired(&self) -> Result<UnboundedReceiver<ExpiredNotification>, Error> {
+        let (tx, rx) = unbounded();
+        let msg = NodeMessage::SubscribeExpired(tx);
+        self.outbox.unbounded_send(msg)?;
+        Ok(rx)
+    }
+
+    /// Subscribe to supply audits.
+    pub fn subscribe_audit(&self) -> Result<UnboundedReceiver<AuditNotification>, Error> {
+        let (tx, rx) = unbounded();
+        let msg = NodeMessage::SubscribeAudit(tx);
+        self.outbox.unbounded_send(msg)?;
+        Ok(rx)
+    }
+
     /// Request a payment.
     pub fn pay(&self, recipient: PublicKey, amount: i64) -> Result<(), Error> {
         let msg = NodeMessage::PaymentRequest { recipient, amount };
@@ -112,6 +148,23 @@ impl Node {
         self.outbox.unbounded_send(msg)?;
         Ok(())
     }
+
+    /// Save the state to the disk before the exit.
+    ///
+    /// The returned channel is notified when the state is saved.
+    pub fn shutdown(&self) -> Result<UnboundedReceiver<()>, Error> {
+        let (tx, rx) = unbounded();
+        let msg = NodeMessage::Shutdown(tx);
+        self.outbox.unbounded_send(msg)?;
+        Ok(rx)
+    }
+
+    /// Check the total supply of money, see Blockchain::audit_supply().
+    pub fn audit_supply(&self) -> Result<(), Error> {
+        let msg = NodeMessage::AuditSupplyRequest;
+        self.outbox.unbounded_send(msg)?;
+        Ok(())
+    }
 }
 
 /// Send when epoch is changed.
@@ -128,6 +181,19 @@ pub struct MessageNotification {
     pub data: Vec<u8>,
 }
 
+/// Send when data UTXO is pruned by TTL.
+#[derive(Debug, Clone)]
+pub struct ExpiredNotification {
+    pub hash: Hash,
+}
+
+/// Send when the total supply of money is audited.
+#[derive(Debug, Clone)]
+pub struct AuditNotification {
+    /// The total supply or the reason of the failure.
+    pub supply: Result<i64, String>,
+}
+
 // ----------------------------------------------------------------
 // Internal Implementation.
 // ----------------------------------------------------------------
@@ -136,9 +202,26 @@ const VERSION: u64 = 1;
 const MEMPOOL_TTL: u64 = 15;
 const TX_TOPIC: &'static str = "tx";
 const BLOCK_TOPIC: &'static str = "block";
+const SNAPSHOT_REQUEST_TOPIC: &'static str = "snapshot_request";
+const SNAPSHOT_TOPIC: &'static str = "snapshot";
+const HEADERS_REQUEST_TOPIC: &'static str = "headers_request";
+const HEADERS_TOPIC: &'static str = "headers";
+const BLOCKS_REQUEST_TOPIC: &'static str = "blocks_request";
+const BLOCKS_TOPIC: &'static str = "blocks";
+const SYNC_MAX_HEADERS: usize = 1024;
+const SYNC_MAX_BLOCKS: usize = 64;
+const SNAPSHOT_MAX_SIZE: usize = 64 * 1024 * 1024;
+const HEADERS_MAX_SIZE: usize = 16 * 1024 * 1024;
+const REQUEST_MAX_SIZE: usize = 64 * 1024;
+const ORPHANS_MAX_SIZE: usize = 256;
+const ORPHANS_MAX_AGE: u64 = 300;
 const MONETARY_FEE: i64 = 1;
 const DATA_UNIT: u64 = 1024;
 const DATA_UNIT_FEE: i64 = 1;
+/// Space reserved in a block for the header, the fee output and the Merkle Tree.
+const BLOCK_SIZE_RESERVE: usize = 16 * 1024;
+/// The maximal size of a serialized transaction, any transaction fits into an empty block.
+const MAX_TRANSACTION_SIZE: usize = MAX_BLOCK_SIZE - BLOCK_SIZE_RESERVE;
 
 #[derive(Clone, Debug)]
 enum NodeMessage {
@@ -152,9 +235,13 @@ enum NodeMessage {
         ttl: u64,
         data: Vec<u8>,
     },
+    AuditSupplyRequest,
+    Shutdown(UnboundedSender<()>),
     SubscribeBalance(UnboundedSender<i64>),
     SubscribeEpoch(UnboundedSender<EpochNotification>),
     SubscribeMessage(UnboundedSender<MessageNotification>),
+    SubscribeExpired(UnboundedSender<ExpiredNotification>),
+    SubscribeAudit(UnboundedSender<AuditNotification>),
 }
 
 #[derive(Debug, Fail)]
@@ -165,11 +252,37 @@ pub enum NodeError {
     NotEnoughMoney,
     #[fail(display = "Fee is to low: min={}, got={}", _0, _1)]
     TooLowFee(i64, i64),
+    #[fail(display = "Stored blockchain has another genesis: expected={}, got={}", _0, _1)]
+    GenesisMismatch(Hash, Hash),
+    #[fail(display = "Invalid fast sync checkpoint: {}", _0)]
+    InvalidCheckpoint(String),
+    #[fail(display = "Snapshot is too large: size={}, max={}", _0, _1)]
+    SnapshotTooLarge(usize, usize),
+    #[fail(display = "Headers are too large: size={}, max={}", _0, _1)]
+    HeadersTooLarge(usize, usize),
+    #[fail(display = "Request is too large: size={}, max={}", _0, _1)]
+    RequestTooLarge(usize, usize),
+}
+
+/// Load genesis blocks of the development network.
+fn builtin_genesis() -> Result<(KeyBlock, MonetaryBlock), Error> {
+    let key_block = include_bytes!("../data/genesis0.bin");
+    let key_block: protos::node::KeyBlock = protobuf::parse_from_bytes(&key_block[..])?;
+    let key_block = KeyBlock::from_proto(&key_block)?;
+
+    let monetary_block = include_bytes!("../data/genesis1.bin");
+    let monetary_block: protos::node::MonetaryBlock =
+        protobuf::parse_from_bytes(&monetary_block[..])?;
+    let monetary_block = MonetaryBlock::from_proto(&monetary_block)?;
+
+    Ok((key_block, monetary_block))
 }
 
 struct NodeService {
     /// Blockchain.
     chain: Blockchain,
+    /// Genesis blocks.
+    genesis: (KeyBlock, MonetaryBlock),
     /// Key Chain.
     keys: KeyChain,
     /// Node's UXTO.
@@ -184,9 +297,15 @@ struct NodeService {
     /// The list of witnesses public keys.
     witnesses: Vec<SecurePublicKey>,
     /// Memory pool of pending transactions.
-    mempool: Vec<Transaction>,
-    /// Hashes of UTXO used by mempool transactions,
-    mempool_outputs: HashSet<Hash>,
+    mempool: TxPool,
+    /// The last epoch when pending transactions were offered to the leader.
+    offered_epoch: u64,
+    /// Path to the file with pending transactions, see ConfigTxPool::path.
+    mempool_path: String,
+    /// The interval to save pending transactions.
+    mempool_save_interval: Duration,
+    /// The last time when pending transactions were saved.
+    mempool_saved: Instant,
     /// Network interface.
     broker: Broker,
     /// MailBox.
@@ -198,6 +317,27 @@ struct NodeService {
     transaction_rx: UnboundedReceiver<Vec<u8>>,
     /// Blocks messages.
     block_rx: UnboundedReceiver<Vec<u8>>,
+    /// The trusted block which a snapshot must include, if waiting for a snapshot
+    /// of the blockchain, see ConfigStorage::fast_sync.
+    fast_sync: Option<Hash>,
+    /// Identifier of the outstanding snapshot request, echoed by peers.
+    snapshot_request_id: u64,
+    /// Snapshot requests.
+    snapshot_request_rx: UnboundedReceiver<Vec<u8>>,
+    /// Snapshot messages.
+    snapshot_rx: UnboundedReceiver<Vec<u8>>,
+    /// Blocks received before their parents.
+    orphans: OrphanPool,
+    /// The epoch to catch up with, None if no blocks are missing.
+    sync_epoch: Option<u64>,
+    /// Headers requests.
+    headers_request_rx: UnboundedReceiver<Vec<u8>>,
+    /// Headers messages.
+    headers_rx: UnboundedReceiver<Vec<u8>>,
+    /// Blocks requests.
+    blocks_request_rx: UnboundedReceiver<Vec<u8>>,
+    /// Blocks messages.
+    blocks_rx: UnboundedReceiver<Vec<u8>>,
     /// Timer.
     timer: Interval,
     /// Triggered when balance is changed.
@@ -206,33 +346,70 @@ struct NodeService {
     on_epoch_changed: Vec<UnboundedSender<EpochNotification>>,
     /// Triggered when message is received.
     on_message_received: Vec<UnboundedSender<MessageNotification>>,
+    /// Triggered when data UTXO is expired.
+    on_output_expired: Vec<UnboundedSender<ExpiredNotification>>,
+    /// Triggered when the supply is audited.
+    on_supply_audited: Vec<UnboundedSender<AuditNotification>>,
 }
 
 impl NodeService {
     /// Constructor.
     fn new(
+        cfg: &ConfigStorage,
+        txpool_cfg: &ConfigTxPool,
+        chain_spec: Option<&ChainSpec>,
         keys: KeyChain,
         broker: Broker,
         inbox: UnboundedReceiver<NodeMessage>,
         outbox: UnboundedSender<NodeMessage>,
     ) -> Result<Self, Error> {
-        let chain = Blockchain::new();
+        let chain = Blockchain::open(&cfg.database_path, Box::new(ProtoBlockCodec))?;
+        let genesis = match chain_spec {
+            Some(chain_spec) => chain_spec.genesis()?,
+            None => builtin_genesis()?,
+        };
         let balance = 0i64;
         let unspent = HashMap::new();
         let epoch: u64 = 1;
         let leader: SecurePublicKey = G2::generator().into(); // some fake key
         let witnesses = Vec::<SecurePublicKey>::new();
-        let mempool = Vec::<Transaction>::new();
-        let mempool_outputs = HashSet::<Hash>::new();
+        let mempool = TxPool::new(txpool_cfg.max_size, Duration::from_secs(txpool_cfg.max_age));
+        let offered_epoch: u64 = 0;
+        let mempool_path = txpool_cfg.path.clone();
+        let mempool_save_interval = Duration::from_secs(txpool_cfg.save_interval);
+        let mempool_saved = Instant::now();
         let transaction_rx = broker.subscribe(&TX_TOPIC.to_string())?;
         let block_rx = broker.subscribe(&BLOCK_TOPIC.to_string())?;
+        let fast_sync = if cfg.fast_sync {
+            match Hash::try_from_hex(&cfg.fast_sync_checkpoint) {
+                Ok(checkpoint) => Some(checkpoint),
+                Err(_) => {
+                    let checkpoint = cfg.fast_sync_checkpoint.clone();
+                    return Err(NodeError::InvalidCheckpoint(checkpoint).into());
+                }
+            }
+        } else {
+            None
+        };
+        let snapshot_request_id = rand::random::<u64>();
+        let snapshot_request_rx = broker.subscribe(&SNAPSHOT_REQUEST_TOPIC.to_string())?;
+        let snapshot_rx = broker.subscribe(&SNAPSHOT_TOPIC.to_string())?;
+        let orphans = OrphanPool::new(ORPHANS_MAX_SIZE, Duration::from_secs(ORPHANS_MAX_AGE));
+        let sync_epoch = None;
+        let headers_request_rx = broker.subscribe(&HEADERS_REQUEST_TOPIC.to_string())?;
+        let headers_rx = broker.subscribe(&HEADERS_TOPIC.to_string())?;
+        let blocks_request_rx = broker.subscribe(&BLOCKS_REQUEST_TOPIC.to_string())?;
+        let blocks_rx = broker.subscribe(&BLOCKS_TOPIC.to_string())?;
         let timer = Interval::new_interval(Duration::from_secs(MEMPOOL_TTL));
         let on_balance_changed = Vec::<UnboundedSender<i64>>::new();
         let on_epoch_changed = Vec::<UnboundedSender<EpochNotification>>::new();
         let on_message_received = Vec::<UnboundedSender<MessageNotification>>::new();
+        let on_output_expired = Vec::<UnboundedSender<ExpiredNotification>>::new();
+        let on_supply_audited = Vec::<UnboundedSender<AuditNotification>>::new();
 
         let service = NodeService {
             chain,
+            genesis,
             keys,
             balance,
             unspent,
@@ -240,16 +417,31 @@ impl NodeService {
             leader,
             witnesses,
             mempool,
-            mempool_outputs,
+            offered_epoch,
+            mempool_path,
+            mempool_save_interval,
+            mempool_saved,
             inbox,
             outbox,
             transaction_rx,
             block_rx,
+            fast_sync,
+            snapshot_request_id,
+            snapshot_request_rx,
+            snapshot_rx,
+            orphans,
+            sync_epoch,
+            headers_request_rx,
+            headers_rx,
+            blocks_request_rx,
+            blocks_rx,
             timer,
             broker,
             on_balance_changed,
             on_epoch_changed,
             on_message_received,
+            on_output_expired,
+            on_supply_audited,
         };
 
         Ok(service)
@@ -257,17 +449,20 @@ impl NodeService {
 
     /// Handler for NodeMessage::Init.
     fn handle_init(&mut self) -> Result<(), Error> {
-        info!("Registering genesis blocks...");
+        if let Some(block) = self.chain.blocks().first() {
+            let genesis_hash = Hash::digest(&self.genesis.0);
+            let block_hash = Hash::digest(block);
+            if block_hash != genesis_hash {
+                return Err(NodeError::GenesisMismatch(genesis_hash, block_hash).into());
+            }
+            self.recover()?;
+            self.load_mempool();
+            return self.request_snapshot();
+        }
 
-        // Load generated blocks
-        let key_block = include_bytes!("../data/genesis0.bin");
-        let key_block: protos::node::KeyBlock = protobuf::parse_from_bytes(&key_block[..])?;
-        let key_block = KeyBlock::from_proto(&key_block)?;
+        info!("Registering genesis blocks...");
 
-        let monetary_block = include_bytes!("../data/genesis1.bin");
-        let monetary_block: protos::node::MonetaryBlock =
-            protobuf::parse_from_bytes(&monetary_block[..])?;
-        let monetary_block = MonetaryBlock::from_proto(&monetary_block)?;
+        let (key_block, monetary_block) = self.genesis.clone();
 
         info!("Genesis key block: hash={}", Hash::digest(&key_block));
         info!(
@@ -275,19 +470,44 @@ impl NodeService {
             Hash::digest(&monetary_block)
         );
 
-        //
-        // Sic: genesis block has invalid monetary balance, so handle_monetary_block_request()
-        // can't be used here.
-        //
+        // Genesis blocks are validated as any other block.
+        let events = self.chain.push_block(Block::KeyBlock(key_block))?;
+        self.on_chain_changed(events);
+        let events = self.chain.push_block(Block::MonetaryBlock(monetary_block))?;
+        self.on_chain_changed(events);
+
+        self.request_snapshot()
+    }
 
-        let key_block2 = key_block.clone();
-        self.chain.register_key_block(key_block)?;
-        self.on_key_block_registered(&key_block2);
+    /// Restore the state from the blocks loaded from the disk.
+    ///
+    /// Only the wallet is rebuilt, subscribers are not notified and
+    /// data outputs are not acknowledged again.
+    ///
+    fn recover(&mut self) -> Result<(), Error> {
+        info!(
+            "Recovering from the stored blockchain: blocks={}",
+            self.chain.blocks().len()
+        );
 
-        let monetary_block2 = monetary_block.clone();
-        let inputs = self.chain.register_monetary_block(monetary_block)?;
-        self.on_monetary_block_registered(&monetary_block2, &inputs);
+        for block_id in 0..self.chain.blocks().len() {
+            // Sic: spent outputs are already pruned from stored blocks.
+            match self.chain.blocks()[block_id].clone() {
+                Block::KeyBlock(key_block) => self.on_key_block_registered(&key_block),
+                Block::MonetaryBlock(monetary_block) => {
+                    for (output, _) in monetary_block.body.outputs.leafs() {
+                        let hash = Hash::digest(output);
+                        self.on_output_recovered(hash, output);
+                    }
+                }
+            }
+        }
 
+        info!(
+            "Recovered: last_block={}, epoch={}",
+            Hash::digest(self.chain.last_block()),
+            self.epoch
+        );
         Ok(())
     }
 
@@ -325,125 +545,470 @@ impl NodeService {
         self.send_transaction(tx)
     }
 
+    /// Handler for NodeMessage::AuditSupplyRequest.
+    fn handle_audit_supply_request(&mut self) -> Result<(), Error> {
+        debug!("Received audit request");
+        let supply = match self.chain.audit_supply() {
+            Ok(supply) => {
+                info!(
+                    "Audited total supply: supply={}, blocks={}, unspent={}",
+                    supply,
+                    self.chain.blocks().len(),
+                    self.chain.unspent().len()
+                );
+                Ok(supply)
+            }
+            Err(e) => {
+                error!("Failed to audit total supply: {}", e);
+                Err(e.to_string())
+            }
+        };
+        let msg = AuditNotification { supply };
+        self.on_supply_audited
+            .retain(move |tx| tx.unbounded_send(msg.clone()).is_ok());
+        Ok(())
+    }
+
+    /// Handler for NodeMessage::Shutdown.
+    fn handle_shutdown(&mut self, tx: UnboundedSender<()>) -> Result<(), Error> {
+        info!("Shutting down");
+        if let Err(e) = self.save_mempool() {
+            error!(
+                "Failed to save mempool: path={}, error={}",
+                self.mempool_path, e
+            );
+        }
+        tx.unbounded_send(()).ok(); // ignore errors
+        Ok(())
+    }
+
     /// Handle incoming transactions received from network.
     fn handle_transaction_request(&mut self, msg: Vec<u8>) -> Result<(), Error> {
-        if !self.is_leader() {
-            return Ok(());
+        if msg.len() > MAX_TRANSACTION_SIZE {
+            return Err(
+                BlockchainError::TransactionTooLarge(msg.len(), MAX_TRANSACTION_SIZE).into(),
+            );
         }
-
         let tx: protos::node::Transaction = protobuf::parse_from_bytes(&msg)?;
         let tx = Transaction::from_proto(&tx)?;
 
         let tx_hash = Hash::digest(&tx.body);
+        if self.mempool.contains(&tx_hash) {
+            // Transactions are re-offered to each new leader, see offer_mempool().
+            debug!("Transaction is already in mempool: hash={}", &tx_hash);
+            return Ok(());
+        }
         info!("Received transaction: hash={}", &tx_hash);
+        self.queue_transaction(tx, msg.len(), SystemTime::now())
+    }
+
+    /// Validate transaction and queue it to mempool.
+    fn queue_transaction(
+        &mut self,
+        tx: Transaction,
+        size: usize,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        let tx_hash = Hash::digest(&tx.body);
         debug!("Validating transaction: hash={}..", &tx_hash);
 
+        // Check that transaction fits into a block, one output is reserved for the fee.
+        let inputs_count = tx.body.txins.len();
+        if inputs_count > MAX_BLOCK_INPUTS {
+            return Err(
+                BlockchainError::TooManyInputs(tx_hash, inputs_count, MAX_BLOCK_INPUTS).into(),
+            );
+        }
+        let outputs_count = tx.body.txouts.len();
+        if outputs_count > MAX_BLOCK_OUTPUTS - 1 {
+            return Err(BlockchainError::TooManyOutputs(
+                tx_hash,
+                outputs_count,
+                MAX_BLOCK_OUTPUTS - 1,
+            )
+            .into());
+        }
+
         // Check fee.
         NodeService::check_acceptable_fee(&tx)?;
 
-        // Resolve inputs.
-        let inputs = self.chain.outputs_by_hashes(&tx.body.txins)?;
+        // Validate against the chain and queue to mempool.
+        // Transactions spending the same UTXOs are replaced if this one pays more.
+        let min_fee = NodeService::min_fee(&tx);
+        self.mempool.insert(tx, size, min_fee, now, &self.chain)?;
+        info!("Queued to mempool: hash={}", &tx_hash);
 
-        // Validate monetary balance and signature.
-        tx.validate(&inputs)?;
-        info!("Transaction is valid: hash={}", &tx_hash);
+        Ok(())
+    }
 
-        // Check that UTXOs are not already queued to mempool.
-        for hash in &tx.body.txins {
-            if let Some(_) = self.mempool_outputs.get(hash) {
-                error!("UTXO is already queued to mempool: hash={}", &hash);
-                return Err(BlockchainError::MissingUTXO(hash.clone()).into());
-            }
+    /// Handle incoming blocks received from network.
+    fn handle_block_request(&mut self, msg: Vec<u8>) -> Result<(), Error> {
+        if msg.len() > MAX_BLOCK_SIZE {
+            return Err(BlockchainError::BlockTooLarge(msg.len(), MAX_BLOCK_SIZE).into());
+        }
+        let block: protos::node::Block = protobuf::parse_from_bytes(&msg)?;
+        let block = Block::from_proto(&block)?;
+
+        let block_hash = Hash::digest(&block);
+        info!("Received block: hash={}", &block_hash);
+
+        // Check that block is not registered yet.
+        if self.chain.contains_block(&block_hash) || self.orphans.contains(&block_hash) {
+            info!("Block is already registered: hash={}", &block_hash);
+            // Already registered, skip.
+            return Ok(());
+        }
+
+        self.register_block(block)
+    }
+
+    /// Register a block received from network.
+    ///
+    /// The block is kept in the orphan pool until its parent is registered.
+    /// Orphans waiting for this block are registered after it.
+    ///
+    fn register_block(&mut self, block: Block) -> Result<(), Error> {
+        let block_hash = Hash::digest(&block);
+
+        // Range hashes are checked before blocks are kept as orphans or side branches.
+        if let Block::MonetaryBlock(ref monetary_block) = block {
+            monetary_block.validate_range_hashes()?;
         }
+        let (previous, epoch) = {
+            let header = block.base_header();
+            (header.previous, header.epoch)
+        };
 
-        // Queue to mempool.
-        debug!("Queuing to mempool: hash={}", &tx_hash);
-        for hash in &tx.body.txins {
-            let nodup = self.mempool_outputs.insert(hash.clone());
-            assert!(nodup);
+        // The parent is missing, fetch it from peers.
+        if !self.chain.contains_block(&previous) {
+            self.orphans.insert(block, Instant::now());
+            warn!(
+                "Missing parent of block: hash={}, previous={}, orphans={}",
+                &block_hash,
+                &previous,
+                self.orphans.len()
+            );
+            return self.request_headers(epoch);
         }
-        self.mempool.push(tx);
 
+        // Check epoch of blocks extending the main chain.
+        // Blocks of side branches are checked by Blockchain::push_block().
+        let previous_hash = Hash::digest(self.chain.last_block());
+        if previous_hash == previous && self.epoch != epoch {
+            error!(
+                "Invalid or out-of-order block received: hash={}, expected_epoch={}, got_epoch={}",
+                &block_hash, self.epoch, epoch
+            );
+            return Ok(());
+        }
+
+        // The block is validated by Blockchain::validate_block() before registration.
+        let events = self.chain.push_block(block)?;
+        self.on_chain_changed(events);
+
+        for orphan in self.orphans.take_children(&block_hash) {
+            info!("Registering orphan block: hash={}", Hash::digest(&orphan));
+            if let Err(e) = self.register_block(orphan) {
+                error!("Invalid orphan block: {}", e);
+            }
+        }
         Ok(())
     }
 
-    /// Handle incoming KeyBlock
-    fn handle_key_block_request(&mut self, key_block: KeyBlock) -> Result<(), Error> {
-        let key_block2 = key_block.clone();
-        self.chain.register_key_block(key_block)?;
-        self.on_key_block_registered(&key_block2);
+    /// Ask peers for headers of blocks missing up to the epoch.
+    fn request_headers(&mut self, epoch: u64) -> Result<(), Error> {
+        let epoch = match self.sync_epoch {
+            Some(sync_epoch) if sync_epoch > epoch => sync_epoch,
+            _ => epoch,
+        };
+        self.sync_epoch = Some(epoch);
+
+        let from_epoch = self.chain.last_block().base_header().epoch;
+        info!(
+            "Requesting headers: from_epoch={}, to_epoch={}",
+            from_epoch, epoch
+        );
+        let mut request = protos::node::HeadersRequest::new();
+        request.set_from_epoch(from_epoch);
+        request.set_to_epoch(epoch);
+        let data = request.write_to_bytes()?;
+        self.broker.publish(&HEADERS_REQUEST_TOPIC.to_string(), data)?;
         Ok(())
     }
 
-    /// Handle incoming KeyBlock
-    fn handle_monetary_block_request(
-        &mut self,
-        monetary_block: MonetaryBlock,
-    ) -> Result<(), Error> {
-        let block_hash = Hash::digest(&monetary_block);
+    /// Handle headers requests received from network.
+    fn handle_headers_request(&mut self, msg: Vec<u8>) -> Result<(), Error> {
+        if msg.len() > REQUEST_MAX_SIZE {
+            return Err(NodeError::RequestTooLarge(msg.len(), REQUEST_MAX_SIZE).into());
+        }
+        let request: protos::node::HeadersRequest = protobuf::parse_from_bytes(&msg)?;
+        let (from_epoch, to_epoch) = (request.get_from_epoch(), request.get_to_epoch());
+        info!(
+            "Received headers request: from_epoch={}, to_epoch={}",
+            from_epoch, to_epoch
+        );
 
-        debug!("Validating block monetary balance: hash={}..", &block_hash);
+        let headers = self
+            .chain
+            .headers_by_epoch(from_epoch, to_epoch, SYNC_MAX_HEADERS);
+        if headers.is_empty() {
+            debug!("Nothing to send: from_epoch={}", from_epoch);
+            return Ok(());
+        }
 
-        // Resolve inputs.
-        let inputs = self.chain.outputs_by_hashes(&monetary_block.body.inputs)?;
+        info!("Sending headers: count={}", headers.len());
+        let mut proto = protos::node::Headers::new();
+        for header in &headers {
+            proto.headers.push(header.into_proto());
+        }
+        let data = proto.write_to_bytes()?;
+        if data.len() > HEADERS_MAX_SIZE {
+            return Err(NodeError::HeadersTooLarge(data.len(), HEADERS_MAX_SIZE).into());
+        }
+        self.broker.publish(&HEADERS_TOPIC.to_string(), data)?;
+        Ok(())
+    }
+
+    /// Handle headers received from network.
+    fn handle_headers(&mut self, msg: Vec<u8>) -> Result<(), Error> {
+        if self.sync_epoch.is_none() {
+            return Ok(());
+        }
 
-        // Validate monetary balance.
-        monetary_block.validate(&inputs)?;
+        if msg.len() > HEADERS_MAX_SIZE {
+            return Err(NodeError::HeadersTooLarge(msg.len(), HEADERS_MAX_SIZE).into());
+        }
+        let proto: protos::node::Headers = protobuf::parse_from_bytes(&msg)?;
+        info!("Received headers: count={}", proto.headers.len());
+
+        // Request blocks for unknown headers linked with the local chain.
+        let last_hash = Hash::digest(self.chain.last_block());
+        let mut previous: Option<Hash> = None;
+        let mut in_sync = false;
+        let mut hashes = Vec::<Hash>::new();
+        for header in proto.headers.iter() {
+            let header = BlockHeader::from_proto(header)?;
+            let header_hash = Hash::digest(&header);
+            if self.chain.contains_block(&header_hash) {
+                in_sync = header_hash == last_hash;
+                continue;
+            }
+            let linked = match previous {
+                Some(previous) => previous == header.base_header().previous,
+                None => self.chain.contains_block(&header.base_header().previous),
+            };
+            if !linked {
+                warn!("Headers don't link with the chain: hash={}", &header_hash);
+                break;
+            }
+            previous = Some(header_hash);
+            hashes.push(header_hash);
+            if hashes.len() >= SYNC_MAX_BLOCKS {
+                break;
+            }
+        }
 
-        info!("Block monetary balance is ok: hash={}", &block_hash);
+        if hashes.is_empty() {
+            if in_sync {
+                info!("Synchronized: last_block={}", &last_hash);
+                self.sync_epoch = None;
+            }
+            return Ok(());
+        }
 
-        let monetary_block2 = monetary_block.clone();
-        let inputs = self.chain.register_monetary_block(monetary_block)?;
-        self.on_monetary_block_registered(&monetary_block2, &inputs);
+        info!("Requesting blocks: count={}", hashes.len());
+        let mut request = protos::node::BlocksRequest::new();
+        for hash in &hashes {
+            request.hashes.push(hash.into_proto());
+        }
+        let data = request.write_to_bytes()?;
+        self.broker.publish(&BLOCKS_REQUEST_TOPIC.to_string(), data)?;
         Ok(())
     }
 
-    /// Handle incoming blocks received from network.
-    fn handle_block_request(&mut self, msg: Vec<u8>) -> Result<(), Error> {
-        let block: protos::node::Block = protobuf::parse_from_bytes(&msg)?;
-        let block = Block::from_proto(&block)?;
+    /// Handle blocks requests received from network.
+    fn handle_blocks_request(&mut self, msg: Vec<u8>) -> Result<(), Error> {
+        if msg.len() > REQUEST_MAX_SIZE {
+            return Err(NodeError::RequestTooLarge(msg.len(), REQUEST_MAX_SIZE).into());
+        }
+        let request: protos::node::BlocksRequest = protobuf::parse_from_bytes(&msg)?;
+        info!("Received blocks request: count={}", request.hashes.len());
+
+        let mut proto = protos::node::Blocks::new();
+        for hash in request.hashes.iter().take(SYNC_MAX_BLOCKS) {
+            let hash = Hash::from_proto(hash)?;
+            // Spent outputs are pruned only in memory, peers need the stored blocks.
+            let block = match self.chain.full_block_by_hash(&hash)? {
+                Some(block) => block,
+                None => {
+                    debug!("Unknown block: hash={}", &hash);
+                    break;
+                }
+            };
+            // Blocks restored from a snapshot are stored pruned, such blocks can't be validated
+            // by peers and are only available by a snapshot, see ConfigStorage::fast_sync.
+            if let Block::MonetaryBlock(ref monetary_block) = block {
+                if monetary_block.validate_range_hashes().is_err() {
+                    debug!("Block is pruned: hash={}", &hash);
+                    break;
+                }
+            }
+            proto.blocks.push(block.into_proto());
+        }
+        if proto.blocks.is_empty() {
+            return Ok(());
+        }
 
-        let block_hash = Hash::digest(&block);
-        info!("Received block: hash={}", &block_hash);
+        info!("Sending blocks: count={}", proto.blocks.len());
+        let data = proto.write_to_bytes()?;
+        self.broker.publish(&BLOCKS_TOPIC.to_string(), data)?;
+        Ok(())
+    }
 
-        // Check that block is not registered yet.
-        if let Some(_) = self.chain.block_by_hash(&block_hash) {
-            info!("Block is already registered: hash={}", &block_hash);
-            // Already registered, skip.
+    /// Handle blocks received from network.
+    fn handle_blocks(&mut self, msg: Vec<u8>) -> Result<(), Error> {
+        let sync_epoch = match self.sync_epoch {
+            Some(sync_epoch) => sync_epoch,
+            None => return Ok(()),
+        };
+
+        let max_size = SYNC_MAX_BLOCKS * MAX_BLOCK_SIZE;
+        if msg.len() > max_size {
+            return Err(BlockchainError::BlockTooLarge(msg.len(), max_size).into());
+        }
+        let proto: protos::node::Blocks = protobuf::parse_from_bytes(&msg)?;
+        info!("Received blocks: count={}", proto.blocks.len());
+
+        for block in proto.blocks.iter() {
+            let size = block.compute_size() as usize;
+            if size > MAX_BLOCK_SIZE {
+                return Err(BlockchainError::BlockTooLarge(size, MAX_BLOCK_SIZE).into());
+            }
+            let block = Block::from_proto(block)?;
+            let block_hash = Hash::digest(&block);
+            if self.chain.contains_block(&block_hash) {
+                continue;
+            }
+            self.register_block(block)?;
+        }
+
+        // Continue until the headers of peers match the local chain.
+        self.request_headers(sync_epoch)
+    }
+
+    /// Ask peers for a snapshot of the blockchain until it is received.
+    fn request_snapshot(&mut self) -> Result<(), Error> {
+        if self.fast_sync.is_none() {
+            return Ok(());
+        }
+        let last_hash = Hash::digest(self.chain.last_block());
+        info!("Requesting snapshot: last_block={}", &last_hash);
+        let mut request = protos::node::SnapshotRequest::new();
+        request.set_last_block(last_hash.into_proto());
+        request.set_request_id(self.snapshot_request_id);
+        let data = request.write_to_bytes()?;
+        self.broker.publish(&SNAPSHOT_REQUEST_TOPIC.to_string(), data)?;
+        Ok(())
+    }
+
+    /// Handle snapshot requests received from network.
+    fn handle_snapshot_request(&mut self, msg: Vec<u8>) -> Result<(), Error> {
+        // Only the leader answers to avoid flooding the network with snapshots.
+        if !self.is_leader() {
             return Ok(());
         }
 
+        if msg.len() > REQUEST_MAX_SIZE {
+            return Err(NodeError::RequestTooLarge(msg.len(), REQUEST_MAX_SIZE).into());
+        }
+        let request: protos::node::SnapshotRequest = protobuf::parse_from_bytes(&msg)?;
+        let last_hash = Hash::from_proto(request.get_last_block())?;
+        info!("Received snapshot request: last_block={}", &last_hash);
+
+        // The requester must have a prefix of the main chain.
+        if last_hash == Hash::digest(self.chain.last_block())
+            || self.chain.block_by_hash(&last_hash).is_none()
         {
-            let header = block.base_header();
+            debug!("Nothing to send: last_block={}", &last_hash);
+            return Ok(());
+        }
 
-            // Check previous hash.
-            let previous_hash = Hash::digest(self.chain.last_block());
-            if previous_hash != header.previous {
-                error!("Invalid or out-of-order block received: hash={}, expected_previous={}, got_previous={}",
-                       &block_hash, &previous_hash, &header.previous);
-                return Ok(());
-            }
+        let snapshot = self.chain.snapshot();
+        info!(
+            "Sending snapshot: epoch={}, blocks={}",
+            snapshot.epoch(),
+            snapshot.blocks.len()
+        );
+        let mut proto = snapshot.into_proto();
+        proto.set_request_id(request.get_request_id());
+        let data = proto.write_to_bytes()?;
+        if data.len() > SNAPSHOT_MAX_SIZE {
+            return Err(NodeError::SnapshotTooLarge(data.len(), SNAPSHOT_MAX_SIZE).into());
+        }
+        self.broker.publish(&SNAPSHOT_TOPIC.to_string(), data)?;
+        Ok(())
+    }
 
-            // Check epoch.
-            if self.epoch != header.epoch {
-                error!("Invalid or out-of-order block received: hash={}, expected_epoch={}, got_epoch={}",
-                       &block_hash, self.epoch, header.epoch);
-                return Ok(());
-            }
+    /// Handle snapshots received from network.
+    fn handle_snapshot(&mut self, msg: Vec<u8>) -> Result<(), Error> {
+        let checkpoint = match self.fast_sync {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(()),
+        };
 
-            // TODO: check CoSi signature
+        if msg.len() > SNAPSHOT_MAX_SIZE {
+            return Err(NodeError::SnapshotTooLarge(msg.len(), SNAPSHOT_MAX_SIZE).into());
+        }
+        let snapshot: protos::node::Snapshot = protobuf::parse_from_bytes(&msg)?;
+        // Only snapshots sent in reply to our request are accepted.
+        if snapshot.get_request_id() != self.snapshot_request_id {
+            debug!("Ignored unsolicited snapshot");
+            return Ok(());
         }
+        let snapshot = Snapshot::from_proto(&snapshot)?;
+        info!(
+            "Received snapshot: epoch={}, blocks={}",
+            snapshot.epoch(),
+            snapshot.blocks.len()
+        );
 
-        match block {
-            Block::KeyBlock(key_block) => self.handle_key_block_request(key_block),
-            Block::MonetaryBlock(monetary_block) => {
-                self.handle_monetary_block_request(monetary_block)
-            }
+        // The snapshot is validated by Blockchain::restore_snapshot().
+        let events = self.chain.restore_snapshot(snapshot, &checkpoint)?;
+        if !events.is_empty() {
+            info!(
+                "Restored snapshot: last_block={}",
+                Hash::digest(self.chain.last_block())
+            );
+            // Follow new blocks from now.
+            self.fast_sync = None;
         }
+        self.on_chain_changed(events);
+        Ok(())
     }
 
     /// Handle period timer.
     fn handle_timer(&mut self) -> Result<(), Error> {
+        self.request_snapshot()?;
+        for block_hash in self.orphans.expire(Instant::now()) {
+            warn!("Dropped orphan block: hash={}", &block_hash);
+        }
+        for tx_hash in self.mempool.expire(SystemTime::now()) {
+            warn!("Dropped stale transaction: hash={}", &tx_hash);
+        }
+        if let Some(sync_epoch) = self.sync_epoch {
+            // Retry if the previous request was lost.
+            self.request_headers(sync_epoch)?;
+        }
+        if self.mempool_saved.elapsed() >= self.mempool_save_interval {
+            // The node keeps working without the disk copy of the mempool.
+            if let Err(e) = self.save_mempool() {
+                error!(
+                    "Failed to save mempool: path={}, error={}",
+                    self.mempool_path, e
+                );
+            }
+        }
+        self.offer_mempool()?;
         self.process_mempool()?;
 
         Ok(())
@@ -476,6 +1041,16 @@ impl NodeService {
         self.on_message_received.push(tx);
     }
 
+    /// Handler for NodeMessage::SubscribeExpired.
+    fn handle_subscribe_expired(&mut self, tx: UnboundedSender<ExpiredNotification>) {
+        self.on_output_expired.push(tx);
+    }
+
+    /// Handler for NodeMessage::SubscribeAudit.
+    fn handle_subscribe_audit(&mut self, tx: UnboundedSender<AuditNotification>) {
+        self.on_supply_audited.push(tx);
+    }
+
     /// Called when balance is changed.
     fn update_balance(&mut self, amount: i64) {
         self.balance += amount;
@@ -504,8 +1079,82 @@ impl NodeService {
         }
     }
 
+    /// Called when a key block is removed from the main chain.
+    fn on_key_block_reverted(&mut self, key_block: &KeyBlock) {
+        info!("Reverted key block: hash={}", Hash::digest(key_block));
+        self.epoch = self.epoch - 1;
+        // Restore the previous leader.
+        for block in self.chain.blocks().iter().rev() {
+            if let Block::KeyBlock(key_block) = block {
+                self.leader = key_block.header.leader.clone();
+                self.witnesses = key_block.header.witnesses.clone();
+                break;
+            }
+        }
+    }
+
+    /// Called when the main chain is changed by Blockchain::push_block().
+    fn on_chain_changed(&mut self, events: Vec<ChainEvent>) {
+        let mut reverted = false;
+        for event in events {
+            match event {
+                ChainEvent::Registered {
+                    block: Block::KeyBlock(key_block),
+                    pruned: _,
+                    expired: _,
+                } => self.on_key_block_registered(&key_block),
+                ChainEvent::Registered {
+                    block: Block::MonetaryBlock(monetary_block),
+                    pruned,
+                    expired,
+                } => self.on_monetary_block_registered(&monetary_block, &pruned, &expired),
+                ChainEvent::Reverted {
+                    block: Block::KeyBlock(key_block),
+                    restored: _,
+                } => {
+                    reverted = true;
+                    self.on_key_block_reverted(&key_block)
+                }
+                ChainEvent::Reverted {
+                    block: Block::MonetaryBlock(monetary_block),
+                    restored,
+                } => {
+                    reverted = true;
+                    self.on_monetary_block_reverted(&monetary_block, &restored)
+                }
+            }
+        }
+
+        // Drop transactions which spend outputs of reverted blocks.
+        if reverted {
+            for tx_hash in self.mempool.revalidate(&self.chain) {
+                warn!("Dropped transaction: hash={}", &tx_hash);
+            }
+        }
+    }
+
     /// Called when a new key block is registered.
-    fn on_monetary_block_registered(&mut self, monetary_block: &MonetaryBlock, inputs: &[Output]) {
+    fn on_monetary_block_registered(
+        &mut self,
+        monetary_block: &MonetaryBlock,
+        inputs: &[Output],
+        expired: &[Output],
+    ) {
+        // Drop transactions which are included into the block or conflict with it.
+        for tx_hash in self.mempool.remove_spent(&monetary_block.body.inputs) {
+            debug!("Removed transaction from mempool: hash={}", &tx_hash);
+        }
+        let created: Vec<Hash> = monetary_block
+            .body
+            .outputs
+            .leafs()
+            .iter()
+            .map(|(o, _path)| Hash::digest(*o))
+            .collect();
+        for tx_hash in self.mempool.remove_created(&created) {
+            debug!("Removed transaction from mempool: hash={}", &tx_hash);
+        }
+
         //
         // Notify subscribers.
         //
@@ -515,7 +1164,30 @@ impl NodeService {
             self.on_output_pruned(hash, input);
         }
 
+        for output in expired {
+            let hash = Hash::digest(output);
+            self.on_output_expired(hash, output);
+        }
+
+        for (output, _) in monetary_block.body.outputs.leafs() {
+            let hash = Hash::digest(output);
+            self.on_output_created(hash, output);
+        }
+    }
+
+    /// Called when a monetary block is removed from the main chain.
+    fn on_monetary_block_reverted(&mut self, monetary_block: &MonetaryBlock, restored: &[Output]) {
+        info!(
+            "Reverted monetary block: hash={}",
+            Hash::digest(monetary_block)
+        );
+
         for (output, _) in monetary_block.body.outputs.leafs() {
+            let hash = Hash::digest(output);
+            self.on_output_pruned(hash, output);
+        }
+
+        for output in restored {
             let hash = Hash::digest(output);
             self.on_output_created(hash, output);
         }
@@ -557,6 +1229,18 @@ impl NodeService {
         }
     }
 
+    /// Called for UTXO of stored blocks by recover().
+    fn on_output_recovered(&mut self, hash: Hash, output: &Output) {
+        if let Output::MonetaryOutput(output) = output {
+            if let Ok((_delta, _gamma, amount)) = output.decrypt_payload(&self.keys.wallet_skey) {
+                info!("Recovered monetary UTXO: hash={}, amount={}", hash, amount);
+                let missing = self.unspent.insert(hash, amount);
+                assert_eq!(missing, None);
+                self.update_balance(amount);
+            }
+        }
+    }
+
     /// Called when UTXO is spent.
     fn on_output_pruned(&mut self, hash: Hash, output: &Output) {
         match output {
@@ -581,6 +1265,22 @@ impl NodeService {
         }
     }
 
+    /// Called when data UTXO is pruned by TTL.
+    fn on_output_expired(&mut self, hash: Hash, output: &Output) {
+        info!("Expired UTXO: hash={}", hash);
+        self.on_output_pruned(hash, output);
+
+        // Drop transactions which spend this UTXO.
+        for tx_hash in self.mempool.remove_spent(&[hash]) {
+            warn!("Dropped transaction: hash={}", &tx_hash);
+        }
+
+        // Notify subscribers.
+        let msg = ExpiredNotification { hash };
+        self.on_output_expired
+            .retain(move |tx| tx.unbounded_send(msg.clone()).is_ok());
+    }
+
     /// Send transaction to network.
     fn send_transaction(&mut self, tx: Transaction) -> Result<(), Error> {
         info!("Sending transaction: hash={}", Hash::digest(&tx.body));
@@ -611,6 +1311,17 @@ impl NodeService {
 
     /// Check minimal acceptable fee for transaction.
     fn check_acceptable_fee(tx: &Transaction) -> Result<(), NodeError> {
+        let min_fee = NodeService::min_fee(tx);
+
+        // Transaction's fee is too low.
+        if tx.body.fee < min_fee {
+            return Err(NodeError::TooLowFee(min_fee, tx.body.fee));
+        }
+        Ok(())
+    }
+
+    /// Calculate the minimal acceptable fee for transaction.
+    fn min_fee(tx: &Transaction) -> i64 {
         let mut min_fee: i64 = 0;
         for txout in &tx.body.txouts {
             min_fee += match txout {
@@ -618,12 +1329,7 @@ impl NodeService {
                 Output::DataOutput(o) => NodeService::data_fee(o.data_size(), o.ttl),
             };
         }
-
-        // Transaction's fee is too low.
-        if tx.body.fee < min_fee {
-            return Err(NodeError::TooLowFee(min_fee, tx.body.fee));
-        }
-        Ok(())
+        min_fee
     }
 
     /// Find UTXO with exact value.
@@ -828,10 +1534,99 @@ impl NodeService {
         Ok(tx)
     }
 
+    /// Load pending transactions saved by save_mempool() and validate them against the chain.
+    fn load_mempool(&mut self) {
+        let transactions = match load_transactions(&self.mempool_path) {
+            Ok(transactions) => transactions,
+            Err(e) => {
+                error!(
+                    "Failed to load mempool: path={}, error={}",
+                    self.mempool_path, e
+                );
+                return;
+            }
+        };
+
+        // Stale transactions are dropped by the next expire().
+        for (tx, arrived) in transactions {
+            let tx_hash = Hash::digest(&tx.body);
+            let size = tx.into_proto().compute_size() as usize;
+            if let Err(e) = self.queue_transaction(tx, size, arrived) {
+                warn!("Dropped transaction: hash={}, error={}", &tx_hash, e);
+            }
+        }
+        info!(
+            "Loaded mempool: path={}, size={}",
+            self.mempool_path,
+            self.mempool.len()
+        );
+    }
+
+    /// Save pending transactions to the disk.
+    fn save_mempool(&mut self) -> Result<(), Error> {
+        debug!(
+            "Saving mempool: path={}, size={}",
+            self.mempool_path,
+            self.mempool.len()
+        );
+        self.mempool_saved = Instant::now();
+        save_transactions(&self.mempool_path, self.mempool.iter_by_arrival())
+    }
+
+    /// Send pending transactions to the leader once per epoch.
+    ///
+    /// The new leader could miss some transactions, so they are re-offered
+    /// after the change of epoch. Each transaction is re-offered by only one witness,
+    /// chosen by the hash of the transaction, and each witness re-offers
+    /// at most the size of a block per epoch, the best transactions first.
+    ///
+    fn offer_mempool(&mut self) -> Result<(), Error> {
+        if self.offered_epoch == self.epoch {
+            return Ok(());
+        }
+        self.offered_epoch = self.epoch;
+        if self.is_leader() || self.mempool.is_empty() {
+            return Ok(());
+        }
+
+        let leader = &self.leader;
+        let offerers: Vec<&SecurePublicKey> =
+            self.witnesses.iter().filter(|w| *w != leader).collect();
+        let position = match offerers.iter().position(|w| **w == self.keys.cosi_pkey) {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let mut size: usize = 0;
+        let mut count: usize = 0;
+        for tx in self.mempool.iter() {
+            let tx_hash = Hash::digest(&tx.body);
+            let offerer = tx_hash
+                .base_vector()
+                .iter()
+                .fold(0usize, |acc, b| (acc * 256 + *b as usize) % offerers.len());
+            if offerer != position {
+                continue;
+            }
+            let data = tx.into_proto().write_to_bytes()?;
+            if size + data.len() > MAX_BLOCK_SIZE - BLOCK_SIZE_RESERVE {
+                break;
+            }
+            size += data.len();
+            count += 1;
+            self.broker.publish(&TX_TOPIC.to_string(), data)?;
+        }
+
+        info!(
+            "Offered mempool to the leader: epoch={}, count={}, size={}",
+            self.epoch, count, size
+        );
+        Ok(())
+    }
+
     /// Process transactions in mempool.
     fn process_mempool(&mut self) -> Result<(), Error> {
         if !self.is_leader() {
-            assert!(self.mempool.is_empty());
             return Ok(());
         }
 
@@ -840,14 +1635,26 @@ impl NodeService {
         }
 
         info!("Processing mempool: size={}", self.mempool.len());
+        // The timestamp can't be before the median time past, even if the local clock is behind.
         let timestamp = Utc::now().timestamp() as u64;
+        let timestamp = cmp::max(timestamp, self.chain.median_time_past());
         let mut gamma = Fr::zero();
         let mut fee = 0i64;
 
+        // Take transactions with the highest fee per byte first while they fit into the block.
+        // The rest is left for the next block. One output is reserved for the fee.
+        // Transactions are removed from mempool only when the block is registered.
+        let txs = self.mempool.select(
+            MAX_BLOCK_INPUTS,
+            MAX_BLOCK_OUTPUTS - 1,
+            MAX_BLOCK_SIZE - BLOCK_SIZE_RESERVE,
+        );
+        assert!(!txs.is_empty(), "any transaction fits into an empty block");
+
         let mut inputs = Vec::<Output>::new();
         let mut inputs_hashes = Vec::<Hash>::new();
         let mut outputs = Vec::<Output>::new();
-        for tx in self.mempool.drain(..) {
+        for tx in txs {
             let tx_hash = Hash::digest(&tx.body);
             info!("Adding transaction: hash={}", &tx_hash);
             let tx_inputs = self
@@ -855,18 +1662,12 @@ impl NodeService {
                 .outputs_by_hashes(&tx.body.txins)
                 .expect("mempool transaction are validated before");
             inputs.extend(tx_inputs);
-            for tx_input in &tx.body.txins {
-                let exists = self.mempool_outputs.remove(tx_input);
-                assert!(exists);
-            }
             inputs_hashes.extend(tx.body.txins);
             outputs.extend(tx.body.txouts);
 
             gamma += tx.body.gamma;
             fee += tx.body.fee;
         }
-        assert!(self.mempool.is_empty());
-        assert!(self.mempool_outputs.is_empty());
 
         // Create transaction for fee
         if fee > 0 {
@@ -895,10 +1696,16 @@ impl NodeService {
         let epoch = self.epoch;
 
         let base = BaseBlockHeader::new(VERSION, previous, epoch, timestamp);
-        let block = MonetaryBlock::new(base, gamma, &inputs_hashes, &outputs);
+        let utxo_root = self.chain.next_utxo_root(epoch, &inputs_hashes, &outputs);
+        let block = MonetaryBlock::new(base, gamma, &inputs_hashes, &outputs, utxo_root, 0);
 
-        // Double-check the monetary balance of created block.
+        // Double-check limits and the monetary balance of created block.
+        block.validate_limits()?;
         block.validate(&inputs)?;
+        let size = block.into_proto().compute_size() as usize;
+        if size > MAX_BLOCK_SIZE {
+            return Err(BlockchainError::BlockTooLarge(size, MAX_BLOCK_SIZE).into());
+        }
 
         info!("Created block: hash={}", Hash::digest(&block));
 
@@ -909,11 +1716,11 @@ impl NodeService {
         //
 
         let block2 = block.clone();
-        let pruned = self
+        let (pruned, expired) = self
             .chain
             .register_monetary_block(block)
             .expect("mempool transaction are validated before");
-        self.on_monetary_block_registered(&block2, &pruned);
+        self.on_monetary_block_registered(&block2, &pruned, &expired);
         self.send_block(Block::MonetaryBlock(block2))?;
 
         Ok(())
@@ -934,12 +1741,22 @@ impl NodeService {
                             ttl,
                             data,
                         } => self.handle_message_request(&recipient, ttl, data),
+                        NodeMessage::AuditSupplyRequest => self.handle_audit_supply_request(),
+                        NodeMessage::Shutdown(tx) => self.handle_shutdown(tx),
                         NodeMessage::SubscribeBalance(tx) => self.handle_subscribe_balance(tx),
                         NodeMessage::SubscribeEpoch(tx) => self.handle_subscribe_epoch(tx),
                         NodeMessage::SubscribeMessage(tx) => {
                             self.handle_subscribe_message(tx);
                             Ok(())
                         }
+                        NodeMessage::SubscribeExpired(tx) => {
+                            self.handle_subscribe_expired(tx);
+                            Ok(())
+                        }
+                        NodeMessage::SubscribeAudit(tx) => {
+                            self.handle_subscribe_audit(tx);
+                            Ok(())
+                        }
                     }
                 } {
                     error!("Error: {}", e)
@@ -976,6 +1793,78 @@ impl NodeService {
             }
         }
 
+        loop {
+            match self.snapshot_request_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => if let Err(e) = self.handle_snapshot_request(msg) {
+                    // Ignore invalid packets.
+                    error!("Invalid request: {}", e);
+                },
+                Ok(Async::Ready(None)) => break, // channel closed, fall through
+                Ok(Async::NotReady) => break,    // not ready, fall through
+                Err(()) => unreachable!(),       // never happens
+            }
+        }
+
+        loop {
+            match self.snapshot_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => if let Err(e) = self.handle_snapshot(msg) {
+                    // Ignore invalid packets.
+                    error!("Invalid request: {}", e);
+                },
+                Ok(Async::Ready(None)) => break, // channel closed, fall through
+                Ok(Async::NotReady) => break,    // not ready, fall through
+                Err(()) => unreachable!(),       // never happens
+            }
+        }
+
+        loop {
+            match self.headers_request_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => if let Err(e) = self.handle_headers_request(msg) {
+                    // Ignore invalid packets.
+                    error!("Invalid request: {}", e);
+                },
+                Ok(Async::Ready(None)) => break, // channel closed, fall through
+                Ok(Async::NotReady) => break,    // not ready, fall through
+                Err(()) => unreachable!(),       // never happens
+            }
+        }
+
+        loop {
+            match self.headers_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => if let Err(e) = self.handle_headers(msg) {
+                    // Ignore invalid packets.
+                    error!("Invalid request: {}", e);
+                },
+                Ok(Async::Ready(None)) => break, // channel closed, fall through
+                Ok(Async::NotReady) => break,    // not ready, fall through
+                Err(()) => unreachable!(),       // never happens
+            }
+        }
+
+        loop {
+            match self.blocks_request_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => if let Err(e) = self.handle_blocks_request(msg) {
+                    // Ignore invalid packets.
+                    error!("Invalid request: {}", e);
+                },
+                Ok(Async::Ready(None)) => break, // channel closed, fall through
+                Ok(Async::NotReady) => break,    // not ready, fall through
+                Err(()) => unreachable!(),       // never happens
+            }
+        }
+
+        loop {
+            match self.blocks_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => if let Err(e) = self.handle_blocks(msg) {
+                    // Ignore invalid packets.
+                    error!("Invalid request: {}", e);
+                },
+                Ok(Async::Ready(None)) => break, // channel closed, fall through
+                Ok(Async::NotReady) => break,    // not ready, fall through
+                Err(()) => unreachable!(),       // never happens
+            }
+        }
+
         // Process timer events
         loop {
             match self.timer.poll() {
@@ -1008,6 +1897,156 @@ impl Future for NodeService {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::mempool::tests::temp_path;
+    use std::path::Path;
+
+    /// Create a node with the database at `database_path`, not connected to the network.
+    ///
+    /// `genesis` must be created for `keys`, so the node is the leader and gets all money.
+    /// The broker must be kept alive.
+    fn test_node(
+        database_path: &Path,
+        txpool_cfg: &ConfigTxPool,
+        keys: &KeyChain,
+        genesis: &(KeyBlock, MonetaryBlock),
+    ) -> (NodeService, impl Future<Item = (), Error = ()>) {
+        let cfg = ConfigStorage {
+            database_path: database_path.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let (broker_service, broker) = Broker::new_dummy();
+        let (outbox, inbox) = unbounded();
+        let mut node =
+            NodeService::new(&cfg, txpool_cfg, None, keys.clone(), broker, inbox, outbox).unwrap();
+        node.genesis = genesis.clone();
+        node.handle_init().unwrap();
+        assert!(node.is_leader());
+        (node, broker_service)
+    }
+
+    /// Check that the node keeps working if the mempool can't be saved.
+    #[test]
+    pub fn save_mempool_failure() {
+        let keys = KeyChain::new_mem();
+        let genesis = genesis(&[keys.clone()]);
+        let txpool_cfg = ConfigTxPool {
+            path: temp_path("missing")
+                .join("stegos.txpool")
+                .to_string_lossy()
+                .to_string(),
+            save_interval: 0,
+            ..Default::default()
+        };
+        let path = temp_path("save_mempool_failure");
+        let (mut node, _broker_service) = test_node(&path, &txpool_cfg, &keys, &genesis);
+        assert!(node.save_mempool().is_err());
+        node.handle_timer().unwrap();
+        node.handle_timer().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Check that transactions are kept in mempool if a block can't be created.
+    #[test]
+    pub fn process_mempool_failure() {
+        let keys = KeyChain::new_mem();
+        let genesis = genesis(&[keys.clone()]);
+        let path = temp_path("process_mempool_failure");
+        let (mut node, _broker_service) = test_node(&path, &Default::default(), &keys, &genesis);
+        let height = node.chain.blocks().len();
+        let now = SystemTime::now();
+
+        // The transaction claims a smaller size than the created block has.
+        let data = vec![0u8; MAX_BLOCK_SIZE];
+        let tx = node
+            .create_data_transaction(&keys.wallet_pkey, 1, data)
+            .unwrap();
+        let tx_hash = Hash::digest(&tx.body);
+        node.queue_transaction(tx, 1024, now).unwrap();
+        match node.process_mempool() {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::BlockTooLarge(_, max) => assert_eq!(max, MAX_BLOCK_SIZE),
+                e => panic!("{}", e),
+            },
+            Ok(()) => panic!("block created"),
+        }
+        assert_eq!(node.chain.blocks().len(), height);
+        assert_eq!(node.mempool.len(), 1);
+        assert!(node.mempool.contains(&tx_hash));
+
+        // Transactions are removed when the block is registered.
+        node.mempool.remove(&tx_hash).unwrap();
+        let tx = node
+            .create_monetary_transaction(&keys.wallet_pkey, 100)
+            .unwrap();
+        let tx_hash = Hash::digest(&tx.body);
+        node.queue_transaction(tx, 1024, now).unwrap();
+        node.process_mempool().unwrap();
+        assert_eq!(node.chain.blocks().len(), height + 1);
+        assert!(!node.mempool.contains(&tx_hash));
+        assert!(node.mempool.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Check that recovery rebuilds the wallet without acknowledging data outputs again.
+    #[test]
+    pub fn recover() {
+        let keys = KeyChain::new_mem();
+        let genesis = genesis(&[keys.clone()]);
+        let txpool_cfg = ConfigTxPool {
+            path: temp_path("recover.txpool").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let path = temp_path("recover");
+        let (balance, unspent) = {
+            let (mut node, _broker_service) = test_node(&path, &txpool_cfg, &keys, &genesis);
+            let tx = node
+                .create_data_transaction(&keys.wallet_pkey, 10, b"hello".to_vec())
+                .unwrap();
+            node.queue_transaction(tx, 1024, SystemTime::now()).unwrap();
+            node.process_mempool().unwrap();
+            // The data output is acknowledged by a new transaction.
+            assert_eq!(node.mempool.len(), 1);
+            (node.balance, node.unspent.clone())
+        };
+
+        let (node, _broker_service) = test_node(&path, &txpool_cfg, &keys, &genesis);
+        assert_eq!(node.balance, balance);
+        assert_eq!(node.unspent, unspent);
+        assert!(node.mempool.is_empty());
+        drop(node);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Check that blocks from the network are rejected if range hashes don't match.
+    #[test]
+    pub fn invalid_range_hash() {
+        let keys = KeyChain::new_mem();
+        let genesis = genesis(&[keys.clone()]);
+        let path = temp_path("invalid_range_hash");
+        let (mut node, _broker_service) = test_node(&path, &Default::default(), &keys, &genesis);
+
+        let last = node.chain.last_block();
+        let timestamp = last.base_header().timestamp;
+        let last_hash = Hash::digest(last);
+        let unknown_hash = Hash::digest(&"unknown".to_string());
+        // Both the next block and an orphan.
+        for previous in &[last_hash, unknown_hash] {
+            let base = BaseBlockHeader::new(1, *previous, node.epoch, timestamp);
+            let mut block = MonetaryBlock::new(base, Fr::zero(), &[], &[], Hash::zero(), 0);
+            block.header.inputs_range_hash = Hash::digest(&"corrupted".to_string());
+            let block = Block::MonetaryBlock(block);
+            let block_hash = Hash::digest(&block);
+            let msg = block.into_proto().write_to_bytes().unwrap();
+            let e = node.handle_block_request(msg).unwrap_err();
+            match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::InvalidInputsRangeHash(..) => {}
+                e => panic!("{}", e),
+            }
+            assert!(!node.chain.contains_block(&block_hash));
+            assert!(!node.orphans.contains(&block_hash));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
 
     /// Check transaction signing and validation.
     #[test]
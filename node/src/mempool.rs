@@ -0,0 +1,159 @@
+//! Mempool Persistence.
+
+//
+// Copyright (c) 2018 Stegos
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::protos::{FromProto, IntoProto};
+use failure::Error;
+use protobuf::{CodedInputStream, Message};
+use std::fs;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use stegos_blockchain::Transaction;
+
+/// Write transactions with the time of arrival to the file as a sequence of
+/// length-delimited protobuf PendingTransaction messages.
+///
+/// The data is written to a temporary file, which is flushed to the disk
+/// and then renamed, so a crash never leaves a partially written file.
+/// The directory is flushed after the rename to make the new file durable.
+///
+pub(crate) fn save_transactions<'a, P, I>(path: P, transactions: I) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    I: Iterator<Item = (&'a Transaction, SystemTime)>,
+{
+    let mut data = Vec::<u8>::new();
+    for (tx, arrived) in transactions {
+        let mut proto = crate::protos::node::PendingTransaction::new();
+        proto.set_transaction(tx.into_proto());
+        let arrived = arrived.duration_since(UNIX_EPOCH).unwrap_or_default();
+        proto.set_arrived(arrived.as_secs());
+        proto.write_length_delimited_to_vec(&mut data)?;
+    }
+
+    let mut tmp_path = path.as_ref().as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(&data)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp_path, &path)?;
+    sync_parent_dir(path.as_ref())?;
+    Ok(())
+}
+
+/// Flush the directory which contains the file to the disk.
+fn sync_parent_dir(path: &Path) -> Result<(), Error> {
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Read transactions with the time of arrival written by save_transactions().
+///
+/// Returns an empty list if the file doesn't exist.
+///
+pub(crate) fn load_transactions<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<(Transaction, SystemTime)>, Error> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut transactions = Vec::<(Transaction, SystemTime)>::new();
+    let mut input = CodedInputStream::from_bytes(&data);
+    while !input.eof()? {
+        let proto: crate::protos::node::PendingTransaction = input.read_message()?;
+        let tx = Transaction::from_proto(proto.get_transaction())?;
+        let arrived = UNIX_EPOCH + Duration::from_secs(proto.get_arrived());
+        transactions.push((tx, arrived));
+    }
+    Ok(transactions)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::path::PathBuf;
+    use stegos_blockchain::Output;
+    use stegos_crypto::curve1174::cpt::make_random_keys;
+    use stegos_crypto::hash::Hash;
+
+    /// Returns a path to a fresh file.
+    pub fn temp_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("stegos-node-{}-{}", std::process::id(), name));
+        fs::remove_file(&path).ok();
+        path
+    }
+
+    fn transaction(amount: i64) -> Transaction {
+        let (skey, pkey, _sig) = make_random_keys();
+        let timestamp = Utc::now().timestamp() as u64;
+        let (input, _gamma) = Output::new_monetary(timestamp, &skey, &pkey, amount).unwrap();
+        let (output, gamma) = Output::new_monetary(timestamp, &skey, &pkey, amount).unwrap();
+        Transaction::new(&skey, &[input], &[output], gamma, 0).unwrap()
+    }
+
+    #[test]
+    fn save_load() {
+        let path = temp_path("mempool");
+        assert!(load_transactions(&path).unwrap().is_empty());
+
+        // The time of arrival is kept with the precision of seconds.
+        let arrived = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let transactions = vec![
+            (transaction(100), arrived),
+            (transaction(200), arrived + Duration::from_secs(10)),
+        ];
+        let iter = || transactions.iter().map(|(tx, arrived)| (tx, *arrived));
+        save_transactions(&path, iter()).unwrap();
+        let loaded = load_transactions(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for ((tx, arrived), (tx2, arrived2)) in transactions.iter().zip(loaded.iter()) {
+            assert_eq!(Hash::digest(&tx.body), Hash::digest(&tx2.body));
+            assert_eq!(arrived, arrived2);
+        }
+
+        // The file is replaced.
+        save_transactions(&path, iter().skip(1)).unwrap();
+        assert_eq!(load_transactions(&path).unwrap().len(), 1);
+        save_transactions(&path, iter().take(0)).unwrap();
+        assert!(load_transactions(&path).unwrap().is_empty());
+
+        // Truncated file.
+        save_transactions(&path, iter()).unwrap();
+        let data = fs::read(&path).unwrap();
+        fs::write(&path, &data[..data.len() - 1]).unwrap();
+        assert!(load_transactions(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+//! Orphan Blocks.
+
+//
+// Copyright (c) 2018 Stegos
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use stegos_blockchain::Block;
+use stegos_crypto::hash::Hash;
+
+/// Blocks received before their parents.
+///
+/// Floodsub doesn't guarantee the order of messages, so a block can arrive before
+/// its parent. Such blocks are kept here until the parent is registered.
+/// The pool is limited by the number of blocks and by the age of each block.
+pub(crate) struct OrphanPool {
+    /// Orphan blocks with the time of arrival by hash.
+    blocks: HashMap<Hash, (Block, Instant)>,
+    /// Hashes of orphan blocks by the hash of the missing parent.
+    children: HashMap<Hash, Vec<Hash>>,
+    /// Hashes of orphan blocks in the order of arrival.
+    queue: VecDeque<Hash>,
+    /// The maximal number of blocks.
+    max_size: usize,
+    /// The maximal time to keep a block.
+    max_age: Duration,
+}
+
+impl OrphanPool {
+    /// Create a new empty pool.
+    pub(crate) fn new(max_size: usize, max_age: Duration) -> OrphanPool {
+        assert!(max_size > 0);
+        OrphanPool {
+            blocks: HashMap::new(),
+            children: HashMap::new(),
+            queue: VecDeque::new(),
+            max_size,
+            max_age,
+        }
+    }
+
+    /// Returns the number of blocks in the pool.
+    pub(crate) fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns true if the block is in the pool.
+    pub(crate) fn contains(&self, block_hash: &Hash) -> bool {
+        self.blocks.contains_key(block_hash)
+    }
+
+    /// Add a block which parent is missing.
+    ///
+    /// The oldest block is evicted if the pool is full.
+    /// Returns false if the block is already in the pool.
+    ///
+    pub(crate) fn insert(&mut self, block: Block, now: Instant) -> bool {
+        let block_hash = Hash::digest(&block);
+        if self.blocks.contains_key(&block_hash) {
+            return false;
+        }
+
+        while self.blocks.len() >= self.max_size {
+            let oldest = self.queue.front().cloned().expect("pool is not empty");
+            self.remove(&oldest);
+        }
+
+        let previous = block.base_header().previous;
+        self.children
+            .entry(previous)
+            .or_insert_with(Vec::new)
+            .push(block_hash);
+        self.queue.push_back(block_hash);
+        self.blocks.insert(block_hash, (block, now));
+        true
+    }
+
+    /// Remove and return blocks which are waiting for the parent, in the order of arrival.
+    pub(crate) fn take_children(&mut self, parent_hash: &Hash) -> Vec<Block> {
+        let children = match self.children.remove(parent_hash) {
+            Some(children) => children,
+            None => return Vec::new(),
+        };
+        self.queue
+            .retain(|block_hash| !children.contains(block_hash));
+        children
+            .iter()
+            .map(|block_hash| self.blocks.remove(block_hash).expect("exists").0)
+            .collect()
+    }
+
+    /// Remove blocks which are kept for longer than max_age.
+    ///
+    /// Returns hashes of removed blocks.
+    ///
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<Hash> {
+        let mut expired = Vec::new();
+        while let Some(block_hash) = self.queue.front().cloned() {
+            let arrived = self.blocks[&block_hash].1;
+            if now.duration_since(arrived) < self.max_age {
+                break;
+            }
+            self.remove(&block_hash);
+            expired.push(block_hash);
+        }
+        expired
+    }
+
+    /// Remove a block from the pool.
+    fn remove(&mut self, block_hash: &Hash) {
+        let (block, _arrived) = self.blocks.remove(block_hash).expect("exists");
+        let previous = block.base_header().previous;
+        let is_empty = {
+            let children = self.children.get_mut(&previous).expect("exists");
+            children.retain(|child| child != block_hash);
+            children.is_empty()
+        };
+        if is_empty {
+            self.children.remove(&previous);
+        }
+        self.queue.retain(|child| child != block_hash);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use stegos_blockchain::{BaseBlockHeader, KeyBlock};
+    use stegos_crypto::pbc::secure::G2;
+
+    fn key_block(previous: Hash, epoch: u64) -> Block {
+        let base = BaseBlockHeader::new(1, previous, epoch, 0);
+        let leader = G2::generator().into();
+        Block::KeyBlock(KeyBlock::new(base, leader, &[]))
+    }
+
+    #[test]
+    fn children() {
+        let now = Instant::now();
+        let mut pool = OrphanPool::new(10, Duration::from_secs(60));
+        let parent = Hash::digest(&"parent".to_string());
+        let a = key_block(parent, 1);
+        let a_hash = Hash::digest(&a);
+        let b = key_block(a_hash, 2);
+        let b_hash = Hash::digest(&b);
+        let c = key_block(parent, 3);
+        let c_hash = Hash::digest(&c);
+
+        assert!(pool.insert(b.clone(), now));
+        assert!(pool.insert(a.clone(), now));
+        assert!(pool.insert(c.clone(), now));
+        assert!(!pool.insert(a.clone(), now));
+        assert_eq!(pool.len(), 3);
+        assert!(pool.contains(&b_hash));
+
+        assert!(pool.take_children(&b_hash).is_empty());
+        let children: Vec<Hash> = pool
+            .take_children(&parent)
+            .iter()
+            .map(|b| Hash::digest(b))
+            .collect();
+        assert_eq!(children, vec![a_hash, c_hash]);
+        assert!(!pool.contains(&a_hash));
+        let children = pool.take_children(&a_hash);
+        assert_eq!(children.len(), 1);
+        assert_eq!(Hash::digest(&children[0]), b_hash);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn limits() {
+        let now = Instant::now();
+        let mut pool = OrphanPool::new(2, Duration::from_secs(60));
+        let parent = Hash::digest(&"parent".to_string());
+        let a = key_block(parent, 1);
+        let b = key_block(parent, 2);
+        let c = key_block(parent, 3);
+
+        // The oldest block is evicted when the pool is full.
+        pool.insert(a.clone(), now);
+        pool.insert(b.clone(), now + Duration::from_secs(30));
+        pool.insert(c.clone(), now + Duration::from_secs(40));
+        assert_eq!(pool.len(), 2);
+        assert!(!pool.contains(&Hash::digest(&a)));
+
+        // Blocks are expired by age.
+        assert!(pool.expire(now + Duration::from_secs(80)).is_empty());
+        let expired = pool.expire(now + Duration::from_secs(90));
+        assert_eq!(expired, vec![Hash::digest(&b)]);
+        assert_eq!(pool.len(), 1);
+        let children = pool.take_children(&parent);
+        assert_eq!(children.len(), 1);
+        assert_eq!(Hash::digest(&children[0]), Hash::digest(&c));
+        assert!(pool.expire(now + Duration::from_secs(1000)).is_empty());
+    }
+}
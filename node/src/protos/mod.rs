@@ -24,6 +24,8 @@
 pub mod node;
 
 use failure::{Error, Fail};
+use protobuf::Message;
+use std::collections::BTreeMap;
 use stegos_blockchain::*;
 use stegos_crypto::bulletproofs::{BulletProof, DotProof, L2_NBASIS, LR};
 use stegos_crypto::curve1174::cpt::Pt;
@@ -524,6 +526,8 @@ impl IntoProto<node::MonetaryBlockHeader> for MonetaryBlockHeader {
         proto.set_gamma(self.gamma.into_proto());
         proto.set_inputs_range_hash(self.inputs_range_hash.into_proto());
         proto.set_outputs_range_hash(self.outputs_range_hash.into_proto());
+        proto.set_utxo_root(self.utxo_root.into_proto());
+        proto.set_monetary_adjustment(self.monetary_adjustment);
         proto
     }
 }
@@ -534,11 +538,15 @@ impl FromProto<node::MonetaryBlockHeader> for MonetaryBlockHeader {
         let gamma = Fr::from_proto(proto.get_gamma())?;
         let inputs_range_hash = Hash::from_proto(proto.get_inputs_range_hash())?;
         let outputs_range_hash = Hash::from_proto(proto.get_outputs_range_hash())?;
+        let utxo_root = Hash::from_proto(proto.get_utxo_root())?;
+        let monetary_adjustment = proto.get_monetary_adjustment();
         Ok(MonetaryBlockHeader {
             base,
             gamma: gamma,
             inputs_range_hash,
             outputs_range_hash,
+            utxo_root,
+            monetary_adjustment,
         })
     }
 }
@@ -591,14 +599,43 @@ impl FromProto<node::MerkleNode> for SerializedNode<Box<Output>> {
     }
 }
 
+impl IntoProto<node::CompactMerkleNode> for CompactNode<Box<Output>> {
+    fn into_proto(&self) -> node::CompactMerkleNode {
+        let mut proto = node::CompactMerkleNode::new();
+        match self {
+            CompactNode::Full => proto.set_children(2),
+            CompactNode::Left => proto.set_children(1),
+            CompactNode::Leaf(value) => proto.set_value(value.into_proto()),
+            CompactNode::Pruned(hash) => proto.set_hash(hash.into_proto()),
+        }
+        proto
+    }
+}
+
+impl FromProto<node::CompactMerkleNode> for CompactNode<Box<Output>> {
+    fn from_proto(proto: &node::CompactMerkleNode) -> Result<Self, Error> {
+        let node = match (proto.get_children(), proto.has_value(), proto.has_hash()) {
+            (2, false, false) => CompactNode::Full,
+            (1, false, false) => CompactNode::Left,
+            (0, true, false) => {
+                let value = Box::new(Output::from_proto(proto.get_value())?);
+                CompactNode::Leaf(value)
+            }
+            (0, false, true) => CompactNode::Pruned(Hash::from_proto(proto.get_hash())?),
+            _ => return Err(MerkleError::InvalidStructure.into()),
+        };
+        Ok(node)
+    }
+}
+
 impl IntoProto<node::MonetaryBlockBody> for MonetaryBlockBody {
     fn into_proto(&self) -> node::MonetaryBlockBody {
         let mut proto = node::MonetaryBlockBody::new();
         for input in &self.inputs {
             proto.inputs.push(input.into_proto());
         }
-        for output in self.outputs.serialize() {
-            proto.outputs.push(output.into_proto());
+        for output in self.outputs.serialize_compact() {
+            proto.compact_outputs.push(output.into_proto());
         }
         proto
     }
@@ -611,11 +648,20 @@ impl FromProto<node::MonetaryBlockBody> for MonetaryBlockBody {
             inputs.push(Hash::from_proto(input)?);
         }
 
-        let mut outputs = Vec::with_capacity(proto.outputs.len());
-        for output in proto.outputs.iter() {
-            outputs.push(SerializedNode::<Box<Output>>::from_proto(output)?);
-        }
-        let outputs = Merkle::deserialize(&outputs)?;
+        let outputs = if !proto.compact_outputs.is_empty() {
+            let mut outputs = Vec::with_capacity(proto.compact_outputs.len());
+            for output in proto.compact_outputs.iter() {
+                outputs.push(CompactNode::<Box<Output>>::from_proto(output)?);
+            }
+            Merkle::deserialize_compact(&outputs)?
+        } else {
+            // Legacy encoding, used by genesis blocks.
+            let mut outputs = Vec::with_capacity(proto.outputs.len());
+            for output in proto.outputs.iter() {
+                outputs.push(SerializedNode::<Box<Output>>::from_proto(output)?);
+            }
+            Merkle::deserialize(&outputs)?
+        };
 
         Ok(MonetaryBlockBody { inputs, outputs })
     }
@@ -676,6 +722,102 @@ impl FromProto<node::Block> for Block {
     }
 }
 
+//
+// enum BlockHeader
+//
+
+impl IntoProto<node::BlockHeader> for BlockHeader {
+    fn into_proto(&self) -> node::BlockHeader {
+        let mut proto = node::BlockHeader::new();
+        match self {
+            BlockHeader::KeyBlockHeader(header) => proto.set_key_block_header(header.into_proto()),
+            BlockHeader::MonetaryBlockHeader(header) => {
+                proto.set_monetary_block_header(header.into_proto())
+            }
+        }
+        proto
+    }
+}
+
+impl FromProto<node::BlockHeader> for BlockHeader {
+    fn from_proto(proto: &node::BlockHeader) -> Result<Self, Error> {
+        let header = match proto.header {
+            Some(node::BlockHeader_oneof_header::key_block_header(ref header)) => {
+                let header = KeyBlockHeader::from_proto(header)?;
+                BlockHeader::KeyBlockHeader(header)
+            }
+            Some(node::BlockHeader_oneof_header::monetary_block_header(ref header)) => {
+                let header = MonetaryBlockHeader::from_proto(header)?;
+                BlockHeader::MonetaryBlockHeader(header)
+            }
+            None => {
+                return Err(ProtoError::MissingField(
+                    "block_header".to_string(),
+                    "header".to_string(),
+                )
+                .into());
+            }
+        };
+        Ok(header)
+    }
+}
+
+//
+// Snapshot
+//
+
+impl IntoProto<node::Snapshot> for Snapshot {
+    fn into_proto(&self) -> node::Snapshot {
+        let mut proto = node::Snapshot::new();
+        for block in &self.blocks {
+            proto.blocks.push(block.into_proto());
+        }
+        for (block_id, commitment) in &self.expired {
+            let mut expired = node::ExpiredCommitment::new();
+            expired.set_block_id(*block_id as u64);
+            expired.set_commitment(commitment.into_proto());
+            proto.expired.push(expired);
+        }
+        proto
+    }
+}
+
+impl FromProto<node::Snapshot> for Snapshot {
+    fn from_proto(proto: &node::Snapshot) -> Result<Self, Error> {
+        let mut blocks = Vec::<Block>::with_capacity(proto.blocks.len());
+        for block in proto.blocks.iter() {
+            blocks.push(Block::from_proto(block)?);
+        }
+        let mut expired = BTreeMap::<usize, Pt>::new();
+        for proto in proto.expired.iter() {
+            let commitment = Pt::from_proto(proto.get_commitment())?;
+            expired.insert(proto.get_block_id() as usize, commitment);
+        }
+        Ok(Snapshot { blocks, expired })
+    }
+}
+
+//
+// BlockCodec
+//
+
+/// Protobuf serialization of blocks for the persistent storage.
+pub struct ProtoBlockCodec;
+
+impl BlockCodec for ProtoBlockCodec {
+    fn encode(&self, block: &Block) -> Vec<u8> {
+        block
+            .into_proto()
+            .write_to_bytes()
+            .expect("protobuf serialization never fails")
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Block, Error> {
+        let proto: node::Block = protobuf::parse_from_bytes(data)?;
+        Block::from_proto(&proto)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -813,6 +955,10 @@ mod tests {
 
         let block = Block::KeyBlock(block);
         roundtrip(&block);
+
+        let header = roundtrip(&block.header());
+        assert_eq!(Hash::digest(&header), Hash::digest(&block));
+        assert!(BlockHeader::from_proto(&node::BlockHeader::new()).is_err());
     }
 
     #[test]
@@ -839,12 +985,60 @@ mod tests {
         let base = BaseBlockHeader::new(version, previous, epoch, timestamp);
         roundtrip(&base);
 
-        let block = MonetaryBlock::new(base, gamma, &inputs1, &outputs1);
+        let utxo_root = Hash::digest(&"utxo".to_string());
+        let block = MonetaryBlock::new(base, gamma, &inputs1, &outputs1, utxo_root, 0);
         roundtrip(&block.header);
         roundtrip(&block.body);
         roundtrip(&block);
 
+        // Legacy encoding.
+        let mut proto = block.into_proto();
+        proto.mut_body().mut_compact_outputs().clear();
+        for output in block.body.outputs.serialize() {
+            proto.mut_body().mut_outputs().push(output.into_proto());
+        }
+        let block2 = MonetaryBlock::from_proto(&proto).unwrap();
+        assert_eq!(Hash::digest(&block2), Hash::digest(&block));
+        assert_eq!(block2.body.outputs.leafs().len(), 1);
+
+        // Invalid compact node.
+        let mut proto = block.into_proto();
+        proto.mut_body().mut_compact_outputs()[0].set_children(3);
+        assert!(MonetaryBlock::from_proto(&proto).is_err());
+
         let block = Block::MonetaryBlock(block);
         roundtrip(&block);
+        let header = roundtrip(&block.header());
+        assert_eq!(Hash::digest(&header), Hash::digest(&block));
+
+        // Snapshots keep pruned blocks.
+        let mut pruned = block.clone();
+        if let Block::MonetaryBlock(ref mut monetary_block) = pruned {
+            let (_output, path) = monetary_block.body.outputs.leafs()[0];
+            monetary_block.body.outputs.prune(&path).unwrap();
+        }
+        roundtrip(&pruned);
+        if let Block::MonetaryBlock(ref monetary_block) = pruned {
+            // Only the hash of the pruned output is kept.
+            let proto = monetary_block.body.into_proto();
+            assert_eq!(proto.compact_outputs.len(), 1);
+            assert!(proto.compact_outputs[0].has_hash());
+        }
+        let mut expired = BTreeMap::new();
+        expired.insert(1, Pt::random());
+        let snapshot = Snapshot {
+            blocks: vec![block, pruned.clone()],
+            expired: expired.clone(),
+        };
+        let snapshot = Snapshot::from_proto(&snapshot.into_proto()).unwrap();
+        assert_eq!(snapshot.expired, expired);
+        assert_eq!(snapshot.blocks.len(), 2);
+        assert_eq!(Hash::digest(&snapshot.blocks[1]), Hash::digest(&pruned));
+        match snapshot.blocks[1] {
+            Block::MonetaryBlock(ref monetary_block) => {
+                assert!(monetary_block.body.outputs.leafs().is_empty())
+            }
+            _ => panic!(),
+        }
     }
 }
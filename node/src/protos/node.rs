@@ -3788,6 +3788,219 @@ impl ::protobuf::reflect::ProtobufValue for Transaction {
     }
 }
 
+#[derive(PartialEq,Clone,Default)]
+pub struct PendingTransaction {
+    // message fields
+    pub transaction: ::protobuf::SingularPtrField<Transaction>,
+    pub arrived: u64,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl PendingTransaction {
+    pub fn new() -> PendingTransaction {
+        ::std::default::Default::default()
+    }
+
+    // .protobuf.pb.Transaction transaction = 1;
+
+    pub fn clear_transaction(&mut self) {
+        self.transaction.clear();
+    }
+
+    pub fn has_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_transaction(&mut self, v: Transaction) {
+        self.transaction = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_transaction(&mut self) -> &mut Transaction {
+        if self.transaction.is_none() {
+            self.transaction.set_default();
+        }
+        self.transaction.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_transaction(&mut self) -> Transaction {
+        self.transaction.take().unwrap_or_else(|| Transaction::new())
+    }
+
+    pub fn get_transaction(&self) -> &Transaction {
+        self.transaction.as_ref().unwrap_or_else(|| Transaction::default_instance())
+    }
+
+    // uint64 arrived = 2;
+
+    pub fn clear_arrived(&mut self) {
+        self.arrived = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_arrived(&mut self, v: u64) {
+        self.arrived = v;
+    }
+
+    pub fn get_arrived(&self) -> u64 {
+        self.arrived
+    }
+}
+
+impl ::protobuf::Message for PendingTransaction {
+    fn is_initialized(&self) -> bool {
+        for v in &self.transaction {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.transaction)?;
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.arrived = tmp;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.transaction.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if self.arrived != 0 {
+            my_size += ::protobuf::rt::value_size(2, self.arrived, ::protobuf::wire_format::WireTypeVarint);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.transaction.as_ref() {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if self.arrived != 0 {
+            os.write_uint64(2, self.arrived)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> PendingTransaction {
+        PendingTransaction::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Transaction>>(
+                    "transaction",
+                    |m: &PendingTransaction| { &m.transaction },
+                    |m: &mut PendingTransaction| { &mut m.transaction },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "arrived",
+                    |m: &PendingTransaction| { &m.arrived },
+                    |m: &mut PendingTransaction| { &mut m.arrived },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<PendingTransaction>(
+                    "PendingTransaction",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static PendingTransaction {
+        static mut instance: ::protobuf::lazy::Lazy<PendingTransaction> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const PendingTransaction,
+        };
+        unsafe {
+            instance.get(PendingTransaction::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for PendingTransaction {
+    fn clear(&mut self) {
+        self.clear_transaction();
+        self.clear_arrived();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for PendingTransaction {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for PendingTransaction {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
 #[derive(PartialEq,Clone,Default)]
 pub struct BaseBlockHeader {
     // message fields
@@ -4078,6 +4291,8 @@ pub struct MonetaryBlockHeader {
     pub gamma: ::protobuf::SingularPtrField<Fr>,
     pub inputs_range_hash: ::protobuf::SingularPtrField<Hash>,
     pub outputs_range_hash: ::protobuf::SingularPtrField<Hash>,
+    pub utxo_root: ::protobuf::SingularPtrField<Hash>,
+    pub monetary_adjustment: i64,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -4219,6 +4434,54 @@ impl MonetaryBlockHeader {
     pub fn get_outputs_range_hash(&self) -> &Hash {
         self.outputs_range_hash.as_ref().unwrap_or_else(|| Hash::default_instance())
     }
+
+    // .protobuf.pb.Hash utxo_root = 5;
+
+    pub fn clear_utxo_root(&mut self) {
+        self.utxo_root.clear();
+    }
+
+    pub fn has_utxo_root(&self) -> bool {
+        self.utxo_root.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_utxo_root(&mut self, v: Hash) {
+        self.utxo_root = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_utxo_root(&mut self) -> &mut Hash {
+        if self.utxo_root.is_none() {
+            self.utxo_root.set_default();
+        }
+        self.utxo_root.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_utxo_root(&mut self) -> Hash {
+        self.utxo_root.take().unwrap_or_else(|| Hash::new())
+    }
+
+    pub fn get_utxo_root(&self) -> &Hash {
+        self.utxo_root.as_ref().unwrap_or_else(|| Hash::default_instance())
+    }
+
+    // int64 monetary_adjustment = 6;
+
+    pub fn clear_monetary_adjustment(&mut self) {
+        self.monetary_adjustment = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_monetary_adjustment(&mut self, v: i64) {
+        self.monetary_adjustment = v;
+    }
+
+    pub fn get_monetary_adjustment(&self) -> i64 {
+        self.monetary_adjustment
+    }
 }
 
 impl ::protobuf::Message for MonetaryBlockHeader {
@@ -4243,6 +4506,11 @@ impl ::protobuf::Message for MonetaryBlockHeader {
                 return false;
             }
         };
+        for v in &self.utxo_root {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
         true
     }
 
@@ -4262,6 +4530,16 @@ impl ::protobuf::Message for MonetaryBlockHeader {
                 4 => {
                     ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.outputs_range_hash)?;
                 },
+                5 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.utxo_root)?;
+                },
+                6 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_int64()?;
+                    self.monetary_adjustment = tmp;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -4290,6 +4568,13 @@ impl ::protobuf::Message for MonetaryBlockHeader {
             let len = v.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
         }
+        if let Some(ref v) = self.utxo_root.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if self.monetary_adjustment != 0 {
+            my_size += ::protobuf::rt::value_size(6, self.monetary_adjustment, ::protobuf::wire_format::WireTypeVarint);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -4316,6 +4601,14 @@ impl ::protobuf::Message for MonetaryBlockHeader {
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
         }
+        if let Some(ref v) = self.utxo_root.as_ref() {
+            os.write_tag(5, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if self.monetary_adjustment != 0 {
+            os.write_int64(6, self.monetary_adjustment)?;
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -4378,6 +4671,16 @@ impl ::protobuf::Message for MonetaryBlockHeader {
                     |m: &MonetaryBlockHeader| { &m.outputs_range_hash },
                     |m: &mut MonetaryBlockHeader| { &mut m.outputs_range_hash },
                 ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Hash>>(
+                    "utxo_root",
+                    |m: &MonetaryBlockHeader| { &m.utxo_root },
+                    |m: &mut MonetaryBlockHeader| { &mut m.utxo_root },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeInt64>(
+                    "monetary_adjustment",
+                    |m: &MonetaryBlockHeader| { &m.monetary_adjustment },
+                    |m: &mut MonetaryBlockHeader| { &mut m.monetary_adjustment },
+                ));
                 ::protobuf::reflect::MessageDescriptor::new::<MonetaryBlockHeader>(
                     "MonetaryBlockHeader",
                     fields,
@@ -4404,6 +4707,8 @@ impl ::protobuf::Clear for MonetaryBlockHeader {
         self.clear_gamma();
         self.clear_inputs_range_hash();
         self.clear_outputs_range_hash();
+        self.clear_utxo_root();
+        self.clear_monetary_adjustment();
         self.unknown_fields.clear();
     }
 }
@@ -4726,79 +5031,111 @@ impl ::protobuf::reflect::ProtobufValue for MerkleNode {
 }
 
 #[derive(PartialEq,Clone,Default)]
-pub struct MonetaryBlockBody {
+pub struct CompactMerkleNode {
     // message fields
-    pub inputs: ::protobuf::RepeatedField<Hash>,
-    pub outputs: ::protobuf::RepeatedField<MerkleNode>,
+    pub children: u64,
+    pub value: ::protobuf::SingularPtrField<Output>,
+    pub hash: ::protobuf::SingularPtrField<Hash>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
 }
 
-impl MonetaryBlockBody {
-    pub fn new() -> MonetaryBlockBody {
+impl CompactMerkleNode {
+    pub fn new() -> CompactMerkleNode {
         ::std::default::Default::default()
     }
 
-    // repeated .protobuf.pb.Hash inputs = 1;
+    // uint64 children = 1;
 
-    pub fn clear_inputs(&mut self) {
-        self.inputs.clear();
+    pub fn clear_children(&mut self) {
+        self.children = 0;
     }
 
     // Param is passed by value, moved
-    pub fn set_inputs(&mut self, v: ::protobuf::RepeatedField<Hash>) {
-        self.inputs = v;
+    pub fn set_children(&mut self, v: u64) {
+        self.children = v;
     }
 
-    // Mutable pointer to the field.
-    pub fn mut_inputs(&mut self) -> &mut ::protobuf::RepeatedField<Hash> {
-        &mut self.inputs
+    pub fn get_children(&self) -> u64 {
+        self.children
     }
 
-    // Take field
-    pub fn take_inputs(&mut self) -> ::protobuf::RepeatedField<Hash> {
-        ::std::mem::replace(&mut self.inputs, ::protobuf::RepeatedField::new())
-    }
+    // .protobuf.pb.Output value = 2;
 
-    pub fn get_inputs(&self) -> &[Hash] {
-        &self.inputs
+    pub fn clear_value(&mut self) {
+        self.value.clear();
     }
 
-    // repeated .protobuf.pb.MerkleNode outputs = 2;
-
-    pub fn clear_outputs(&mut self) {
-        self.outputs.clear();
+    pub fn has_value(&self) -> bool {
+        self.value.is_some()
     }
 
     // Param is passed by value, moved
-    pub fn set_outputs(&mut self, v: ::protobuf::RepeatedField<MerkleNode>) {
-        self.outputs = v;
+    pub fn set_value(&mut self, v: Output) {
+        self.value = ::protobuf::SingularPtrField::some(v);
     }
 
     // Mutable pointer to the field.
-    pub fn mut_outputs(&mut self) -> &mut ::protobuf::RepeatedField<MerkleNode> {
-        &mut self.outputs
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_value(&mut self) -> &mut Output {
+        if self.value.is_none() {
+            self.value.set_default();
+        }
+        self.value.as_mut().unwrap()
     }
 
     // Take field
-    pub fn take_outputs(&mut self) -> ::protobuf::RepeatedField<MerkleNode> {
-        ::std::mem::replace(&mut self.outputs, ::protobuf::RepeatedField::new())
+    pub fn take_value(&mut self) -> Output {
+        self.value.take().unwrap_or_else(|| Output::new())
     }
 
-    pub fn get_outputs(&self) -> &[MerkleNode] {
-        &self.outputs
+    pub fn get_value(&self) -> &Output {
+        self.value.as_ref().unwrap_or_else(|| Output::default_instance())
+    }
+
+    // .protobuf.pb.Hash hash = 3;
+
+    pub fn clear_hash(&mut self) {
+        self.hash.clear();
+    }
+
+    pub fn has_hash(&self) -> bool {
+        self.hash.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_hash(&mut self, v: Hash) {
+        self.hash = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_hash(&mut self) -> &mut Hash {
+        if self.hash.is_none() {
+            self.hash.set_default();
+        }
+        self.hash.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_hash(&mut self) -> Hash {
+        self.hash.take().unwrap_or_else(|| Hash::new())
+    }
+
+    pub fn get_hash(&self) -> &Hash {
+        self.hash.as_ref().unwrap_or_else(|| Hash::default_instance())
     }
 }
 
-impl ::protobuf::Message for MonetaryBlockBody {
+impl ::protobuf::Message for CompactMerkleNode {
     fn is_initialized(&self) -> bool {
-        for v in &self.inputs {
+        for v in &self.value {
             if !v.is_initialized() {
                 return false;
             }
         };
-        for v in &self.outputs {
+        for v in &self.hash {
             if !v.is_initialized() {
                 return false;
             }
@@ -4811,10 +5148,17 @@ impl ::protobuf::Message for MonetaryBlockBody {
             let (field_number, wire_type) = is.read_tag_unpack()?;
             match field_number {
                 1 => {
-                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.inputs)?;
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.children = tmp;
                 },
                 2 => {
-                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.outputs)?;
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.value)?;
+                },
+                3 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.hash)?;
                 },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
@@ -4828,30 +5172,36 @@ impl ::protobuf::Message for MonetaryBlockBody {
     #[allow(unused_variables)]
     fn compute_size(&self) -> u32 {
         let mut my_size = 0;
-        for value in &self.inputs {
-            let len = value.compute_size();
+        if self.children != 0 {
+            my_size += ::protobuf::rt::value_size(1, self.children, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if let Some(ref v) = self.value.as_ref() {
+            let len = v.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
-        };
-        for value in &self.outputs {
-            let len = value.compute_size();
+        }
+        if let Some(ref v) = self.hash.as_ref() {
+            let len = v.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
-        };
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
     }
 
     fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
-        for v in &self.inputs {
-            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+        if self.children != 0 {
+            os.write_uint64(1, self.children)?;
+        }
+        if let Some(ref v) = self.value.as_ref() {
+            os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
-        };
-        for v in &self.outputs {
-            os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+        }
+        if let Some(ref v) = self.hash.as_ref() {
+            os.write_tag(3, ::protobuf::wire_format::WireTypeLengthDelimited)?;
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
-        };
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -4882,8 +5232,8 @@ impl ::protobuf::Message for MonetaryBlockBody {
         Self::descriptor_static()
     }
 
-    fn new() -> MonetaryBlockBody {
-        MonetaryBlockBody::new()
+    fn new() -> CompactMerkleNode {
+        CompactMerkleNode::new()
     }
 
     fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
@@ -4894,18 +5244,23 @@ impl ::protobuf::Message for MonetaryBlockBody {
         unsafe {
             descriptor.get(|| {
                 let mut fields = ::std::vec::Vec::new();
-                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Hash>>(
-                    "inputs",
-                    |m: &MonetaryBlockBody| { &m.inputs },
-                    |m: &mut MonetaryBlockBody| { &mut m.inputs },
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "children",
+                    |m: &CompactMerkleNode| { &m.children },
+                    |m: &mut CompactMerkleNode| { &mut m.children },
                 ));
-                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<MerkleNode>>(
-                    "outputs",
-                    |m: &MonetaryBlockBody| { &m.outputs },
-                    |m: &mut MonetaryBlockBody| { &mut m.outputs },
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Output>>(
+                    "value",
+                    |m: &CompactMerkleNode| { &m.value },
+                    |m: &mut CompactMerkleNode| { &mut m.value },
                 ));
-                ::protobuf::reflect::MessageDescriptor::new::<MonetaryBlockBody>(
-                    "MonetaryBlockBody",
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Hash>>(
+                    "hash",
+                    |m: &CompactMerkleNode| { &m.hash },
+                    |m: &mut CompactMerkleNode| { &mut m.hash },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<CompactMerkleNode>(
+                    "CompactMerkleNode",
                     fields,
                     file_descriptor_proto()
                 )
@@ -4913,127 +5268,143 @@ impl ::protobuf::Message for MonetaryBlockBody {
         }
     }
 
-    fn default_instance() -> &'static MonetaryBlockBody {
-        static mut instance: ::protobuf::lazy::Lazy<MonetaryBlockBody> = ::protobuf::lazy::Lazy {
+    fn default_instance() -> &'static CompactMerkleNode {
+        static mut instance: ::protobuf::lazy::Lazy<CompactMerkleNode> = ::protobuf::lazy::Lazy {
             lock: ::protobuf::lazy::ONCE_INIT,
-            ptr: 0 as *const MonetaryBlockBody,
+            ptr: 0 as *const CompactMerkleNode,
         };
         unsafe {
-            instance.get(MonetaryBlockBody::new)
+            instance.get(CompactMerkleNode::new)
         }
     }
 }
 
-impl ::protobuf::Clear for MonetaryBlockBody {
+impl ::protobuf::Clear for CompactMerkleNode {
     fn clear(&mut self) {
-        self.clear_inputs();
-        self.clear_outputs();
+        self.clear_children();
+        self.clear_value();
+        self.clear_hash();
         self.unknown_fields.clear();
     }
 }
 
-impl ::std::fmt::Debug for MonetaryBlockBody {
+impl ::std::fmt::Debug for CompactMerkleNode {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         ::protobuf::text_format::fmt(self, f)
     }
 }
 
-impl ::protobuf::reflect::ProtobufValue for MonetaryBlockBody {
+impl ::protobuf::reflect::ProtobufValue for CompactMerkleNode {
     fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
         ::protobuf::reflect::ProtobufValueRef::Message(self)
     }
 }
 
 #[derive(PartialEq,Clone,Default)]
-pub struct MonetaryBlock {
+pub struct MonetaryBlockBody {
     // message fields
-    pub header: ::protobuf::SingularPtrField<MonetaryBlockHeader>,
-    pub body: ::protobuf::SingularPtrField<MonetaryBlockBody>,
+    pub inputs: ::protobuf::RepeatedField<Hash>,
+    pub outputs: ::protobuf::RepeatedField<MerkleNode>,
+    pub compact_outputs: ::protobuf::RepeatedField<CompactMerkleNode>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
 }
 
-impl MonetaryBlock {
-    pub fn new() -> MonetaryBlock {
+impl MonetaryBlockBody {
+    pub fn new() -> MonetaryBlockBody {
         ::std::default::Default::default()
     }
 
-    // .protobuf.pb.MonetaryBlockHeader header = 1;
-
-    pub fn clear_header(&mut self) {
-        self.header.clear();
-    }
+    // repeated .protobuf.pb.Hash inputs = 1;
 
-    pub fn has_header(&self) -> bool {
-        self.header.is_some()
+    pub fn clear_inputs(&mut self) {
+        self.inputs.clear();
     }
 
     // Param is passed by value, moved
-    pub fn set_header(&mut self, v: MonetaryBlockHeader) {
-        self.header = ::protobuf::SingularPtrField::some(v);
+    pub fn set_inputs(&mut self, v: ::protobuf::RepeatedField<Hash>) {
+        self.inputs = v;
     }
 
     // Mutable pointer to the field.
-    // If field is not initialized, it is initialized with default value first.
-    pub fn mut_header(&mut self) -> &mut MonetaryBlockHeader {
-        if self.header.is_none() {
-            self.header.set_default();
-        }
-        self.header.as_mut().unwrap()
+    pub fn mut_inputs(&mut self) -> &mut ::protobuf::RepeatedField<Hash> {
+        &mut self.inputs
     }
 
     // Take field
-    pub fn take_header(&mut self) -> MonetaryBlockHeader {
-        self.header.take().unwrap_or_else(|| MonetaryBlockHeader::new())
+    pub fn take_inputs(&mut self) -> ::protobuf::RepeatedField<Hash> {
+        ::std::mem::replace(&mut self.inputs, ::protobuf::RepeatedField::new())
     }
 
-    pub fn get_header(&self) -> &MonetaryBlockHeader {
-        self.header.as_ref().unwrap_or_else(|| MonetaryBlockHeader::default_instance())
+    pub fn get_inputs(&self) -> &[Hash] {
+        &self.inputs
     }
 
-    // .protobuf.pb.MonetaryBlockBody body = 2;
+    // repeated .protobuf.pb.MerkleNode outputs = 2;
 
-    pub fn clear_body(&mut self) {
-        self.body.clear();
+    pub fn clear_outputs(&mut self) {
+        self.outputs.clear();
     }
 
-    pub fn has_body(&self) -> bool {
-        self.body.is_some()
+    // Param is passed by value, moved
+    pub fn set_outputs(&mut self, v: ::protobuf::RepeatedField<MerkleNode>) {
+        self.outputs = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_outputs(&mut self) -> &mut ::protobuf::RepeatedField<MerkleNode> {
+        &mut self.outputs
+    }
+
+    // Take field
+    pub fn take_outputs(&mut self) -> ::protobuf::RepeatedField<MerkleNode> {
+        ::std::mem::replace(&mut self.outputs, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_outputs(&self) -> &[MerkleNode] {
+        &self.outputs
+    }
+
+    // repeated .protobuf.pb.CompactMerkleNode compact_outputs = 3;
+
+    pub fn clear_compact_outputs(&mut self) {
+        self.compact_outputs.clear();
     }
 
     // Param is passed by value, moved
-    pub fn set_body(&mut self, v: MonetaryBlockBody) {
-        self.body = ::protobuf::SingularPtrField::some(v);
+    pub fn set_compact_outputs(&mut self, v: ::protobuf::RepeatedField<CompactMerkleNode>) {
+        self.compact_outputs = v;
     }
 
     // Mutable pointer to the field.
-    // If field is not initialized, it is initialized with default value first.
-    pub fn mut_body(&mut self) -> &mut MonetaryBlockBody {
-        if self.body.is_none() {
-            self.body.set_default();
-        }
-        self.body.as_mut().unwrap()
+    pub fn mut_compact_outputs(&mut self) -> &mut ::protobuf::RepeatedField<CompactMerkleNode> {
+        &mut self.compact_outputs
     }
 
     // Take field
-    pub fn take_body(&mut self) -> MonetaryBlockBody {
-        self.body.take().unwrap_or_else(|| MonetaryBlockBody::new())
+    pub fn take_compact_outputs(&mut self) -> ::protobuf::RepeatedField<CompactMerkleNode> {
+        ::std::mem::replace(&mut self.compact_outputs, ::protobuf::RepeatedField::new())
     }
 
-    pub fn get_body(&self) -> &MonetaryBlockBody {
-        self.body.as_ref().unwrap_or_else(|| MonetaryBlockBody::default_instance())
+    pub fn get_compact_outputs(&self) -> &[CompactMerkleNode] {
+        &self.compact_outputs
     }
 }
 
-impl ::protobuf::Message for MonetaryBlock {
+impl ::protobuf::Message for MonetaryBlockBody {
     fn is_initialized(&self) -> bool {
-        for v in &self.header {
+        for v in &self.inputs {
             if !v.is_initialized() {
                 return false;
             }
         };
-        for v in &self.body {
+        for v in &self.outputs {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.compact_outputs {
             if !v.is_initialized() {
                 return false;
             }
@@ -5046,10 +5417,13 @@ impl ::protobuf::Message for MonetaryBlock {
             let (field_number, wire_type) = is.read_tag_unpack()?;
             match field_number {
                 1 => {
-                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.header)?;
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.inputs)?;
                 },
                 2 => {
-                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.body)?;
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.outputs)?;
+                },
+                3 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.compact_outputs)?;
                 },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
@@ -5063,30 +5437,39 @@ impl ::protobuf::Message for MonetaryBlock {
     #[allow(unused_variables)]
     fn compute_size(&self) -> u32 {
         let mut my_size = 0;
-        if let Some(ref v) = self.header.as_ref() {
-            let len = v.compute_size();
+        for value in &self.inputs {
+            let len = value.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
-        }
-        if let Some(ref v) = self.body.as_ref() {
-            let len = v.compute_size();
+        };
+        for value in &self.outputs {
+            let len = value.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
-        }
+        };
+        for value in &self.compact_outputs {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
     }
 
     fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
-        if let Some(ref v) = self.header.as_ref() {
+        for v in &self.inputs {
             os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
-        }
-        if let Some(ref v) = self.body.as_ref() {
+        };
+        for v in &self.outputs {
             os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
-        }
+        };
+        for v in &self.compact_outputs {
+            os.write_tag(3, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -5117,8 +5500,8 @@ impl ::protobuf::Message for MonetaryBlock {
         Self::descriptor_static()
     }
 
-    fn new() -> MonetaryBlock {
-        MonetaryBlock::new()
+    fn new() -> MonetaryBlockBody {
+        MonetaryBlockBody::new()
     }
 
     fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
@@ -5129,18 +5512,23 @@ impl ::protobuf::Message for MonetaryBlock {
         unsafe {
             descriptor.get(|| {
                 let mut fields = ::std::vec::Vec::new();
-                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<MonetaryBlockHeader>>(
-                    "header",
-                    |m: &MonetaryBlock| { &m.header },
-                    |m: &mut MonetaryBlock| { &mut m.header },
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Hash>>(
+                    "inputs",
+                    |m: &MonetaryBlockBody| { &m.inputs },
+                    |m: &mut MonetaryBlockBody| { &mut m.inputs },
                 ));
-                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<MonetaryBlockBody>>(
-                    "body",
-                    |m: &MonetaryBlock| { &m.body },
-                    |m: &mut MonetaryBlock| { &mut m.body },
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<MerkleNode>>(
+                    "outputs",
+                    |m: &MonetaryBlockBody| { &m.outputs },
+                    |m: &mut MonetaryBlockBody| { &mut m.outputs },
                 ));
-                ::protobuf::reflect::MessageDescriptor::new::<MonetaryBlock>(
-                    "MonetaryBlock",
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<CompactMerkleNode>>(
+                    "compact_outputs",
+                    |m: &MonetaryBlockBody| { &m.compact_outputs },
+                    |m: &mut MonetaryBlockBody| { &mut m.compact_outputs },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<MonetaryBlockBody>(
+                    "MonetaryBlockBody",
                     fields,
                     file_descriptor_proto()
                 )
@@ -5148,10 +5536,246 @@ impl ::protobuf::Message for MonetaryBlock {
         }
     }
 
-    fn default_instance() -> &'static MonetaryBlock {
-        static mut instance: ::protobuf::lazy::Lazy<MonetaryBlock> = ::protobuf::lazy::Lazy {
+    fn default_instance() -> &'static MonetaryBlockBody {
+        static mut instance: ::protobuf::lazy::Lazy<MonetaryBlockBody> = ::protobuf::lazy::Lazy {
             lock: ::protobuf::lazy::ONCE_INIT,
-            ptr: 0 as *const MonetaryBlock,
+            ptr: 0 as *const MonetaryBlockBody,
+        };
+        unsafe {
+            instance.get(MonetaryBlockBody::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for MonetaryBlockBody {
+    fn clear(&mut self) {
+        self.clear_inputs();
+        self.clear_outputs();
+        self.clear_compact_outputs();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for MonetaryBlockBody {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for MonetaryBlockBody {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct MonetaryBlock {
+    // message fields
+    pub header: ::protobuf::SingularPtrField<MonetaryBlockHeader>,
+    pub body: ::protobuf::SingularPtrField<MonetaryBlockBody>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl MonetaryBlock {
+    pub fn new() -> MonetaryBlock {
+        ::std::default::Default::default()
+    }
+
+    // .protobuf.pb.MonetaryBlockHeader header = 1;
+
+    pub fn clear_header(&mut self) {
+        self.header.clear();
+    }
+
+    pub fn has_header(&self) -> bool {
+        self.header.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_header(&mut self, v: MonetaryBlockHeader) {
+        self.header = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_header(&mut self) -> &mut MonetaryBlockHeader {
+        if self.header.is_none() {
+            self.header.set_default();
+        }
+        self.header.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_header(&mut self) -> MonetaryBlockHeader {
+        self.header.take().unwrap_or_else(|| MonetaryBlockHeader::new())
+    }
+
+    pub fn get_header(&self) -> &MonetaryBlockHeader {
+        self.header.as_ref().unwrap_or_else(|| MonetaryBlockHeader::default_instance())
+    }
+
+    // .protobuf.pb.MonetaryBlockBody body = 2;
+
+    pub fn clear_body(&mut self) {
+        self.body.clear();
+    }
+
+    pub fn has_body(&self) -> bool {
+        self.body.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_body(&mut self, v: MonetaryBlockBody) {
+        self.body = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_body(&mut self) -> &mut MonetaryBlockBody {
+        if self.body.is_none() {
+            self.body.set_default();
+        }
+        self.body.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_body(&mut self) -> MonetaryBlockBody {
+        self.body.take().unwrap_or_else(|| MonetaryBlockBody::new())
+    }
+
+    pub fn get_body(&self) -> &MonetaryBlockBody {
+        self.body.as_ref().unwrap_or_else(|| MonetaryBlockBody::default_instance())
+    }
+}
+
+impl ::protobuf::Message for MonetaryBlock {
+    fn is_initialized(&self) -> bool {
+        for v in &self.header {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.body {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.header)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.body)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.header.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if let Some(ref v) = self.body.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.header.as_ref() {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if let Some(ref v) = self.body.as_ref() {
+            os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> MonetaryBlock {
+        MonetaryBlock::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<MonetaryBlockHeader>>(
+                    "header",
+                    |m: &MonetaryBlock| { &m.header },
+                    |m: &mut MonetaryBlock| { &mut m.header },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<MonetaryBlockBody>>(
+                    "body",
+                    |m: &MonetaryBlock| { &m.body },
+                    |m: &mut MonetaryBlock| { &mut m.body },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<MonetaryBlock>(
+                    "MonetaryBlock",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static MonetaryBlock {
+        static mut instance: ::protobuf::lazy::Lazy<MonetaryBlock> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const MonetaryBlock,
         };
         unsafe {
             instance.get(MonetaryBlock::new)
@@ -5395,8 +6019,1811 @@ impl ::protobuf::Message for KeyBlockHeader {
         Self::descriptor_static()
     }
 
-    fn new() -> KeyBlockHeader {
-        KeyBlockHeader::new()
+    fn new() -> KeyBlockHeader {
+        KeyBlockHeader::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<BaseBlockHeader>>(
+                    "base",
+                    |m: &KeyBlockHeader| { &m.base },
+                    |m: &mut KeyBlockHeader| { &mut m.base },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<SecurePublicKey>>(
+                    "leader",
+                    |m: &KeyBlockHeader| { &m.leader },
+                    |m: &mut KeyBlockHeader| { &mut m.leader },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<SecurePublicKey>>(
+                    "witnesses",
+                    |m: &KeyBlockHeader| { &m.witnesses },
+                    |m: &mut KeyBlockHeader| { &mut m.witnesses },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<KeyBlockHeader>(
+                    "KeyBlockHeader",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static KeyBlockHeader {
+        static mut instance: ::protobuf::lazy::Lazy<KeyBlockHeader> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const KeyBlockHeader,
+        };
+        unsafe {
+            instance.get(KeyBlockHeader::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for KeyBlockHeader {
+    fn clear(&mut self) {
+        self.clear_base();
+        self.clear_leader();
+        self.clear_witnesses();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for KeyBlockHeader {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for KeyBlockHeader {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct KeyBlock {
+    // message fields
+    pub header: ::protobuf::SingularPtrField<KeyBlockHeader>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl KeyBlock {
+    pub fn new() -> KeyBlock {
+        ::std::default::Default::default()
+    }
+
+    // .protobuf.pb.KeyBlockHeader header = 1;
+
+    pub fn clear_header(&mut self) {
+        self.header.clear();
+    }
+
+    pub fn has_header(&self) -> bool {
+        self.header.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_header(&mut self, v: KeyBlockHeader) {
+        self.header = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_header(&mut self) -> &mut KeyBlockHeader {
+        if self.header.is_none() {
+            self.header.set_default();
+        }
+        self.header.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_header(&mut self) -> KeyBlockHeader {
+        self.header.take().unwrap_or_else(|| KeyBlockHeader::new())
+    }
+
+    pub fn get_header(&self) -> &KeyBlockHeader {
+        self.header.as_ref().unwrap_or_else(|| KeyBlockHeader::default_instance())
+    }
+}
+
+impl ::protobuf::Message for KeyBlock {
+    fn is_initialized(&self) -> bool {
+        for v in &self.header {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.header)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.header.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.header.as_ref() {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> KeyBlock {
+        KeyBlock::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<KeyBlockHeader>>(
+                    "header",
+                    |m: &KeyBlock| { &m.header },
+                    |m: &mut KeyBlock| { &mut m.header },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<KeyBlock>(
+                    "KeyBlock",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static KeyBlock {
+        static mut instance: ::protobuf::lazy::Lazy<KeyBlock> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const KeyBlock,
+        };
+        unsafe {
+            instance.get(KeyBlock::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for KeyBlock {
+    fn clear(&mut self) {
+        self.clear_header();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for KeyBlock {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for KeyBlock {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Block {
+    // message oneof groups
+    pub block: ::std::option::Option<Block_oneof_block>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+#[derive(Clone,PartialEq)]
+pub enum Block_oneof_block {
+    key_block(KeyBlock),
+    monetary_block(MonetaryBlock),
+}
+
+impl Block {
+    pub fn new() -> Block {
+        ::std::default::Default::default()
+    }
+
+    // .protobuf.pb.KeyBlock key_block = 1;
+
+    pub fn clear_key_block(&mut self) {
+        self.block = ::std::option::Option::None;
+    }
+
+    pub fn has_key_block(&self) -> bool {
+        match self.block {
+            ::std::option::Option::Some(Block_oneof_block::key_block(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_key_block(&mut self, v: KeyBlock) {
+        self.block = ::std::option::Option::Some(Block_oneof_block::key_block(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_key_block(&mut self) -> &mut KeyBlock {
+        if let ::std::option::Option::Some(Block_oneof_block::key_block(_)) = self.block {
+        } else {
+            self.block = ::std::option::Option::Some(Block_oneof_block::key_block(KeyBlock::new()));
+        }
+        match self.block {
+            ::std::option::Option::Some(Block_oneof_block::key_block(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_key_block(&mut self) -> KeyBlock {
+        if self.has_key_block() {
+            match self.block.take() {
+                ::std::option::Option::Some(Block_oneof_block::key_block(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            KeyBlock::new()
+        }
+    }
+
+    pub fn get_key_block(&self) -> &KeyBlock {
+        match self.block {
+            ::std::option::Option::Some(Block_oneof_block::key_block(ref v)) => v,
+            _ => KeyBlock::default_instance(),
+        }
+    }
+
+    // .protobuf.pb.MonetaryBlock monetary_block = 2;
+
+    pub fn clear_monetary_block(&mut self) {
+        self.block = ::std::option::Option::None;
+    }
+
+    pub fn has_monetary_block(&self) -> bool {
+        match self.block {
+            ::std::option::Option::Some(Block_oneof_block::monetary_block(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_monetary_block(&mut self, v: MonetaryBlock) {
+        self.block = ::std::option::Option::Some(Block_oneof_block::monetary_block(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_monetary_block(&mut self) -> &mut MonetaryBlock {
+        if let ::std::option::Option::Some(Block_oneof_block::monetary_block(_)) = self.block {
+        } else {
+            self.block = ::std::option::Option::Some(Block_oneof_block::monetary_block(MonetaryBlock::new()));
+        }
+        match self.block {
+            ::std::option::Option::Some(Block_oneof_block::monetary_block(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_monetary_block(&mut self) -> MonetaryBlock {
+        if self.has_monetary_block() {
+            match self.block.take() {
+                ::std::option::Option::Some(Block_oneof_block::monetary_block(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            MonetaryBlock::new()
+        }
+    }
+
+    pub fn get_monetary_block(&self) -> &MonetaryBlock {
+        match self.block {
+            ::std::option::Option::Some(Block_oneof_block::monetary_block(ref v)) => v,
+            _ => MonetaryBlock::default_instance(),
+        }
+    }
+}
+
+impl ::protobuf::Message for Block {
+    fn is_initialized(&self) -> bool {
+        if let Some(Block_oneof_block::key_block(ref v)) = self.block {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
+        if let Some(Block_oneof_block::monetary_block(ref v)) = self.block {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.block = ::std::option::Option::Some(Block_oneof_block::key_block(is.read_message()?));
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.block = ::std::option::Option::Some(Block_oneof_block::monetary_block(is.read_message()?));
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let ::std::option::Option::Some(ref v) = self.block {
+            match v {
+                &Block_oneof_block::key_block(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
+                &Block_oneof_block::monetary_block(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
+            };
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if let ::std::option::Option::Some(ref v) = self.block {
+            match v {
+                &Block_oneof_block::key_block(ref v) => {
+                    os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
+                &Block_oneof_block::monetary_block(ref v) => {
+                    os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
+            };
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Block {
+        Block::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, KeyBlock>(
+                    "key_block",
+                    Block::has_key_block,
+                    Block::get_key_block,
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, MonetaryBlock>(
+                    "monetary_block",
+                    Block::has_monetary_block,
+                    Block::get_monetary_block,
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Block>(
+                    "Block",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Block {
+        static mut instance: ::protobuf::lazy::Lazy<Block> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Block,
+        };
+        unsafe {
+            instance.get(Block::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Block {
+    fn clear(&mut self) {
+        self.clear_key_block();
+        self.clear_monetary_block();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Block {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Block {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct ExpiredCommitment {
+    // message fields
+    pub block_id: u64,
+    pub commitment: ::protobuf::SingularPtrField<Pt>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl ExpiredCommitment {
+    pub fn new() -> ExpiredCommitment {
+        ::std::default::Default::default()
+    }
+
+    // uint64 block_id = 1;
+
+    pub fn clear_block_id(&mut self) {
+        self.block_id = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_block_id(&mut self, v: u64) {
+        self.block_id = v;
+    }
+
+    pub fn get_block_id(&self) -> u64 {
+        self.block_id
+    }
+
+    // .protobuf.pb.Pt commitment = 2;
+
+    pub fn clear_commitment(&mut self) {
+        self.commitment.clear();
+    }
+
+    pub fn has_commitment(&self) -> bool {
+        self.commitment.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_commitment(&mut self, v: Pt) {
+        self.commitment = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_commitment(&mut self) -> &mut Pt {
+        if self.commitment.is_none() {
+            self.commitment.set_default();
+        }
+        self.commitment.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_commitment(&mut self) -> Pt {
+        self.commitment.take().unwrap_or_else(|| Pt::new())
+    }
+
+    pub fn get_commitment(&self) -> &Pt {
+        self.commitment.as_ref().unwrap_or_else(|| Pt::default_instance())
+    }
+}
+
+impl ::protobuf::Message for ExpiredCommitment {
+    fn is_initialized(&self) -> bool {
+        for v in &self.commitment {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.block_id = tmp;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.commitment)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if self.block_id != 0 {
+            my_size += ::protobuf::rt::value_size(1, self.block_id, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if let Some(ref v) = self.commitment.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if self.block_id != 0 {
+            os.write_uint64(1, self.block_id)?;
+        }
+        if let Some(ref v) = self.commitment.as_ref() {
+            os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> ExpiredCommitment {
+        ExpiredCommitment::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "block_id",
+                    |m: &ExpiredCommitment| { &m.block_id },
+                    |m: &mut ExpiredCommitment| { &mut m.block_id },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Pt>>(
+                    "commitment",
+                    |m: &ExpiredCommitment| { &m.commitment },
+                    |m: &mut ExpiredCommitment| { &mut m.commitment },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<ExpiredCommitment>(
+                    "ExpiredCommitment",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static ExpiredCommitment {
+        static mut instance: ::protobuf::lazy::Lazy<ExpiredCommitment> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ExpiredCommitment,
+        };
+        unsafe {
+            instance.get(ExpiredCommitment::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for ExpiredCommitment {
+    fn clear(&mut self) {
+        self.clear_block_id();
+        self.clear_commitment();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for ExpiredCommitment {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for ExpiredCommitment {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Snapshot {
+    // message fields
+    pub blocks: ::protobuf::RepeatedField<Block>,
+    pub request_id: u64,
+    pub expired: ::protobuf::RepeatedField<ExpiredCommitment>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Snapshot {
+    pub fn new() -> Snapshot {
+        ::std::default::Default::default()
+    }
+
+    // repeated .protobuf.pb.Block blocks = 1;
+
+    pub fn clear_blocks(&mut self) {
+        self.blocks.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_blocks(&mut self, v: ::protobuf::RepeatedField<Block>) {
+        self.blocks = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_blocks(&mut self) -> &mut ::protobuf::RepeatedField<Block> {
+        &mut self.blocks
+    }
+
+    // Take field
+    pub fn take_blocks(&mut self) -> ::protobuf::RepeatedField<Block> {
+        ::std::mem::replace(&mut self.blocks, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    // uint64 request_id = 2;
+
+    pub fn clear_request_id(&mut self) {
+        self.request_id = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_request_id(&mut self, v: u64) {
+        self.request_id = v;
+    }
+
+    pub fn get_request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    // repeated .protobuf.pb.ExpiredCommitment expired = 3;
+
+    pub fn clear_expired(&mut self) {
+        self.expired.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_expired(&mut self, v: ::protobuf::RepeatedField<ExpiredCommitment>) {
+        self.expired = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_expired(&mut self) -> &mut ::protobuf::RepeatedField<ExpiredCommitment> {
+        &mut self.expired
+    }
+
+    // Take field
+    pub fn take_expired(&mut self) -> ::protobuf::RepeatedField<ExpiredCommitment> {
+        ::std::mem::replace(&mut self.expired, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_expired(&self) -> &[ExpiredCommitment] {
+        &self.expired
+    }
+}
+
+impl ::protobuf::Message for Snapshot {
+    fn is_initialized(&self) -> bool {
+        for v in &self.blocks {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.expired {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.blocks)?;
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.request_id = tmp;
+                },
+                3 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.expired)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        for value in &self.blocks {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        if self.request_id != 0 {
+            my_size += ::protobuf::rt::value_size(2, self.request_id, ::protobuf::wire_format::WireTypeVarint);
+        }
+        for value in &self.expired {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        for v in &self.blocks {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        if self.request_id != 0 {
+            os.write_uint64(2, self.request_id)?;
+        }
+        for v in &self.expired {
+            os.write_tag(3, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Snapshot {
+        Snapshot::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Block>>(
+                    "blocks",
+                    |m: &Snapshot| { &m.blocks },
+                    |m: &mut Snapshot| { &mut m.blocks },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "request_id",
+                    |m: &Snapshot| { &m.request_id },
+                    |m: &mut Snapshot| { &mut m.request_id },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<ExpiredCommitment>>(
+                    "expired",
+                    |m: &Snapshot| { &m.expired },
+                    |m: &mut Snapshot| { &mut m.expired },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Snapshot>(
+                    "Snapshot",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Snapshot {
+        static mut instance: ::protobuf::lazy::Lazy<Snapshot> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Snapshot,
+        };
+        unsafe {
+            instance.get(Snapshot::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Snapshot {
+    fn clear(&mut self) {
+        self.clear_blocks();
+        self.clear_request_id();
+        self.clear_expired();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Snapshot {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct SnapshotRequest {
+    // message fields
+    pub last_block: ::protobuf::SingularPtrField<Hash>,
+    pub request_id: u64,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl SnapshotRequest {
+    pub fn new() -> SnapshotRequest {
+        ::std::default::Default::default()
+    }
+
+    // .protobuf.pb.Hash last_block = 1;
+
+    pub fn clear_last_block(&mut self) {
+        self.last_block.clear();
+    }
+
+    pub fn has_last_block(&self) -> bool {
+        self.last_block.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_last_block(&mut self, v: Hash) {
+        self.last_block = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_last_block(&mut self) -> &mut Hash {
+        if self.last_block.is_none() {
+            self.last_block.set_default();
+        }
+        self.last_block.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_last_block(&mut self) -> Hash {
+        self.last_block.take().unwrap_or_else(|| Hash::new())
+    }
+
+    pub fn get_last_block(&self) -> &Hash {
+        self.last_block.as_ref().unwrap_or_else(|| Hash::default_instance())
+    }
+
+    // uint64 request_id = 2;
+
+    pub fn clear_request_id(&mut self) {
+        self.request_id = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_request_id(&mut self, v: u64) {
+        self.request_id = v;
+    }
+
+    pub fn get_request_id(&self) -> u64 {
+        self.request_id
+    }
+}
+
+impl ::protobuf::Message for SnapshotRequest {
+    fn is_initialized(&self) -> bool {
+        for v in &self.last_block {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.last_block)?;
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.request_id = tmp;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.last_block.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if self.request_id != 0 {
+            my_size += ::protobuf::rt::value_size(2, self.request_id, ::protobuf::wire_format::WireTypeVarint);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.last_block.as_ref() {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if self.request_id != 0 {
+            os.write_uint64(2, self.request_id)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> SnapshotRequest {
+        SnapshotRequest::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Hash>>(
+                    "last_block",
+                    |m: &SnapshotRequest| { &m.last_block },
+                    |m: &mut SnapshotRequest| { &mut m.last_block },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "request_id",
+                    |m: &SnapshotRequest| { &m.request_id },
+                    |m: &mut SnapshotRequest| { &mut m.request_id },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<SnapshotRequest>(
+                    "SnapshotRequest",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static SnapshotRequest {
+        static mut instance: ::protobuf::lazy::Lazy<SnapshotRequest> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const SnapshotRequest,
+        };
+        unsafe {
+            instance.get(SnapshotRequest::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for SnapshotRequest {
+    fn clear(&mut self) {
+        self.clear_last_block();
+        self.clear_request_id();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for SnapshotRequest {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for SnapshotRequest {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct BlockHeader {
+    // message oneof groups
+    pub header: ::std::option::Option<BlockHeader_oneof_header>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+#[derive(Clone,PartialEq)]
+pub enum BlockHeader_oneof_header {
+    key_block_header(KeyBlockHeader),
+    monetary_block_header(MonetaryBlockHeader),
+}
+
+impl BlockHeader {
+    pub fn new() -> BlockHeader {
+        ::std::default::Default::default()
+    }
+
+    // .protobuf.pb.KeyBlockHeader key_block_header = 1;
+
+    pub fn clear_key_block_header(&mut self) {
+        self.header = ::std::option::Option::None;
+    }
+
+    pub fn has_key_block_header(&self) -> bool {
+        match self.header {
+            ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_key_block_header(&mut self, v: KeyBlockHeader) {
+        self.header = ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_key_block_header(&mut self) -> &mut KeyBlockHeader {
+        if let ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(_)) = self.header {
+        } else {
+            self.header = ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(KeyBlockHeader::new()));
+        }
+        match self.header {
+            ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_key_block_header(&mut self) -> KeyBlockHeader {
+        if self.has_key_block_header() {
+            match self.header.take() {
+                ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            KeyBlockHeader::new()
+        }
+    }
+
+    pub fn get_key_block_header(&self) -> &KeyBlockHeader {
+        match self.header {
+            ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(ref v)) => v,
+            _ => KeyBlockHeader::default_instance(),
+        }
+    }
+
+    // .protobuf.pb.MonetaryBlockHeader monetary_block_header = 2;
+
+    pub fn clear_monetary_block_header(&mut self) {
+        self.header = ::std::option::Option::None;
+    }
+
+    pub fn has_monetary_block_header(&self) -> bool {
+        match self.header {
+            ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_monetary_block_header(&mut self, v: MonetaryBlockHeader) {
+        self.header = ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_monetary_block_header(&mut self) -> &mut MonetaryBlockHeader {
+        if let ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(_)) = self.header {
+        } else {
+            self.header = ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(MonetaryBlockHeader::new()));
+        }
+        match self.header {
+            ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_monetary_block_header(&mut self) -> MonetaryBlockHeader {
+        if self.has_monetary_block_header() {
+            match self.header.take() {
+                ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            MonetaryBlockHeader::new()
+        }
+    }
+
+    pub fn get_monetary_block_header(&self) -> &MonetaryBlockHeader {
+        match self.header {
+            ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(ref v)) => v,
+            _ => MonetaryBlockHeader::default_instance(),
+        }
+    }
+}
+
+impl ::protobuf::Message for BlockHeader {
+    fn is_initialized(&self) -> bool {
+        if let Some(BlockHeader_oneof_header::key_block_header(ref v)) = self.header {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
+        if let Some(BlockHeader_oneof_header::monetary_block_header(ref v)) = self.header {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.header = ::std::option::Option::Some(BlockHeader_oneof_header::key_block_header(is.read_message()?));
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.header = ::std::option::Option::Some(BlockHeader_oneof_header::monetary_block_header(is.read_message()?));
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let ::std::option::Option::Some(ref v) = self.header {
+            match v {
+                &BlockHeader_oneof_header::key_block_header(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
+                &BlockHeader_oneof_header::monetary_block_header(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
+            };
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if let ::std::option::Option::Some(ref v) = self.header {
+            match v {
+                &BlockHeader_oneof_header::key_block_header(ref v) => {
+                    os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
+                &BlockHeader_oneof_header::monetary_block_header(ref v) => {
+                    os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
+            };
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> BlockHeader {
+        BlockHeader::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, KeyBlockHeader>(
+                    "key_block_header",
+                    BlockHeader::has_key_block_header,
+                    BlockHeader::get_key_block_header,
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, MonetaryBlockHeader>(
+                    "monetary_block_header",
+                    BlockHeader::has_monetary_block_header,
+                    BlockHeader::get_monetary_block_header,
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<BlockHeader>(
+                    "BlockHeader",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static BlockHeader {
+        static mut instance: ::protobuf::lazy::Lazy<BlockHeader> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const BlockHeader,
+        };
+        unsafe {
+            instance.get(BlockHeader::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for BlockHeader {
+    fn clear(&mut self) {
+        self.clear_key_block_header();
+        self.clear_monetary_block_header();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for BlockHeader {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for BlockHeader {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct HeadersRequest {
+    // message fields
+    pub from_epoch: u64,
+    pub to_epoch: u64,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl HeadersRequest {
+    pub fn new() -> HeadersRequest {
+        ::std::default::Default::default()
+    }
+
+    // uint64 from_epoch = 1;
+
+    pub fn clear_from_epoch(&mut self) {
+        self.from_epoch = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_from_epoch(&mut self, v: u64) {
+        self.from_epoch = v;
+    }
+
+    pub fn get_from_epoch(&self) -> u64 {
+        self.from_epoch
+    }
+
+    // uint64 to_epoch = 2;
+
+    pub fn clear_to_epoch(&mut self) {
+        self.to_epoch = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_to_epoch(&mut self, v: u64) {
+        self.to_epoch = v;
+    }
+
+    pub fn get_to_epoch(&self) -> u64 {
+        self.to_epoch
+    }
+}
+
+impl ::protobuf::Message for HeadersRequest {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.from_epoch = tmp;
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.to_epoch = tmp;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if self.from_epoch != 0 {
+            my_size += ::protobuf::rt::value_size(1, self.from_epoch, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.to_epoch != 0 {
+            my_size += ::protobuf::rt::value_size(2, self.to_epoch, ::protobuf::wire_format::WireTypeVarint);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if self.from_epoch != 0 {
+            os.write_uint64(1, self.from_epoch)?;
+        }
+        if self.to_epoch != 0 {
+            os.write_uint64(2, self.to_epoch)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> HeadersRequest {
+        HeadersRequest::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "from_epoch",
+                    |m: &HeadersRequest| { &m.from_epoch },
+                    |m: &mut HeadersRequest| { &mut m.from_epoch },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "to_epoch",
+                    |m: &HeadersRequest| { &m.to_epoch },
+                    |m: &mut HeadersRequest| { &mut m.to_epoch },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<HeadersRequest>(
+                    "HeadersRequest",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static HeadersRequest {
+        static mut instance: ::protobuf::lazy::Lazy<HeadersRequest> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const HeadersRequest,
+        };
+        unsafe {
+            instance.get(HeadersRequest::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for HeadersRequest {
+    fn clear(&mut self) {
+        self.clear_from_epoch();
+        self.clear_to_epoch();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for HeadersRequest {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for HeadersRequest {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Headers {
+    // message fields
+    pub headers: ::protobuf::RepeatedField<BlockHeader>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Headers {
+    pub fn new() -> Headers {
+        ::std::default::Default::default()
+    }
+
+    // repeated .protobuf.pb.BlockHeader headers = 1;
+
+    pub fn clear_headers(&mut self) {
+        self.headers.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_headers(&mut self, v: ::protobuf::RepeatedField<BlockHeader>) {
+        self.headers = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_headers(&mut self) -> &mut ::protobuf::RepeatedField<BlockHeader> {
+        &mut self.headers
+    }
+
+    // Take field
+    pub fn take_headers(&mut self) -> ::protobuf::RepeatedField<BlockHeader> {
+        ::std::mem::replace(&mut self.headers, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_headers(&self) -> &[BlockHeader] {
+        &self.headers
+    }
+}
+
+impl ::protobuf::Message for Headers {
+    fn is_initialized(&self) -> bool {
+        for v in &self.headers {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.headers)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        for value in &self.headers {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        for v in &self.headers {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+    fn as_any_mut(&mut self) -> &mut ::std::any::Any {
+        self as &mut ::std::any::Any
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<::std::any::Any> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Headers {
+        Headers::new()
     }
 
     fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
@@ -5407,23 +7834,13 @@ impl ::protobuf::Message for KeyBlockHeader {
         unsafe {
             descriptor.get(|| {
                 let mut fields = ::std::vec::Vec::new();
-                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<BaseBlockHeader>>(
-                    "base",
-                    |m: &KeyBlockHeader| { &m.base },
-                    |m: &mut KeyBlockHeader| { &mut m.base },
-                ));
-                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<SecurePublicKey>>(
-                    "leader",
-                    |m: &KeyBlockHeader| { &m.leader },
-                    |m: &mut KeyBlockHeader| { &mut m.leader },
-                ));
-                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<SecurePublicKey>>(
-                    "witnesses",
-                    |m: &KeyBlockHeader| { &m.witnesses },
-                    |m: &mut KeyBlockHeader| { &mut m.witnesses },
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<BlockHeader>>(
+                    "headers",
+                    |m: &Headers| { &m.headers },
+                    |m: &mut Headers| { &mut m.headers },
                 ));
-                ::protobuf::reflect::MessageDescriptor::new::<KeyBlockHeader>(
-                    "KeyBlockHeader",
+                ::protobuf::reflect::MessageDescriptor::new::<Headers>(
+                    "Headers",
                     fields,
                     file_descriptor_proto()
                 )
@@ -5431,89 +7848,79 @@ impl ::protobuf::Message for KeyBlockHeader {
         }
     }
 
-    fn default_instance() -> &'static KeyBlockHeader {
-        static mut instance: ::protobuf::lazy::Lazy<KeyBlockHeader> = ::protobuf::lazy::Lazy {
+    fn default_instance() -> &'static Headers {
+        static mut instance: ::protobuf::lazy::Lazy<Headers> = ::protobuf::lazy::Lazy {
             lock: ::protobuf::lazy::ONCE_INIT,
-            ptr: 0 as *const KeyBlockHeader,
+            ptr: 0 as *const Headers,
         };
         unsafe {
-            instance.get(KeyBlockHeader::new)
+            instance.get(Headers::new)
         }
     }
 }
 
-impl ::protobuf::Clear for KeyBlockHeader {
+impl ::protobuf::Clear for Headers {
     fn clear(&mut self) {
-        self.clear_base();
-        self.clear_leader();
-        self.clear_witnesses();
+        self.clear_headers();
         self.unknown_fields.clear();
     }
 }
 
-impl ::std::fmt::Debug for KeyBlockHeader {
+impl ::std::fmt::Debug for Headers {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         ::protobuf::text_format::fmt(self, f)
     }
 }
 
-impl ::protobuf::reflect::ProtobufValue for KeyBlockHeader {
+impl ::protobuf::reflect::ProtobufValue for Headers {
     fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
         ::protobuf::reflect::ProtobufValueRef::Message(self)
     }
 }
 
 #[derive(PartialEq,Clone,Default)]
-pub struct KeyBlock {
+pub struct BlocksRequest {
     // message fields
-    pub header: ::protobuf::SingularPtrField<KeyBlockHeader>,
+    pub hashes: ::protobuf::RepeatedField<Hash>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
 }
 
-impl KeyBlock {
-    pub fn new() -> KeyBlock {
+impl BlocksRequest {
+    pub fn new() -> BlocksRequest {
         ::std::default::Default::default()
     }
 
-    // .protobuf.pb.KeyBlockHeader header = 1;
-
-    pub fn clear_header(&mut self) {
-        self.header.clear();
-    }
+    // repeated .protobuf.pb.Hash hashes = 1;
 
-    pub fn has_header(&self) -> bool {
-        self.header.is_some()
+    pub fn clear_hashes(&mut self) {
+        self.hashes.clear();
     }
 
     // Param is passed by value, moved
-    pub fn set_header(&mut self, v: KeyBlockHeader) {
-        self.header = ::protobuf::SingularPtrField::some(v);
+    pub fn set_hashes(&mut self, v: ::protobuf::RepeatedField<Hash>) {
+        self.hashes = v;
     }
 
     // Mutable pointer to the field.
-    // If field is not initialized, it is initialized with default value first.
-    pub fn mut_header(&mut self) -> &mut KeyBlockHeader {
-        if self.header.is_none() {
-            self.header.set_default();
-        }
-        self.header.as_mut().unwrap()
+    pub fn mut_hashes(&mut self) -> &mut ::protobuf::RepeatedField<Hash> {
+        &mut self.hashes
     }
 
     // Take field
-    pub fn take_header(&mut self) -> KeyBlockHeader {
-        self.header.take().unwrap_or_else(|| KeyBlockHeader::new())
+    pub fn take_hashes(&mut self) -> ::protobuf::RepeatedField<Hash> {
+        ::std::mem::replace(&mut self.hashes, ::protobuf::RepeatedField::new())
     }
 
-    pub fn get_header(&self) -> &KeyBlockHeader {
-        self.header.as_ref().unwrap_or_else(|| KeyBlockHeader::default_instance())
+    pub fn get_hashes(&self) -> &[Hash] {
+        &self.hashes
     }
 }
 
-impl ::protobuf::Message for KeyBlock {
+impl ::protobuf::Message for BlocksRequest {
     fn is_initialized(&self) -> bool {
-        for v in &self.header {
+        for v in &self.hashes {
             if !v.is_initialized() {
                 return false;
             }
@@ -5526,7 +7933,7 @@ impl ::protobuf::Message for KeyBlock {
             let (field_number, wire_type) = is.read_tag_unpack()?;
             match field_number {
                 1 => {
-                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.header)?;
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.hashes)?;
                 },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
@@ -5540,21 +7947,21 @@ impl ::protobuf::Message for KeyBlock {
     #[allow(unused_variables)]
     fn compute_size(&self) -> u32 {
         let mut my_size = 0;
-        if let Some(ref v) = self.header.as_ref() {
-            let len = v.compute_size();
+        for value in &self.hashes {
+            let len = value.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
-        }
+        };
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
     }
 
     fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
-        if let Some(ref v) = self.header.as_ref() {
+        for v in &self.hashes {
             os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
-        }
+        };
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -5585,8 +7992,8 @@ impl ::protobuf::Message for KeyBlock {
         Self::descriptor_static()
     }
 
-    fn new() -> KeyBlock {
-        KeyBlock::new()
+    fn new() -> BlocksRequest {
+        BlocksRequest::new()
     }
 
     fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
@@ -5597,13 +8004,13 @@ impl ::protobuf::Message for KeyBlock {
         unsafe {
             descriptor.get(|| {
                 let mut fields = ::std::vec::Vec::new();
-                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<KeyBlockHeader>>(
-                    "header",
-                    |m: &KeyBlock| { &m.header },
-                    |m: &mut KeyBlock| { &mut m.header },
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Hash>>(
+                    "hashes",
+                    |m: &BlocksRequest| { &m.hashes },
+                    |m: &mut BlocksRequest| { &mut m.hashes },
                 ));
-                ::protobuf::reflect::MessageDescriptor::new::<KeyBlock>(
-                    "KeyBlock",
+                ::protobuf::reflect::MessageDescriptor::new::<BlocksRequest>(
+                    "BlocksRequest",
                     fields,
                     file_descriptor_proto()
                 )
@@ -5611,167 +8018,83 @@ impl ::protobuf::Message for KeyBlock {
         }
     }
 
-    fn default_instance() -> &'static KeyBlock {
-        static mut instance: ::protobuf::lazy::Lazy<KeyBlock> = ::protobuf::lazy::Lazy {
+    fn default_instance() -> &'static BlocksRequest {
+        static mut instance: ::protobuf::lazy::Lazy<BlocksRequest> = ::protobuf::lazy::Lazy {
             lock: ::protobuf::lazy::ONCE_INIT,
-            ptr: 0 as *const KeyBlock,
+            ptr: 0 as *const BlocksRequest,
         };
         unsafe {
-            instance.get(KeyBlock::new)
+            instance.get(BlocksRequest::new)
         }
     }
 }
 
-impl ::protobuf::Clear for KeyBlock {
+impl ::protobuf::Clear for BlocksRequest {
     fn clear(&mut self) {
-        self.clear_header();
+        self.clear_hashes();
         self.unknown_fields.clear();
     }
 }
 
-impl ::std::fmt::Debug for KeyBlock {
+impl ::std::fmt::Debug for BlocksRequest {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         ::protobuf::text_format::fmt(self, f)
     }
 }
 
-impl ::protobuf::reflect::ProtobufValue for KeyBlock {
+impl ::protobuf::reflect::ProtobufValue for BlocksRequest {
     fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
         ::protobuf::reflect::ProtobufValueRef::Message(self)
     }
 }
 
 #[derive(PartialEq,Clone,Default)]
-pub struct Block {
-    // message oneof groups
-    pub block: ::std::option::Option<Block_oneof_block>,
+pub struct Blocks {
+    // message fields
+    pub blocks: ::protobuf::RepeatedField<Block>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
 }
 
-#[derive(Clone,PartialEq)]
-pub enum Block_oneof_block {
-    key_block(KeyBlock),
-    monetary_block(MonetaryBlock),
-}
-
-impl Block {
-    pub fn new() -> Block {
+impl Blocks {
+    pub fn new() -> Blocks {
         ::std::default::Default::default()
     }
 
-    // .protobuf.pb.KeyBlock key_block = 1;
-
-    pub fn clear_key_block(&mut self) {
-        self.block = ::std::option::Option::None;
-    }
-
-    pub fn has_key_block(&self) -> bool {
-        match self.block {
-            ::std::option::Option::Some(Block_oneof_block::key_block(..)) => true,
-            _ => false,
-        }
-    }
-
-    // Param is passed by value, moved
-    pub fn set_key_block(&mut self, v: KeyBlock) {
-        self.block = ::std::option::Option::Some(Block_oneof_block::key_block(v))
-    }
-
-    // Mutable pointer to the field.
-    pub fn mut_key_block(&mut self) -> &mut KeyBlock {
-        if let ::std::option::Option::Some(Block_oneof_block::key_block(_)) = self.block {
-        } else {
-            self.block = ::std::option::Option::Some(Block_oneof_block::key_block(KeyBlock::new()));
-        }
-        match self.block {
-            ::std::option::Option::Some(Block_oneof_block::key_block(ref mut v)) => v,
-            _ => panic!(),
-        }
-    }
-
-    // Take field
-    pub fn take_key_block(&mut self) -> KeyBlock {
-        if self.has_key_block() {
-            match self.block.take() {
-                ::std::option::Option::Some(Block_oneof_block::key_block(v)) => v,
-                _ => panic!(),
-            }
-        } else {
-            KeyBlock::new()
-        }
-    }
-
-    pub fn get_key_block(&self) -> &KeyBlock {
-        match self.block {
-            ::std::option::Option::Some(Block_oneof_block::key_block(ref v)) => v,
-            _ => KeyBlock::default_instance(),
-        }
-    }
-
-    // .protobuf.pb.MonetaryBlock monetary_block = 2;
-
-    pub fn clear_monetary_block(&mut self) {
-        self.block = ::std::option::Option::None;
-    }
+    // repeated .protobuf.pb.Block blocks = 1;
 
-    pub fn has_monetary_block(&self) -> bool {
-        match self.block {
-            ::std::option::Option::Some(Block_oneof_block::monetary_block(..)) => true,
-            _ => false,
-        }
+    pub fn clear_blocks(&mut self) {
+        self.blocks.clear();
     }
 
     // Param is passed by value, moved
-    pub fn set_monetary_block(&mut self, v: MonetaryBlock) {
-        self.block = ::std::option::Option::Some(Block_oneof_block::monetary_block(v))
+    pub fn set_blocks(&mut self, v: ::protobuf::RepeatedField<Block>) {
+        self.blocks = v;
     }
 
     // Mutable pointer to the field.
-    pub fn mut_monetary_block(&mut self) -> &mut MonetaryBlock {
-        if let ::std::option::Option::Some(Block_oneof_block::monetary_block(_)) = self.block {
-        } else {
-            self.block = ::std::option::Option::Some(Block_oneof_block::monetary_block(MonetaryBlock::new()));
-        }
-        match self.block {
-            ::std::option::Option::Some(Block_oneof_block::monetary_block(ref mut v)) => v,
-            _ => panic!(),
-        }
+    pub fn mut_blocks(&mut self) -> &mut ::protobuf::RepeatedField<Block> {
+        &mut self.blocks
     }
 
     // Take field
-    pub fn take_monetary_block(&mut self) -> MonetaryBlock {
-        if self.has_monetary_block() {
-            match self.block.take() {
-                ::std::option::Option::Some(Block_oneof_block::monetary_block(v)) => v,
-                _ => panic!(),
-            }
-        } else {
-            MonetaryBlock::new()
-        }
+    pub fn take_blocks(&mut self) -> ::protobuf::RepeatedField<Block> {
+        ::std::mem::replace(&mut self.blocks, ::protobuf::RepeatedField::new())
     }
 
-    pub fn get_monetary_block(&self) -> &MonetaryBlock {
-        match self.block {
-            ::std::option::Option::Some(Block_oneof_block::monetary_block(ref v)) => v,
-            _ => MonetaryBlock::default_instance(),
-        }
+    pub fn get_blocks(&self) -> &[Block] {
+        &self.blocks
     }
 }
 
-impl ::protobuf::Message for Block {
+impl ::protobuf::Message for Blocks {
     fn is_initialized(&self) -> bool {
-        if let Some(Block_oneof_block::key_block(ref v)) = self.block {
+        for v in &self.blocks {
             if !v.is_initialized() {
                 return false;
             }
-        }
-        if let Some(Block_oneof_block::monetary_block(ref v)) = self.block {
-            if !v.is_initialized() {
-                return false;
-            }
-        }
+        };
         true
     }
 
@@ -5780,16 +8103,7 @@ impl ::protobuf::Message for Block {
             let (field_number, wire_type) = is.read_tag_unpack()?;
             match field_number {
                 1 => {
-                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
-                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
-                    }
-                    self.block = ::std::option::Option::Some(Block_oneof_block::key_block(is.read_message()?));
-                },
-                2 => {
-                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
-                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
-                    }
-                    self.block = ::std::option::Option::Some(Block_oneof_block::monetary_block(is.read_message()?));
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.blocks)?;
                 },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
@@ -5803,38 +8117,21 @@ impl ::protobuf::Message for Block {
     #[allow(unused_variables)]
     fn compute_size(&self) -> u32 {
         let mut my_size = 0;
-        if let ::std::option::Option::Some(ref v) = self.block {
-            match v {
-                &Block_oneof_block::key_block(ref v) => {
-                    let len = v.compute_size();
-                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
-                },
-                &Block_oneof_block::monetary_block(ref v) => {
-                    let len = v.compute_size();
-                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
-                },
-            };
-        }
+        for value in &self.blocks {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
     }
 
     fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
-        if let ::std::option::Option::Some(ref v) = self.block {
-            match v {
-                &Block_oneof_block::key_block(ref v) => {
-                    os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
-                    os.write_raw_varint32(v.get_cached_size())?;
-                    v.write_to_with_cached_sizes(os)?;
-                },
-                &Block_oneof_block::monetary_block(ref v) => {
-                    os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
-                    os.write_raw_varint32(v.get_cached_size())?;
-                    v.write_to_with_cached_sizes(os)?;
-                },
-            };
-        }
+        for v in &self.blocks {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -5865,8 +8162,8 @@ impl ::protobuf::Message for Block {
         Self::descriptor_static()
     }
 
-    fn new() -> Block {
-        Block::new()
+    fn new() -> Blocks {
+        Blocks::new()
     }
 
     fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
@@ -5877,18 +8174,13 @@ impl ::protobuf::Message for Block {
         unsafe {
             descriptor.get(|| {
                 let mut fields = ::std::vec::Vec::new();
-                fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, KeyBlock>(
-                    "key_block",
-                    Block::has_key_block,
-                    Block::get_key_block,
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Block>>(
+                    "blocks",
+                    |m: &Blocks| { &m.blocks },
+                    |m: &mut Blocks| { &mut m.blocks },
                 ));
-                fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, MonetaryBlock>(
-                    "monetary_block",
-                    Block::has_monetary_block,
-                    Block::get_monetary_block,
-                ));
-                ::protobuf::reflect::MessageDescriptor::new::<Block>(
-                    "Block",
+                ::protobuf::reflect::MessageDescriptor::new::<Blocks>(
+                    "Blocks",
                     fields,
                     file_descriptor_proto()
                 )
@@ -5896,32 +8188,31 @@ impl ::protobuf::Message for Block {
         }
     }
 
-    fn default_instance() -> &'static Block {
-        static mut instance: ::protobuf::lazy::Lazy<Block> = ::protobuf::lazy::Lazy {
+    fn default_instance() -> &'static Blocks {
+        static mut instance: ::protobuf::lazy::Lazy<Blocks> = ::protobuf::lazy::Lazy {
             lock: ::protobuf::lazy::ONCE_INIT,
-            ptr: 0 as *const Block,
+            ptr: 0 as *const Blocks,
         };
         unsafe {
-            instance.get(Block::new)
+            instance.get(Blocks::new)
         }
     }
 }
 
-impl ::protobuf::Clear for Block {
+impl ::protobuf::Clear for Blocks {
     fn clear(&mut self) {
-        self.clear_key_block();
-        self.clear_monetary_block();
+        self.clear_blocks();
         self.unknown_fields.clear();
     }
 }
 
-impl ::std::fmt::Debug for Block {
+impl ::std::fmt::Debug for Blocks {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         ::protobuf::text_format::fmt(self, f)
     }
 }
 
-impl ::protobuf::reflect::ProtobufValue for Block {
+impl ::protobuf::reflect::ProtobufValue for Blocks {
     fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
         ::protobuf::reflect::ProtobufValueRef::Message(self)
     }
@@ -5969,33 +8260,58 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     .pb.HashB\x02\x18\0\x12'\n\x06txouts\x18\x02\x20\x03(\x0b2\x13.protobuf.\
     pb.OutputB\x02\x18\0\x12\"\n\x05gamma\x18\x03\x20\x01(\x0b2\x0f.protobuf\
     .pb.FrB\x02\x18\0\x12\x0f\n\x03fee\x18\x04\x20\x01(\x03B\x02\x18\0\x12(\
-    \n\x03sig\x18\x05\x20\x01(\x0b2\x17.protobuf.pb.SchnorrSigB\x02\x18\0\"y\
-    \n\x0fBaseBlockHeader\x12\x13\n\x07version\x18\x01\x20\x01(\x04B\x02\x18\
-    \0\x12'\n\x08previous\x18\x02\x20\x01(\x0b2\x11.protobuf.pb.HashB\x02\
-    \x18\0\x12\x11\n\x05epoch\x18\x03\x20\x01(\x04B\x02\x18\0\x12\x15\n\ttim\
-    estamp\x18\x04\x20\x01(\x04B\x02\x18\0\"\xce\x01\n\x13MonetaryBlockHeade\
-    r\x12.\n\x04base\x18\x01\x20\x01(\x0b2\x1c.protobuf.pb.BaseBlockHeaderB\
-    \x02\x18\0\x12\"\n\x05gamma\x18\x02\x20\x01(\x0b2\x0f.protobuf.pb.FrB\
-    \x02\x18\0\x120\n\x11inputs_range_hash\x18\x03\x20\x01(\x0b2\x11.protobu\
-    f.pb.HashB\x02\x18\0\x121\n\x12outputs_range_hash\x18\x04\x20\x01(\x0b2\
-    \x11.protobuf.pb.HashB\x02\x18\0\"~\n\nMerkleNode\x12#\n\x04hash\x18\x01\
-    \x20\x01(\x0b2\x11.protobuf.pb.HashB\x02\x18\0\x12\x10\n\x04left\x18\x02\
-    \x20\x01(\x04B\x02\x18\0\x12\x11\n\x05right\x18\x03\x20\x01(\x04B\x02\
-    \x18\0\x12&\n\x05value\x18\x04\x20\x01(\x0b2\x13.protobuf.pb.OutputB\x02\
-    \x18\0\"h\n\x11MonetaryBlockBody\x12%\n\x06inputs\x18\x01\x20\x03(\x0b2\
-    \x11.protobuf.pb.HashB\x02\x18\0\x12,\n\x07outputs\x18\x02\x20\x03(\x0b2\
-    \x17.protobuf.pb.MerkleNodeB\x02\x18\0\"w\n\rMonetaryBlock\x124\n\x06hea\
-    der\x18\x01\x20\x01(\x0b2\x20.protobuf.pb.MonetaryBlockHeaderB\x02\x18\0\
-    \x120\n\x04body\x18\x02\x20\x01(\x0b2\x1e.protobuf.pb.MonetaryBlockBodyB\
-    \x02\x18\0\"\xa7\x01\n\x0eKeyBlockHeader\x12.\n\x04base\x18\x01\x20\x01(\
-    \x0b2\x1c.protobuf.pb.BaseBlockHeaderB\x02\x18\0\x120\n\x06leader\x18\
-    \x02\x20\x01(\x0b2\x1c.protobuf.pb.SecurePublicKeyB\x02\x18\0\x123\n\twi\
-    tnesses\x18\x03\x20\x03(\x0b2\x1c.protobuf.pb.SecurePublicKeyB\x02\x18\0\
-    \";\n\x08KeyBlock\x12/\n\x06header\x18\x01\x20\x01(\x0b2\x1b.protobuf.pb\
-    .KeyBlockHeaderB\x02\x18\0\"z\n\x05Block\x12.\n\tkey_block\x18\x01\x20\
-    \x01(\x0b2\x15.protobuf.pb.KeyBlockH\0B\x02\x18\0\x128\n\x0emonetary_blo\
-    ck\x18\x02\x20\x01(\x0b2\x1a.protobuf.pb.MonetaryBlockH\0B\x02\x18\0B\
-    \x07\n\x05blockB\0b\x06proto3\
+    \n\x03sig\x18\x05\x20\x01(\x0b2\x17.protobuf.pb.SchnorrSigB\x02\x18\0\"\
+    \\\n\x12PendingTransaction\x121\n\x0btransaction\x18\x01\x20\x01(\x0b2\
+    \x18.protobuf.pb.TransactionB\x02\x18\0\x12\x13\n\x07arrived\x18\x02\x20\
+    \x01(\x04B\x02\x18\0\"y\n\x0fBaseBlockHeader\x12\x13\n\x07version\x18\
+    \x01\x20\x01(\x04B\x02\x18\0\x12'\n\x08previous\x18\x02\x20\x01(\x0b2\
+    \x11.protobuf.pb.HashB\x02\x18\0\x12\x11\n\x05epoch\x18\x03\x20\x01(\x04\
+    B\x02\x18\0\x12\x15\n\ttimestamp\x18\x04\x20\x01(\x04B\x02\x18\0\"\x99\
+    \x02\n\x13MonetaryBlockHeader\x12.\n\x04base\x18\x01\x20\x01(\x0b2\x1c.p\
+    rotobuf.pb.BaseBlockHeaderB\x02\x18\0\x12\"\n\x05gamma\x18\x02\x20\x01(\
+    \x0b2\x0f.protobuf.pb.FrB\x02\x18\0\x120\n\x11inputs_range_hash\x18\x03\
+    \x20\x01(\x0b2\x11.protobuf.pb.HashB\x02\x18\0\x121\n\x12outputs_range_h\
+    ash\x18\x04\x20\x01(\x0b2\x11.protobuf.pb.HashB\x02\x18\0\x12(\n\tutxo_r\
+    oot\x18\x05\x20\x01(\x0b2\x11.protobuf.pb.HashB\x02\x18\0\x12\x1f\n\x13m\
+    onetary_adjustment\x18\x06\x20\x01(\x03B\x02\x18\0\"~\n\nMerkleNode\x12#\
+    \n\x04hash\x18\x01\x20\x01(\x0b2\x11.protobuf.pb.HashB\x02\x18\0\x12\x10\
+    \n\x04left\x18\x02\x20\x01(\x04B\x02\x18\0\x12\x11\n\x05right\x18\x03\
+    \x20\x01(\x04B\x02\x18\0\x12&\n\x05value\x18\x04\x20\x01(\x0b2\x13.proto\
+    buf.pb.OutputB\x02\x18\0\"v\n\x11CompactMerkleNode\x12\x14\n\x08children\
+    \x18\x01\x20\x01(\x04B\x02\x18\0\x12&\n\x05value\x18\x02\x20\x01(\x0b2\
+    \x13.protobuf.pb.OutputB\x02\x18\0\x12#\n\x04hash\x18\x03\x20\x01(\x0b2\
+    \x11.protobuf.pb.HashB\x02\x18\0\"\xa5\x01\n\x11MonetaryBlockBody\x12%\n\
+    \x06inputs\x18\x01\x20\x03(\x0b2\x11.protobuf.pb.HashB\x02\x18\0\x12,\n\
+    \x07outputs\x18\x02\x20\x03(\x0b2\x17.protobuf.pb.MerkleNodeB\x02\x18\0\
+    \x12;\n\x0fcompact_outputs\x18\x03\x20\x03(\x0b2\x1e.protobuf.pb.Compact\
+    MerkleNodeB\x02\x18\0\"w\n\rMonetaryBlock\x124\n\x06header\x18\x01\x20\
+    \x01(\x0b2\x20.protobuf.pb.MonetaryBlockHeaderB\x02\x18\0\x120\n\x04body\
+    \x18\x02\x20\x01(\x0b2\x1e.protobuf.pb.MonetaryBlockBodyB\x02\x18\0\"\
+    \xa7\x01\n\x0eKeyBlockHeader\x12.\n\x04base\x18\x01\x20\x01(\x0b2\x1c.pr\
+    otobuf.pb.BaseBlockHeaderB\x02\x18\0\x120\n\x06leader\x18\x02\x20\x01(\
+    \x0b2\x1c.protobuf.pb.SecurePublicKeyB\x02\x18\0\x123\n\twitnesses\x18\
+    \x03\x20\x03(\x0b2\x1c.protobuf.pb.SecurePublicKeyB\x02\x18\0\";\n\x08Ke\
+    yBlock\x12/\n\x06header\x18\x01\x20\x01(\x0b2\x1b.protobuf.pb.KeyBlockHe\
+    aderB\x02\x18\0\"z\n\x05Block\x12.\n\tkey_block\x18\x01\x20\x01(\x0b2\
+    \x15.protobuf.pb.KeyBlockH\0B\x02\x18\0\x128\n\x0emonetary_block\x18\x02\
+    \x20\x01(\x0b2\x1a.protobuf.pb.MonetaryBlockH\0B\x02\x18\0B\x07\n\x05blo\
+    ck\"R\n\x11ExpiredCommitment\x12\x14\n\x08block_id\x18\x01\x20\x01(\x04B\
+    \x02\x18\0\x12'\n\ncommitment\x18\x02\x20\x01(\x0b2\x0f.protobuf.pb.PtB\
+    \x02\x18\0\"\x7f\n\x08Snapshot\x12&\n\x06blocks\x18\x01\x20\x03(\x0b2\
+    \x12.protobuf.pb.BlockB\x02\x18\0\x12\x16\n\nrequest_id\x18\x02\x20\x01(\
+    \x04B\x02\x18\0\x123\n\x07expired\x18\x03\x20\x03(\x0b2\x1e.protobuf.pb.\
+    ExpiredCommitmentB\x02\x18\0\"T\n\x0fSnapshotRequest\x12)\n\nlast_block\
+    \x18\x01\x20\x01(\x0b2\x11.protobuf.pb.HashB\x02\x18\0\x12\x16\n\nreques\
+    t_id\x18\x02\x20\x01(\x04B\x02\x18\0\"\x9b\x01\n\x0bBlockHeader\x12;\n\
+    \x10key_block_header\x18\x01\x20\x01(\x0b2\x1b.protobuf.pb.KeyBlockHeade\
+    rH\0B\x02\x18\0\x12E\n\x15monetary_block_header\x18\x02\x20\x01(\x0b2\
+    \x20.protobuf.pb.MonetaryBlockHeaderH\0B\x02\x18\0B\x08\n\x06header\">\n\
+    \x0eHeadersRequest\x12\x16\n\nfrom_epoch\x18\x01\x20\x01(\x04B\x02\x18\0\
+    \x12\x14\n\x08to_epoch\x18\x02\x20\x01(\x04B\x02\x18\0\"8\n\x07Headers\
+    \x12-\n\x07headers\x18\x01\x20\x03(\x0b2\x18.protobuf.pb.BlockHeaderB\
+    \x02\x18\0\"6\n\rBlocksRequest\x12%\n\x06hashes\x18\x01\x20\x03(\x0b2\
+    \x11.protobuf.pb.HashB\x02\x18\0\"0\n\x06Blocks\x12&\n\x06blocks\x18\x01\
+    \x20\x03(\x0b2\x12.protobuf.pb.BlockB\x02\x18\0B\0b\x06proto3\
 ";
 
 static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
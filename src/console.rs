@@ -89,6 +89,12 @@ struct ConsoleService {
     epoch_rx: UnboundedReceiver<EpochNotification>,
     /// A channel to receive notification about new messages..
     message_rx: UnboundedReceiver<MessageNotification>,
+    /// A channel to receive notification about expired data outputs.
+    expired_rx: UnboundedReceiver<ExpiredNotification>,
+    /// A channel to receive notification about supply audits.
+    audit_rx: UnboundedReceiver<AuditNotification>,
+    /// A channel to receive notification when the node is ready to exit.
+    shutdown_rx: Option<UnboundedReceiver<()>>,
 }
 
 impl ConsoleService {
@@ -100,6 +106,8 @@ impl ConsoleService {
         let balance_rx = node.subscribe_balance()?;
         let epoch_rx = node.subscribe_epoch()?;
         let message_rx = node.subscribe_messages()?;
+        let expired_rx = node.subscribe_expired()?;
+        let audit_rx = node.subscribe_audit()?;
         let service = ConsoleService {
             network,
             broker,
@@ -109,6 +117,9 @@ impl ConsoleService {
             balance_rx,
             epoch_rx,
             message_rx,
+            expired_rx,
+            audit_rx,
+            shutdown_rx: None,
         };
         Ok(service)
     }
@@ -165,6 +176,7 @@ impl ConsoleService {
         println!("publish TOPIC MESSAGE");
         println!("pay PUBLICKEY AMOUNT");
         println!("msg PUBLICKEY MESSAGE");
+        println!("audit");
         println!("");
     }
 
@@ -192,7 +204,7 @@ impl ConsoleService {
         println!("Usage: /msg PUBLICKEY MESSAGE [TTL]");
         println!(" - PUBLICKEY recipient's public key in HEX format");
         println!(" - MESSAGE some message");
-        println!(" - TTL the number of blocks for which this message should be kept");
+        println!(" - TTL the number of epochs for which this message should be kept");
         println!("");
     }
 
@@ -271,13 +283,28 @@ impl ConsoleService {
             if let Err(e) = self.node.msg(recipient, ttl, data.as_bytes().to_vec()) {
                 error!("Request failed: {}", e);
             }
+        } else if msg.trim() == "audit" {
+            info!("Requesting supply audit");
+            if let Err(e) = self.node.audit_supply() {
+                error!("Request failed: {}", e);
+            }
         } else {
             return ConsoleService::help();
         }
     }
 
-    fn on_exit(&self) {
-        std::process::exit(0);
+    /// Exit after the node saves its state, see Node::shutdown().
+    fn on_exit(&mut self) -> Poll<(), ()> {
+        if self.shutdown_rx.is_none() {
+            match self.node.shutdown() {
+                Ok(rx) => self.shutdown_rx = Some(rx),
+                Err(_) => std::process::exit(0), // the node is already stopped
+            }
+        }
+        match self.shutdown_rx.as_mut().unwrap().poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            _ => std::process::exit(0),
+        }
     }
 
     fn on_balance_changed(&self, balance: i64) {
@@ -291,6 +318,17 @@ impl ConsoleService {
     fn on_message_received(&self, msg: MessageNotification) {
         info!("Message => {}", String::from_utf8_lossy(&msg.data));
     }
+
+    fn on_output_expired(&self, msg: ExpiredNotification) {
+        info!("Expired => {}", msg.hash);
+    }
+
+    fn on_supply_audited(&self, msg: AuditNotification) {
+        match msg.supply {
+            Ok(supply) => info!("Supply => {}", supply),
+            Err(e) => error!("Supply => {}", e),
+        }
+    }
 }
 
 // Event loop.
@@ -306,7 +344,7 @@ impl Future for ConsoleService {
                     // Wake up readline thread after processing input.
                     self.stdin_th.thread().unpark();
                 }
-                Ok(Async::Ready(None)) => self.on_exit(),
+                Ok(Async::Ready(None)) => return self.on_exit(),
                 Ok(Async::NotReady) => break, // fall through
                 Err(()) => panic!(),
             }
@@ -315,7 +353,7 @@ impl Future for ConsoleService {
         loop {
             match self.balance_rx.poll() {
                 Ok(Async::Ready(Some(balance))) => self.on_balance_changed(balance),
-                Ok(Async::Ready(None)) => self.on_exit(),
+                Ok(Async::Ready(None)) => return self.on_exit(),
                 Ok(Async::NotReady) => break, // fall through
                 Err(()) => panic!("Wallet failure"),
             }
@@ -324,7 +362,7 @@ impl Future for ConsoleService {
         loop {
             match self.epoch_rx.poll() {
                 Ok(Async::Ready(Some(msg))) => self.on_epoch_changed(msg),
-                Ok(Async::Ready(None)) => self.on_exit(),
+                Ok(Async::Ready(None)) => return self.on_exit(),
                 Ok(Async::NotReady) => break, // fall through
                 Err(()) => panic!("Wallet failure"),
             }
@@ -333,7 +371,25 @@ impl Future for ConsoleService {
         loop {
             match self.message_rx.poll() {
                 Ok(Async::Ready(Some(msg))) => self.on_message_received(msg),
-                Ok(Async::Ready(None)) => self.on_exit(),
+                Ok(Async::Ready(None)) => return self.on_exit(),
+                Ok(Async::NotReady) => break, // fall through
+                Err(()) => panic!("Wallet failure"),
+            }
+        }
+
+        loop {
+            match self.expired_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => self.on_output_expired(msg),
+                Ok(Async::Ready(None)) => return self.on_exit(),
+                Ok(Async::NotReady) => break, // fall through
+                Err(()) => panic!("Wallet failure"),
+            }
+        }
+
+        loop {
+            match self.audit_rx.poll() {
+                Ok(Async::Ready(Some(msg))) => self.on_supply_audited(msg),
+                Ok(Async::Ready(None)) => return self.on_exit(),
                 Ok(Async::NotReady) => break, // fall through
                 Err(()) => panic!("Wallet failure"),
             }
@@ -25,21 +25,26 @@ mod consts;
 use atty;
 use clap;
 use clap::crate_version;
-use clap::{App, Arg, ArgMatches};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use dirs;
 use log::*;
 use log4rs::append::console::ConsoleAppender;
 use log4rs::config::{Appender, Config as LogConfig, Logger, Root};
 use log4rs::encode::pattern::PatternEncoder;
 use log4rs::{Error as LogError, Handle as LogHandle};
+use protobuf::Message;
 use rustyline;
 use std::error::Error;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
+use stegos_blockchain::ChainSpec;
 use stegos_config;
 use stegos_config::{Config, ConfigError};
+use stegos_crypto::hash::Hash;
 use stegos_keychain::*;
 use stegos_network::Network;
+use stegos_node::protos::IntoProto;
 use stegos_node::Node;
 use stegos_randhound::*;
 use tokio::runtime::Runtime;
@@ -94,6 +99,35 @@ fn initialize_logger(cfg: &Config) -> Result<LogHandle, LogError> {
     Ok(handle)
 }
 
+fn load_chain_spec(cfg: &Config) -> Result<Option<ChainSpec>, Box<dyn Error>> {
+    if cfg.general.chain_spec.is_empty() {
+        return Ok(None);
+    }
+    let chain_spec = ChainSpec::from_file(&cfg.general.chain_spec)?;
+    Ok(Some(chain_spec))
+}
+
+/// Create genesis blocks from a chain specification and save them to a directory.
+fn generate_genesis(args: &ArgMatches<'_>) -> Result<(), Box<dyn Error>> {
+    let spec_path = args.value_of("spec").expect("spec is required");
+    let output_dir = Path::new(args.value_of("output").expect("output has a default value"));
+
+    let chain_spec = ChainSpec::from_file(spec_path)?;
+    let (key_block, monetary_block) = chain_spec.genesis()?;
+
+    let key_block_data = key_block.into_proto().write_to_bytes()?;
+    let monetary_block_data = monetary_block.into_proto().write_to_bytes()?;
+    fs::write(output_dir.join("genesis0.bin"), &key_block_data)?;
+    fs::write(output_dir.join("genesis1.bin"), &monetary_block_data)?;
+
+    println!("Genesis key block: hash={}", Hash::digest(&key_block));
+    println!(
+        "Genesis monetary block: hash={}",
+        Hash::digest(&monetary_block)
+    );
+    Ok(())
+}
+
 fn run() -> Result<(), Box<dyn Error>> {
     let args = App::new("Stegos")
         .version(crate_version!())
@@ -107,14 +141,40 @@ fn run() -> Result<(), Box<dyn Error>> {
                 .help("Path to stegos.toml configuration file")
                 .takes_value(true),
         )
+        .subcommand(
+            SubCommand::with_name("genesis")
+                .about("Create genesis blocks from a chain specification")
+                .arg(
+                    Arg::with_name("spec")
+                        .value_name("SPEC")
+                        .help("Path to a chain specification in TOML format")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Directory to save genesis0.bin and genesis1.bin")
+                        .default_value("."),
+                ),
+        )
         .get_matches();
 
+    if let Some(args) = args.subcommand_matches("genesis") {
+        return generate_genesis(args);
+    }
+
     // Parse configuration
     let cfg = load_configuration(&args)?;
 
     // Initialize logger
     initialize_logger(&cfg)?;
 
+    // Load the chain specification
+    let chain_spec = load_chain_spec(&cfg)?;
+
     // Initialize keychain
     let keychain = KeyChain::new(&cfg.keychain)?;
 
@@ -123,7 +183,13 @@ fn run() -> Result<(), Box<dyn Error>> {
     let (network, network_service, broker) = Network::new(&cfg.network, &keychain)?;
 
     // Initialize node
-    let (node_service, node) = Node::new(keychain.clone(), broker.clone())?;
+    let (node_service, node) = Node::new(
+        &cfg.storage,
+        &cfg.txpool,
+        chain_spec.as_ref(),
+        keychain.clone(),
+        broker.clone(),
+    )?;
     rt.spawn(node_service);
 
     // Don't initialize REPL if stdin is not a TTY device
@@ -1,3 +1,5 @@
+//! Persistent Key-Value Storage.
+
 //
 // Copyright (c) 2018 Stegos
 //
@@ -21,10 +23,611 @@
 
 #![deny(warnings)]
 
+use failure::Error;
+use log::*;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Size of record header: checksum (u32) + payload length (u32).
+const RECORD_HEADER_LEN: u64 = 8;
+/// Operation code for put().
+const OP_PUT: u8 = 1;
+/// Operation code for delete().
+const OP_DELETE: u8 = 2;
+/// Size of operation header: operation code (u8) + key length (u32) + value length (u32).
+const OP_HEADER_LEN: u64 = 9;
+/// The log is compacted on open() if it is larger than this size...
+const COMPACT_MIN_SIZE: u64 = 1024 * 1024;
+/// ...and live values take less than 1/COMPACT_RATIO of it.
+const COMPACT_RATIO: u64 = 2;
+/// The maximal payload of a record written by compact().
+const COMPACT_RECORD_SIZE: usize = 16 * 1024 * 1024;
+
+/// A single modification of the storage.
+enum Op {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+/// A set of modifications applied atomically by Storage::write().
+pub struct WriteBatch {
+    ops: Vec<Op>,
+}
+
+impl WriteBatch {
+    pub fn new() -> WriteBatch {
+        WriteBatch { ops: Vec::new() }
+    }
+
+    /// Write the value by the key.
+    pub fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.ops.push(Op::Put(key.to_vec(), value.to_vec()));
+    }
+
+    /// Remove the key.
+    pub fn delete(&mut self, key: &[u8]) {
+        self.ops.push(Op::Delete(key.to_vec()));
+    }
+
+    /// Returns the number of operations in this batch.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns true if this batch has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// Persistent Key-Value Storage.
+///
+/// The storage is an append-only log of checksummed records.
+/// Only keys and positions of values are kept in memory, values are read from the disk on demand.
+/// Every record contains all operations of a WriteBatch and works as a write-ahead journal:
+/// a truncated or corrupted record at the end of the log is a result of interrupted write
+/// and is discarded on open() as a whole, so batches are never applied partially.
+/// Overwritten and removed values are reclaimed by compact(), which is called by open()
+/// when most of the log is dead.
+pub struct Storage {
+    /// Path to the log file.
+    path: PathBuf,
+    /// The log file, opened in append mode.
+    file: File,
+    /// (offset, length) of values by key.
+    index: BTreeMap<Vec<u8>, (u64, u32)>,
+    /// The length of valid part of the log.
+    len: u64,
+}
+
+impl Storage {
+    /// Open the storage, creating the log file if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Storage, Error> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let file_len = file.metadata()?.len();
+
+        let mut index = BTreeMap::<Vec<u8>, (u64, u32)>::new();
+        let mut len: u64 = 0;
+        {
+            let mut reader = BufReader::new(&file);
+            while let Some(payload) = read_record(&mut reader, file_len - len)? {
+                let offset = len + RECORD_HEADER_LEN;
+                match decode_ops(&payload) {
+                    Some(ops) => {
+                        for (key, value) in ops {
+                            match value {
+                                Some((pos, size)) => index.insert(key, (offset + pos, size)),
+                                None => index.remove(&key),
+                            };
+                        }
+                    }
+                    None => break,
+                }
+                len = offset + payload.len() as u64;
+            }
+        }
+
+        if len < file_len {
+            warn!(
+                "Discarding incomplete record: path={}, offset={}, size={}",
+                path.display(),
+                len,
+                file_len - len
+            );
+            file.set_len(len)?;
+            file.sync_all()?;
+        }
+
+        debug!(
+            "Opened storage: path={}, keys={}, size={}",
+            path.display(),
+            index.len(),
+            len
+        );
+
+        let mut storage = Storage {
+            path,
+            file,
+            index,
+            len,
+        };
+        if storage.len > COMPACT_MIN_SIZE && storage.live_size() * COMPACT_RATIO < storage.len {
+            storage.compact()?;
+        }
+        Ok(storage)
+    }
+
+    /// Rewrite the log keeping only live values.
+    ///
+    /// The new log is written to a temporary file, flushed to the disk and then
+    /// atomically replaces the old one. The directory is flushed after the rename.
+    pub fn compact(&mut self) -> Result<(), Error> {
+        let mut tmp_path = self.path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)?;
+
+        let mut index = BTreeMap::<Vec<u8>, (u64, u32)>::new();
+        let mut len: u64 = 0;
+        let mut ops = Vec::<Op>::new();
+        let mut ops_size: usize = 0;
+        for key in self.index.keys() {
+            let value = self.get(key)?.expect("key exists");
+            ops_size += key.len() + value.len();
+            ops.push(Op::Put(key.clone(), value));
+            if ops_size >= COMPACT_RECORD_SIZE {
+                append_compacted(&mut tmp, &ops, &mut len, &mut index)?;
+                ops.clear();
+                ops_size = 0;
+            }
+        }
+        if !ops.is_empty() {
+            append_compacted(&mut tmp, &ops, &mut len, &mut index)?;
+        }
+        tmp.sync_all()?;
+        drop(tmp);
+        fs::rename(&tmp_path, &self.path)?;
+        sync_parent_dir(&self.path)?;
+
+        info!(
+            "Compacted storage: path={}, keys={}, old_size={}, new_size={}",
+            self.path.display(),
+            index.len(),
+            self.len,
+            len
+        );
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        self.index = index;
+        self.len = len;
+        Ok(())
+    }
+
+    /// Returns the size of live keys and values in the log.
+    fn live_size(&self) -> u64 {
+        self.index
+            .iter()
+            .map(|(key, (_offset, size))| OP_HEADER_LEN + key.len() as u64 + *size as u64)
+            .sum()
+    }
+
+    /// Returns the path to the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns true if the storage has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns true if the storage contains the key.
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns all keys starting with prefix, in ascending order.
+    pub fn keys_with_prefix(&self, prefix: &[u8]) -> Vec<Vec<u8>> {
+        self.index
+            .range(prefix.to_vec()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Read the value by the key.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let (offset, size) = match self.index.get(key) {
+            Some(v) => *v,
+            None => return Ok(None),
+        };
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut value = vec![0u8; size as usize];
+        file.read_exact(&mut value)?;
+        Ok(Some(value))
+    }
+
+    /// Write the value by the key.
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        let mut batch = WriteBatch::new();
+        batch.put(key, value);
+        self.write(batch)
+    }
+
+    /// Remove the key.
+    pub fn delete(&mut self, key: &[u8]) -> Result<(), Error> {
+        let mut batch = WriteBatch::new();
+        batch.delete(key);
+        self.write(batch)
+    }
+
+    /// Atomically apply all operations of the batch.
+    ///
+    /// The batch is flushed to the disk before returning.
+    /// On error, neither the disk nor the in-memory state is modified.
+    pub fn write(&mut self, batch: WriteBatch) -> Result<(), Error> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let payload = encode_ops(&batch.ops);
+        let record = encode_record(&payload);
+
+        if let Err(e) = self
+            .file
+            .write_all(&record)
+            .and_then(|_| self.file.sync_data())
+        {
+            // Remove the partially written record to keep the log valid.
+            self.file.set_len(self.len).ok();
+            return Err(e.into());
+        }
+
+        let offset = self.len + RECORD_HEADER_LEN;
+        let ops = decode_ops(&payload).expect("encoded by encode_ops()");
+        for (key, value) in ops {
+            match value {
+                Some((pos, size)) => self.index.insert(key, (offset + pos, size)),
+                None => self.index.remove(&key),
+            };
+        }
+        self.len = offset + payload.len() as u64;
+        Ok(())
+    }
+}
+
+/// Flush the directory which contains the file to the disk.
+fn sync_parent_dir(path: &Path) -> Result<(), Error> {
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Write a record with compacted operations, see Storage::compact().
+fn append_compacted(
+    file: &mut File,
+    ops: &[Op],
+    len: &mut u64,
+    index: &mut BTreeMap<Vec<u8>, (u64, u32)>,
+) -> Result<(), Error> {
+    let payload = encode_ops(ops);
+    file.write_all(&encode_record(&payload))?;
+    let offset = *len + RECORD_HEADER_LEN;
+    for (key, value) in decode_ops(&payload).expect("encoded by encode_ops()") {
+        let (pos, size) = value.expect("only puts are compacted");
+        index.insert(key, (offset + pos, size));
+    }
+    *len = offset + payload.len() as u64;
+    Ok(())
+}
+
+/// Add the header to the record payload.
+fn encode_record(payload: &[u8]) -> Vec<u8> {
+    assert!(payload.len() <= u32::max_value() as usize);
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + payload.len());
+    put_u32(&mut record, crc32(payload));
+    put_u32(&mut record, payload.len() as u32);
+    record.extend_from_slice(payload);
+    record
+}
+
+/// Read a record from the log.
+///
+/// Returns None if the record is truncated or corrupted.
+fn read_record<R: Read>(reader: &mut R, remaining: u64) -> Result<Option<Vec<u8>>, Error> {
+    if remaining < RECORD_HEADER_LEN {
+        return Ok(None);
+    }
+    let mut header = [0u8; RECORD_HEADER_LEN as usize];
+    reader.read_exact(&mut header)?;
+    let checksum = get_u32(&header[0..4]);
+    let size = get_u32(&header[4..8]) as u64;
+    if size > remaining - RECORD_HEADER_LEN {
+        return Ok(None);
+    }
+    let mut payload = vec![0u8; size as usize];
+    match reader.read_exact(&mut payload) {
+        Ok(()) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    if crc32(&payload) != checksum {
+        return Ok(None);
+    }
+    Ok(Some(payload))
+}
+
+/// Serialize operations into the record payload.
+fn encode_ops(ops: &[Op]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for op in ops {
+        match op {
+            Op::Put(key, value) => {
+                buf.push(OP_PUT);
+                put_u32(&mut buf, key.len() as u32);
+                buf.extend_from_slice(key);
+                put_u32(&mut buf, value.len() as u32);
+                buf.extend_from_slice(value);
+            }
+            Op::Delete(key) => {
+                buf.push(OP_DELETE);
+                put_u32(&mut buf, key.len() as u32);
+                buf.extend_from_slice(key);
+            }
+        }
+    }
+    buf
+}
+
+/// Deserialize the record payload.
+///
+/// Returns keys with (position inside payload, length) of values, None for removed keys.
+fn decode_ops(payload: &[u8]) -> Option<Vec<(Vec<u8>, Option<(u64, u32)>)>> {
+    let mut ops = Vec::new();
+    let mut pos: usize = 0;
+    while pos < payload.len() {
+        let op = payload[pos];
+        pos += 1;
+        let key = read_chunk(payload, &mut pos)?;
+        let key = payload[key.0..key.0 + key.1].to_vec();
+        match op {
+            OP_PUT => {
+                let (value_pos, value_len) = read_chunk(payload, &mut pos)?;
+                ops.push((key, Some((value_pos as u64, value_len as u32))));
+            }
+            OP_DELETE => ops.push((key, None)),
+            _ => return None,
+        }
+    }
+    Some(ops)
+}
+
+/// Read a length-prefixed chunk, returning its (position, length).
+fn read_chunk(payload: &[u8], pos: &mut usize) -> Option<(usize, usize)> {
+    if payload.len() - *pos < 4 {
+        return None;
+    }
+    let len = get_u32(&payload[*pos..*pos + 4]) as usize;
+    *pos += 4;
+    if payload.len() - *pos < len {
+        return None;
+    }
+    let chunk = (*pos, len);
+    *pos += len;
+    Some(chunk)
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.push((v >> 24) as u8);
+    buf.push((v >> 16) as u8);
+    buf.push((v >> 8) as u8);
+    buf.push(v as u8);
+}
+
+fn get_u32(buf: &[u8]) -> u32 {
+    ((buf[0] as u32) << 24) | ((buf[1] as u32) << 16) | ((buf[2] as u32) << 8) | (buf[3] as u32)
+}
+
+/// CRC-32 (IEEE 802.3).
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
 #[cfg(test)]
-mod tests {
+pub mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Returns a path to a fresh temporary file.
+    pub fn temp_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("stegos-storage-{}-{}", std::process::id(), name));
+        fs::remove_file(&path).ok();
+        path
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn basic() {
+        let path = temp_path("basic");
+        {
+            let mut storage = Storage::open(&path).unwrap();
+            assert!(storage.is_empty());
+            storage.put(b"a1", b"one").unwrap();
+            storage.put(b"a2", b"two").unwrap();
+            storage.put(b"b1", b"").unwrap();
+            storage.put(b"a1", b"uno").unwrap();
+            storage.delete(b"a2").unwrap();
+            storage.delete(b"missing").unwrap();
+
+            assert_eq!(storage.len(), 2);
+            assert_eq!(storage.get(b"a1").unwrap(), Some(b"uno".to_vec()));
+            assert_eq!(storage.get(b"a2").unwrap(), None);
+            assert_eq!(storage.get(b"b1").unwrap(), Some(Vec::new()));
+            assert!(storage.contains_key(b"b1"));
+            assert_eq!(storage.keys_with_prefix(b"a"), vec![b"a1".to_vec()]);
+        }
+
+        // Reopen.
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.len(), 2);
+        assert_eq!(storage.get(b"a1").unwrap(), Some(b"uno".to_vec()));
+        assert_eq!(storage.get(b"a2").unwrap(), None);
+        assert_eq!(storage.get(b"b1").unwrap(), Some(Vec::new()));
+        drop(storage);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn batch() {
+        let path = temp_path("batch");
+        {
+            let mut storage = Storage::open(&path).unwrap();
+            storage.put(b"key1", b"value1").unwrap();
+            let mut batch = WriteBatch::new();
+            batch.delete(b"key1");
+            batch.put(b"key2", b"value2");
+            batch.put(b"key3", b"value3");
+            batch.put(b"key2", b"value22");
+            assert_eq!(batch.len(), 4);
+            storage.write(batch).unwrap();
+            storage.write(WriteBatch::new()).unwrap();
+            assert_eq!(storage.get(b"key1").unwrap(), None);
+            assert_eq!(storage.get(b"key2").unwrap(), Some(b"value22".to_vec()));
+            assert_eq!(storage.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        }
+
+        // Cut the batch - all its operations must be discarded.
+        let len = fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 1).unwrap();
+        drop(file);
+
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(storage.get(b"key2").unwrap(), None);
+        assert_eq!(storage.get(b"key3").unwrap(), None);
+        drop(storage);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated() {
+        let path = temp_path("truncated");
+        {
+            let mut storage = Storage::open(&path).unwrap();
+            storage.put(b"key1", b"value1").unwrap();
+            storage.put(b"key2", b"value2").unwrap();
+        }
+
+        // Cut the last record in the middle.
+        let len = fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 3).unwrap();
+        drop(file);
+
+        {
+            let mut storage = Storage::open(&path).unwrap();
+            assert_eq!(storage.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+            assert_eq!(storage.get(b"key2").unwrap(), None);
+            storage.put(b"key3", b"value3").unwrap();
+        }
+
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(storage.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+        drop(storage);
+        fs::remove_file(&path).unwrap();
+    }
+
     #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+    fn corrupted() {
+        let path = temp_path("corrupted");
+        {
+            let mut storage = Storage::open(&path).unwrap();
+            storage.put(b"key1", b"value1").unwrap();
+            storage.put(b"key2", b"value2").unwrap();
+        }
+
+        // Flip the last byte.
+        let mut data = fs::read(&path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        fs::write(&path, &data).unwrap();
+
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(storage.get(b"key2").unwrap(), None);
+        drop(storage);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn compaction() {
+        let path = temp_path("compaction");
+        let value = vec![0xAAu8; 64 * 1024];
+        {
+            let mut storage = Storage::open(&path).unwrap();
+            for _ in 0..32 {
+                storage.put(b"key1", &value).unwrap();
+            }
+            storage.put(b"key2", b"value2").unwrap();
+            storage.put(b"key3", b"value3").unwrap();
+            storage.delete(b"key3").unwrap();
+        }
+        let old_len = fs::metadata(&path).unwrap().len();
+        assert!(old_len > COMPACT_MIN_SIZE);
+
+        // Dead values are reclaimed on open.
+        {
+            let mut storage = Storage::open(&path).unwrap();
+            assert!(fs::metadata(&path).unwrap().len() < old_len / COMPACT_RATIO);
+            assert_eq!(storage.len(), 2);
+            assert_eq!(storage.get(b"key1").unwrap(), Some(value.clone()));
+            assert_eq!(storage.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+            assert_eq!(storage.get(b"key3").unwrap(), None);
+            storage.put(b"key4", b"value4").unwrap();
+        }
+
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage.get(b"key1").unwrap(), Some(value.clone()));
+        assert_eq!(storage.get(b"key4").unwrap(), Some(b"value4".to_vec()));
+        drop(storage);
+        fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,61 @@
+//! Transaction Pool Errors.
+
+//
+// Copyright (c) 2018 Stegos
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use failure::Fail;
+use stegos_crypto::hash::Hash;
+
+#[derive(Debug, Fail)]
+pub enum TxPoolError {
+    #[fail(display = "Transaction is already in the pool: hash={}.", _0)]
+    AlreadyExists(Hash),
+    #[fail(
+        display = "Transaction spends the same UTXO twice: hash={}, utxo={}.",
+        _0, _1
+    )]
+    DuplicateInput(Hash, Hash),
+    #[fail(
+        display = "Transaction output collides with an existing output: hash={}, output={}.",
+        _0, _1
+    )]
+    OutputHashCollision(Hash, Hash),
+    #[fail(
+        display = "Replacement fee is too low: hash={}, min={}, got={}.",
+        _0, _1, _2
+    )]
+    ReplacementFeeTooLow(Hash, i64, i64),
+    #[fail(
+        display = "Replacement fee per byte is not higher than of replaced transaction: hash={}, other={}.",
+        _0, _1
+    )]
+    ReplacementFeeRateTooLow(Hash, Hash),
+    #[fail(
+        display = "Too many transactions to replace: hash={}, count={}, max={}.",
+        _0, _1, _2
+    )]
+    TooManyReplacements(Hash, usize, usize),
+    #[fail(
+        display = "Pool is full and transaction fee is too low: hash={}, max_size={}.",
+        _0, _1
+    )]
+    PoolFull(Hash, usize),
+}
@@ -1,3 +1,5 @@
+//! Transaction Pool.
+
 //
 // Copyright (c) 2018 Stegos
 //
@@ -21,10 +23,8 @@
 
 #![deny(warnings)]
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+mod error;
+mod pool;
+
+pub use crate::error::*;
+pub use crate::pool::*;
@@ -0,0 +1,845 @@
+//! Transaction Pool.
+
+//
+// Copyright (c) 2018 Stegos
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::error::*;
+use failure::Error;
+use log::*;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use stegos_blockchain::*;
+use stegos_crypto::hash::Hash;
+
+/// The maximal number of transactions replaced by a new one.
+pub const MAX_REPLACED_TRANSACTIONS: usize = 100;
+
+/// The priority of a pending transaction.
+///
+/// Transactions with the higher fee per serialized byte are better,
+/// the earlier transaction is better if fees per byte are equal.
+#[derive(Debug, Clone, Copy)]
+struct Priority {
+    /// Fee.
+    fee: i64,
+    /// The size of serialized transaction in bytes.
+    size: usize,
+    /// The sequence number of insertion.
+    seq: u64,
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other: &Priority) -> Ordering {
+        // fee / size > other.fee / other.size <=> fee * other.size > other.fee * size
+        let lhs = (self.fee as i128) * (other.size as i128);
+        let rhs = (other.fee as i128) * (self.size as i128);
+        lhs.cmp(&rhs).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Priority) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Priority {
+    fn eq(&self, other: &Priority) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Priority {}
+
+/// Pending transaction.
+struct PoolEntry {
+    /// Transaction.
+    tx: Transaction,
+    /// Priority.
+    priority: Priority,
+    /// The time of arrival by the wall clock, which is kept across restarts.
+    arrived: SystemTime,
+}
+
+/// Transactions waiting to be included into a block.
+///
+/// Each transaction is validated against the chain on insertion.
+/// No two transactions in the pool spend the same UTXO, a conflicting transaction
+/// replaces the old ones if it pays more, see insert().
+/// Outputs of transactions don't collide with each other and with UTXOs of the chain.
+/// The pool is limited by the total size of transactions, the cheapest
+/// transactions by fee per byte are evicted when the pool is full.
+/// Transactions are also limited by age.
+pub struct TxPool {
+    /// Pending transactions by hash.
+    transactions: HashMap<Hash, PoolEntry>,
+    /// Hashes of pending transactions by the sequence number of insertion,
+    /// i.e. in the order of arrival.
+    queue: BTreeMap<u64, Hash>,
+    /// Hashes of pending transactions by priority.
+    priorities: BTreeMap<Priority, Hash>,
+    /// Hashes of pending transactions by the hash of spent UTXO.
+    spent: HashMap<Hash, Hash>,
+    /// Hashes of pending transactions by the hash of created output.
+    created: HashMap<Hash, Hash>,
+    /// The total size of pending transactions in bytes.
+    size: usize,
+    /// The sequence number for the next transaction.
+    seq: u64,
+    /// The maximal total size of transactions in bytes.
+    max_size: usize,
+    /// The maximal time to keep a transaction.
+    max_age: Duration,
+}
+
+impl TxPool {
+    /// Create a new empty pool.
+    ///
+    /// `max_size` must be greater than zero, see ConfigTxPool.
+    pub fn new(max_size: usize, max_age: Duration) -> TxPool {
+        assert!(max_size > 0);
+        TxPool {
+            transactions: HashMap::new(),
+            queue: BTreeMap::new(),
+            priorities: BTreeMap::new(),
+            spent: HashMap::new(),
+            created: HashMap::new(),
+            size: 0,
+            seq: 0,
+            max_size,
+            max_age,
+        }
+    }
+
+    /// Returns the number of transactions in the pool.
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Returns true if the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Returns the total size of transactions in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns true if the transaction is in the pool.
+    pub fn contains(&self, tx_hash: &Hash) -> bool {
+        self.transactions.contains_key(tx_hash)
+    }
+
+    /// Find a transaction by its hash.
+    pub fn get(&self, tx_hash: &Hash) -> Option<&Transaction> {
+        self.transactions.get(tx_hash).map(|entry| &entry.tx)
+    }
+
+    /// Returns the hash of the transaction which spends the UTXO.
+    pub fn spent_by(&self, output_hash: &Hash) -> Option<&Hash> {
+        self.spent.get(output_hash)
+    }
+
+    /// Returns an iterator over transactions, the best first.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a Transaction> + 'a {
+        self.priorities
+            .values()
+            .rev()
+            .map(move |tx_hash| &self.transactions[tx_hash].tx)
+    }
+
+    /// Returns an iterator over transactions with the time of arrival, the earliest first.
+    pub fn iter_by_arrival<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (&'a Transaction, SystemTime)> + 'a {
+        self.queue.values().map(move |tx_hash| {
+            let entry = &self.transactions[tx_hash];
+            (&entry.tx, entry.arrived)
+        })
+    }
+
+    /// Validate a transaction against the chain and add it to the pool.
+    ///
+    /// A transaction which spends the same UTXOs as transactions in the pool
+    /// replaces them if all of the following is true:
+    ///
+    /// * it pays at least the sum of their fees plus `min_fee`, so the replacement
+    ///   pays for its own relay;
+    /// * its fee per byte is strictly higher than the fee per byte of each of them;
+    /// * it replaces no more than MAX_REPLACED_TRANSACTIONS transactions.
+    ///
+    /// The cheapest transactions are evicted if the pool is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - transaction.
+    /// * `size` - the size of serialized transaction in bytes.
+    /// * `min_fee` - the minimal acceptable fee for the transaction.
+    /// * `now` - the time of arrival.
+    /// * `chain` - the current state of the main chain.
+    ///
+    /// Returns the hash of the transaction.
+    ///
+    pub fn insert(
+        &mut self,
+        tx: Transaction,
+        size: usize,
+        min_fee: i64,
+        now: SystemTime,
+        chain: &Blockchain,
+    ) -> Result<Hash, Error> {
+        assert!(size > 0);
+        let tx_hash = Hash::digest(&tx.body);
+        if self.transactions.contains_key(&tx_hash) {
+            return Err(TxPoolError::AlreadyExists(tx_hash).into());
+        }
+
+        // Check that UTXOs are not spent twice and find conflicting transactions.
+        let mut replaced = Vec::<Hash>::new();
+        for (i, input_hash) in tx.body.txins.iter().enumerate() {
+            if tx.body.txins[..i].contains(input_hash) {
+                return Err(TxPoolError::DuplicateInput(tx_hash, input_hash.clone()).into());
+            }
+            if let Some(other_hash) = self.spent.get(input_hash) {
+                if !replaced.contains(other_hash) {
+                    replaced.push(other_hash.clone());
+                }
+            }
+        }
+
+        // Check that outputs don't collide with UTXOs and outputs of other transactions,
+        // except the replaced ones.
+        let mut output_hashes = Vec::<Hash>::with_capacity(tx.body.txouts.len());
+        for output in &tx.body.txouts {
+            let output_hash = Hash::digest(output);
+            let collision = output_hashes.contains(&output_hash)
+                || chain.contains_output(&output_hash)
+                || match self.created.get(&output_hash) {
+                    Some(other_hash) => !replaced.contains(other_hash),
+                    None => false,
+                };
+            if collision {
+                return Err(TxPoolError::OutputHashCollision(tx_hash, output_hash).into());
+            }
+            output_hashes.push(output_hash);
+        }
+
+        // Resolve inputs.
+        let inputs = chain.outputs_by_hashes(&tx.body.txins)?;
+
+        // Validate monetary balance and signature.
+        tx.validate(&inputs)?;
+
+        let priority = Priority {
+            fee: tx.body.fee,
+            size,
+            seq: self.seq,
+        };
+
+        // Check replace-by-fee rules.
+        if replaced.len() > MAX_REPLACED_TRANSACTIONS {
+            return Err(TxPoolError::TooManyReplacements(
+                tx_hash,
+                replaced.len(),
+                MAX_REPLACED_TRANSACTIONS,
+            )
+            .into());
+        }
+        let mut replaced_fee: i64 = 0;
+        let mut replaced_size: usize = 0;
+        for other_hash in &replaced {
+            let other = &self.transactions[other_hash].priority;
+            if *other > priority {
+                return Err(
+                    TxPoolError::ReplacementFeeRateTooLow(tx_hash, other_hash.clone()).into(),
+                );
+            }
+            replaced_fee += other.fee;
+            replaced_size += other.size;
+        }
+        if !replaced.is_empty() && tx.body.fee < replaced_fee + min_fee {
+            return Err(TxPoolError::ReplacementFeeTooLow(
+                tx_hash,
+                replaced_fee + min_fee,
+                tx.body.fee,
+            )
+            .into());
+        }
+
+        // Find the cheapest transactions to free space.
+        let mut free = self.max_size - self.size + replaced_size;
+        let mut evicted = Vec::<Hash>::new();
+        for (other, other_hash) in &self.priorities {
+            if free >= size || *other > priority {
+                break;
+            }
+            if replaced.contains(other_hash) {
+                continue;
+            }
+            free += other.size;
+            evicted.push(other_hash.clone());
+        }
+        if free < size {
+            return Err(TxPoolError::PoolFull(tx_hash, self.max_size).into());
+        }
+        for other_hash in replaced {
+            info!(
+                "Replaced transaction: hash={}, by={}",
+                &other_hash, &tx_hash
+            );
+            self.remove(&other_hash);
+        }
+        for other_hash in evicted {
+            warn!("Evicted transaction: hash={}", &other_hash);
+            self.remove(&other_hash);
+        }
+
+        for input_hash in &tx.body.txins {
+            self.spent.insert(input_hash.clone(), tx_hash);
+        }
+        for output_hash in output_hashes {
+            self.created.insert(output_hash, tx_hash);
+        }
+        self.seq += 1;
+        self.size += size;
+        self.queue.insert(priority.seq, tx_hash);
+        self.priorities.insert(priority, tx_hash);
+        let entry = PoolEntry {
+            tx,
+            priority,
+            arrived: now,
+        };
+        self.transactions.insert(tx_hash, entry);
+        Ok(tx_hash)
+    }
+
+    /// Remove a transaction from the pool.
+    pub fn remove(&mut self, tx_hash: &Hash) -> Option<Transaction> {
+        let entry = self.transactions.remove(tx_hash)?;
+        for input_hash in &entry.tx.body.txins {
+            let other_hash = self.spent.remove(input_hash);
+            assert_eq!(other_hash, Some(*tx_hash));
+        }
+        for output in &entry.tx.body.txouts {
+            let other_hash = self.created.remove(&Hash::digest(output));
+            assert_eq!(other_hash, Some(*tx_hash));
+        }
+        let other_hash = self.priorities.remove(&entry.priority);
+        assert_eq!(other_hash, Some(*tx_hash));
+        self.size -= entry.priority.size;
+        let other_hash = self.queue.remove(&entry.priority.seq);
+        assert_eq!(other_hash, Some(*tx_hash));
+        Some(entry.tx)
+    }
+
+    /// Return transactions for a new block, the best first.
+    ///
+    /// Transactions which don't fit into the limits are left for the next block.
+    /// Selected transactions stay in the pool until the block is registered
+    /// and they are removed by remove_spent().
+    ///
+    /// # Arguments
+    ///
+    /// * `max_inputs` - the maximal total number of inputs.
+    /// * `max_outputs` - the maximal total number of outputs.
+    /// * `max_size` - the maximal total size of serialized transactions.
+    ///
+    pub fn select(
+        &self,
+        max_inputs: usize,
+        max_outputs: usize,
+        max_size: usize,
+    ) -> Vec<Transaction> {
+        let mut inputs_count: usize = 0;
+        let mut outputs_count: usize = 0;
+        let mut size: usize = 0;
+        let mut selected = Vec::<Transaction>::new();
+        for (priority, tx_hash) in self.priorities.iter().rev() {
+            let tx = &self.transactions[tx_hash].tx;
+            if inputs_count + tx.body.txins.len() > max_inputs
+                || outputs_count + tx.body.txouts.len() > max_outputs
+                || size + priority.size > max_size
+            {
+                continue;
+            }
+            inputs_count += tx.body.txins.len();
+            outputs_count += tx.body.txouts.len();
+            size += priority.size;
+            selected.push(tx.clone());
+        }
+
+        selected
+    }
+
+    /// Remove transactions which are kept for longer than max_age.
+    ///
+    /// Returns hashes of removed transactions.
+    ///
+    pub fn expire(&mut self, now: SystemTime) -> Vec<Hash> {
+        let mut expired = Vec::new();
+        while let Some(tx_hash) = self.queue.values().next().cloned() {
+            let arrived = self.transactions[&tx_hash].arrived;
+            // The wall clock can go backwards.
+            let age = now.duration_since(arrived).unwrap_or_default();
+            if age < self.max_age {
+                break;
+            }
+            self.remove(&tx_hash);
+            expired.push(tx_hash);
+        }
+        expired
+    }
+
+    /// Remove transactions which spend any of the UTXOs.
+    ///
+    /// Called when UTXOs are spent by a new block or pruned by TTL.
+    /// Returns hashes of removed transactions.
+    ///
+    pub fn remove_spent(&mut self, output_hashes: &[Hash]) -> Vec<Hash> {
+        let mut removed = Vec::new();
+        for output_hash in output_hashes {
+            if let Some(tx_hash) = self.spent.get(output_hash).cloned() {
+                self.remove(&tx_hash);
+                removed.push(tx_hash);
+            }
+        }
+        removed
+    }
+
+    /// Remove transactions which create any of the outputs.
+    ///
+    /// Called when outputs are created by a new block.
+    /// Returns hashes of removed transactions.
+    ///
+    pub fn remove_created(&mut self, output_hashes: &[Hash]) -> Vec<Hash> {
+        let mut removed = Vec::new();
+        for output_hash in output_hashes {
+            if let Some(tx_hash) = self.created.get(output_hash).cloned() {
+                self.remove(&tx_hash);
+                removed.push(tx_hash);
+            }
+        }
+        removed
+    }
+
+    /// Remove transactions which spend UTXOs missing in the chain.
+    ///
+    /// Called after the tip of the main chain is changed by a rollback or a snapshot.
+    /// UTXOs are identified by the hash of content, so the monetary balance and
+    /// the signature of the remaining transactions are still valid.
+    /// Returns hashes of removed transactions.
+    ///
+    pub fn revalidate(&mut self, chain: &Blockchain) -> Vec<Hash> {
+        let invalid: Vec<Hash> = self
+            .queue
+            .values()
+            .filter(|tx_hash| {
+                let tx = &self.transactions[*tx_hash].tx;
+                tx.body
+                    .txins
+                    .iter()
+                    .any(|input_hash| chain.output_by_hash(input_hash).is_none())
+            })
+            .cloned()
+            .collect();
+        for tx_hash in &invalid {
+            debug!("Transaction is no longer valid: hash={}", tx_hash);
+            self.remove(tx_hash);
+        }
+        invalid
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::Utc;
+    use stegos_crypto::curve1174::fields::Fr;
+    use stegos_keychain::KeyChain;
+
+    /// Create a transaction which moves the input to new outputs of the same owner.
+    fn transfer(keys: &KeyChain, inputs: &[Output], amounts: &[i64]) -> Transaction {
+        transfer_with_fee(keys, inputs, amounts, 0)
+    }
+
+    /// Same as transfer(), but also pays the fee.
+    fn transfer_with_fee(
+        keys: &KeyChain,
+        inputs: &[Output],
+        amounts: &[i64],
+        fee: i64,
+    ) -> Transaction {
+        let timestamp = Utc::now().timestamp() as u64;
+        let mut outputs = Vec::<Output>::new();
+        let mut outputs_gamma = Fr::zero();
+        for amount in amounts {
+            let (output, gamma) =
+                Output::new_monetary(timestamp, &keys.wallet_skey, &keys.wallet_pkey, *amount)
+                    .unwrap();
+            outputs.push(output);
+            outputs_gamma += gamma;
+        }
+        Transaction::new(&keys.wallet_skey, inputs, &outputs, outputs_gamma, fee).unwrap()
+    }
+
+    /// Create a monetary block from transactions.
+    fn block(chain: &Blockchain, txs: &[Transaction]) -> MonetaryBlock {
+        let timestamp = Utc::now().timestamp() as u64;
+        let last = chain.last_block();
+        let epoch = last.base_header().epoch;
+        let base = BaseBlockHeader::new(1, Hash::digest(last), epoch, timestamp);
+        let mut gamma = Fr::zero();
+        let mut inputs = Vec::<Hash>::new();
+        let mut outputs = Vec::<Output>::new();
+        for tx in txs {
+            gamma += tx.body.gamma;
+            inputs.extend(tx.body.txins.iter().cloned());
+            outputs.extend(tx.body.txouts.iter().cloned());
+        }
+        let utxo_root = chain.next_utxo_root(epoch, &inputs, &outputs);
+        MonetaryBlock::new(base, gamma, &inputs, &outputs, utxo_root, 0)
+    }
+
+    /// Create a chain where the owner has four UTXOs.
+    fn chain(keys: &KeyChain) -> (Blockchain, Vec<Output>) {
+        let (key_block, monetary_block) = genesis(&[keys.clone()]);
+        let mut chain = Blockchain::new();
+        chain.register_key_block(key_block).unwrap();
+        chain.register_monetary_block(monetary_block).unwrap();
+        let inputs = chain.outputs_by_hashes(&chain.unspent()).unwrap();
+        let tx = transfer(keys, &inputs, &[100, 200, 300, 999_400]);
+        let block = block(&chain, &[tx.clone()]);
+        chain.register_monetary_block(block).unwrap();
+        (chain, tx.body.txouts)
+    }
+
+    #[test]
+    fn insert() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let keys = KeyChain::new_mem();
+        let (chain, outputs) = chain(&keys);
+        let now = SystemTime::now();
+        let mut pool = TxPool::new(10_000, Duration::from_secs(60));
+
+        let tx1 = transfer(&keys, &outputs[0..1], &[100]);
+        let tx1_hash = pool.insert(tx1.clone(), 100, 0, now, &chain).unwrap();
+        assert_eq!(tx1_hash, Hash::digest(&tx1.body));
+        assert!(pool.contains(&tx1_hash));
+        assert_eq!(pool.spent_by(&Hash::digest(&outputs[0])), Some(&tx1_hash));
+
+        // The same transaction.
+        match pool.insert(tx1.clone(), 100, 0, now, &chain) {
+            Err(e) => match e.downcast::<TxPoolError>().unwrap() {
+                TxPoolError::AlreadyExists(hash) => assert_eq!(hash, tx1_hash),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+
+        // Conflicting transaction which doesn't pay more.
+        let tx2 = transfer(&keys, &outputs[0..2], &[300]);
+        match pool.insert(tx2, 100, 0, now, &chain) {
+            Err(e) => match e.downcast::<TxPoolError>().unwrap() {
+                TxPoolError::ReplacementFeeRateTooLow(_, other_hash) => {
+                    assert_eq!(other_hash, tx1_hash)
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+
+        // The same UTXO twice.
+        let tx3 = transfer(&keys, &[outputs[1].clone(), outputs[1].clone()], &[400]);
+        match pool.insert(tx3, 100, 0, now, &chain) {
+            Err(e) => match e.downcast::<TxPoolError>().unwrap() {
+                TxPoolError::DuplicateInput(_, input_hash) => {
+                    assert_eq!(input_hash, Hash::digest(&outputs[1]))
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+
+        // Missing UTXO.
+        let tx4 = transfer(&keys, &tx1.body.txouts, &[100]);
+        match pool.insert(tx4, 100, 0, now, &chain) {
+            Err(e) => match e.downcast::<BlockchainError>().unwrap() {
+                BlockchainError::MissingUTXO(_) => {}
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+
+        // Invalid monetary balance.
+        let mut tx5 = transfer(&keys, &outputs[1..2], &[200]);
+        tx5.body.fee = 1;
+        assert!(pool.insert(tx5, 100, 0, now, &chain).is_err());
+
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.remove(&tx1_hash).unwrap().body.txins, tx1.body.txins);
+        assert!(pool.is_empty());
+        assert_eq!(pool.spent_by(&Hash::digest(&outputs[0])), None);
+    }
+
+    #[test]
+    fn select() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let keys = KeyChain::new_mem();
+        let (chain, outputs) = chain(&keys);
+        let now = SystemTime::now();
+        let mut pool = TxPool::new(10_000, Duration::from_secs(60));
+
+        let tx1 = transfer(&keys, &outputs[0..1], &[50, 50]);
+        let tx2 = transfer_with_fee(&keys, &outputs[1..3], &[490], 10);
+        let tx3 = transfer_with_fee(&keys, &outputs[3..4], &[999_380], 20);
+        let tx1_hash = pool.insert(tx1, 100, 0, now, &chain).unwrap();
+        let tx2_hash = pool.insert(tx2, 200, 0, now, &chain).unwrap();
+        let tx3_hash = pool.insert(tx3, 100, 0, now, &chain).unwrap();
+        assert_eq!(pool.size(), 400);
+
+        // Transactions are ordered by fee per byte.
+        let order: Vec<Hash> = pool.iter().map(|tx| Hash::digest(&tx.body)).collect();
+        assert_eq!(order, vec![tx3_hash, tx2_hash, tx1_hash]);
+
+        // Nothing fits.
+        assert!(pool.select(0, 10, 1000).is_empty());
+        assert!(pool.select(10, 0, 1000).is_empty());
+        assert!(pool.select(10, 10, 99).is_empty());
+
+        // The best transactions are selected first, the rest is left for the next block.
+        let selected = pool.select(10, 1, 1000);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(Hash::digest(&selected[0].body), tx3_hash);
+        let selected = pool.select(10, 3, 300);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(Hash::digest(&selected[0].body), tx3_hash);
+        assert_eq!(Hash::digest(&selected[1].body), tx2_hash);
+        let selected = pool.select(10, 10, 1000);
+        let order: Vec<Hash> = selected.iter().map(|tx| Hash::digest(&tx.body)).collect();
+        assert_eq!(order, vec![tx3_hash, tx2_hash, tx1_hash]);
+
+        // Selected transactions stay in the pool.
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.size(), 400);
+        assert_eq!(pool.spent_by(&Hash::digest(&outputs[1])), Some(&tx2_hash));
+    }
+
+    #[test]
+    fn limits() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let keys = KeyChain::new_mem();
+        let (chain, outputs) = chain(&keys);
+        let now = SystemTime::now();
+        let mut pool = TxPool::new(300, Duration::from_secs(60));
+
+        let tx1 = transfer(&keys, &outputs[0..1], &[100]);
+        let tx2 = transfer_with_fee(&keys, &outputs[1..2], &[190], 10);
+        let tx3 = transfer_with_fee(&keys, &outputs[2..3], &[290], 10);
+        let tx1_hash = pool.insert(tx1, 100, 0, now, &chain).unwrap();
+        let tx2_hash = pool.insert(tx2, 100, 0, now, &chain).unwrap();
+        let tx3_hash = pool
+            .insert(tx3, 100, 0, now + Duration::from_secs(10), &chain)
+            .unwrap();
+        assert_eq!(pool.size(), 300);
+
+        // The pool is full and fee per byte is not higher than the cheapest one.
+        let tx4 = transfer(&keys, &outputs[3..4], &[999_400]);
+        match pool.insert(tx4, 100, 0, now, &chain) {
+            Err(e) => match e.downcast::<TxPoolError>().unwrap() {
+                TxPoolError::PoolFull(_, max_size) => assert_eq!(max_size, 300),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+
+        // The cheapest transactions are evicted, the later one if fees per byte are equal.
+        let tx5 = transfer_with_fee(&keys, &outputs[3..4], &[999_370], 30);
+        let tx5_hash = pool
+            .insert(tx5, 200, 0, now + Duration::from_secs(20), &chain)
+            .unwrap();
+        assert!(!pool.contains(&tx1_hash));
+        assert!(pool.contains(&tx2_hash));
+        assert!(!pool.contains(&tx3_hash));
+        assert_eq!(pool.spent_by(&Hash::digest(&outputs[0])), None);
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.size(), 300);
+        let arrived: Vec<(Hash, SystemTime)> = pool
+            .iter_by_arrival()
+            .map(|(tx, arrived)| (Hash::digest(&tx.body), arrived))
+            .collect();
+        let expected = vec![(tx2_hash, now), (tx5_hash, now + Duration::from_secs(20))];
+        assert_eq!(arrived, expected);
+
+        // Transaction is larger than the pool.
+        let tx6 = transfer_with_fee(&keys, &outputs[0..1], &[0], 100);
+        assert!(pool.insert(tx6, 301, 0, now, &chain).is_err());
+
+        // Transactions are expired by age.
+        assert!(pool.expire(now + Duration::from_secs(59)).is_empty());
+        let expired = pool.expire(now + Duration::from_secs(60));
+        assert_eq!(expired, vec![tx2_hash]);
+        assert_eq!(pool.size(), 200);
+        let expired = pool.expire(now + Duration::from_secs(1000));
+        assert_eq!(expired, vec![tx5_hash]);
+        assert!(pool.is_empty());
+        assert_eq!(pool.size(), 0);
+    }
+
+    #[test]
+    fn replace() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let keys = KeyChain::new_mem();
+        let (chain, outputs) = chain(&keys);
+        let now = SystemTime::now();
+        let mut pool = TxPool::new(10_000, Duration::from_secs(60));
+
+        let tx1 = transfer_with_fee(&keys, &outputs[0..1], &[90], 10);
+        let tx2 = transfer_with_fee(&keys, &outputs[1..2], &[190], 10);
+        let tx1_hash = pool.insert(tx1, 100, 10, now, &chain).unwrap();
+        let tx2_hash = pool.insert(tx2, 100, 10, now, &chain).unwrap();
+
+        // The replacement doesn't pay for its own relay.
+        let tx3 = transfer_with_fee(&keys, &outputs[0..2], &[275], 25);
+        match pool.insert(tx3, 200, 10, now, &chain) {
+            Err(e) => match e.downcast::<TxPoolError>().unwrap() {
+                TxPoolError::ReplacementFeeTooLow(_, min, got) => {
+                    assert_eq!(min, 30);
+                    assert_eq!(got, 25);
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+
+        // Fee per byte is not higher.
+        let tx4 = transfer_with_fee(&keys, &outputs[0..2], &[270], 30);
+        match pool.insert(tx4.clone(), 300, 10, now, &chain) {
+            Err(e) => match e.downcast::<TxPoolError>().unwrap() {
+                TxPoolError::ReplacementFeeRateTooLow(..) => {}
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+        assert!(pool.contains(&tx1_hash));
+        assert!(pool.contains(&tx2_hash));
+
+        // Both transactions are replaced.
+        let tx4_hash = pool.insert(tx4, 200, 10, now, &chain).unwrap();
+        assert!(!pool.contains(&tx1_hash));
+        assert!(!pool.contains(&tx2_hash));
+        assert_eq!(pool.spent_by(&Hash::digest(&outputs[0])), Some(&tx4_hash));
+        assert_eq!(pool.spent_by(&Hash::digest(&outputs[1])), Some(&tx4_hash));
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.size(), 200);
+    }
+
+    #[test]
+    fn output_collisions() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let keys = KeyChain::new_mem();
+        let (mut chain, outputs) = chain(&keys);
+        let now = SystemTime::now();
+        let mut pool = TxPool::new(10_000, Duration::from_secs(60));
+
+        let timestamp = Utc::now().timestamp() as u64;
+        let (output, gamma) =
+            Output::new_monetary(timestamp, &keys.wallet_skey, &keys.wallet_pkey, 100).unwrap();
+        let output_hash = Hash::digest(&output);
+        let skey = &keys.wallet_skey;
+        let expect_collision = |result: Result<Hash, Error>| match result {
+            Err(e) => match e.downcast::<TxPoolError>().unwrap() {
+                TxPoolError::OutputHashCollision(_, hash) => assert_eq!(hash, output_hash),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+
+        let tx1 = Transaction::new(skey, &outputs[0..1], &[output.clone()], gamma, 0).unwrap();
+        let tx1_hash = pool.insert(tx1.clone(), 100, 0, now, &chain).unwrap();
+
+        // The same output twice in a transaction.
+        let outputs2 = [output.clone(), output.clone()];
+        let tx2 = Transaction::new(skey, &outputs[2..3], &outputs2, gamma + gamma, 100).unwrap();
+        expect_collision(pool.insert(tx2, 100, 0, now, &chain));
+
+        // The output of another pending transaction.
+        let tx3 = Transaction::new(skey, &outputs[1..2], &[output.clone()], gamma, 100).unwrap();
+        expect_collision(pool.insert(tx3.clone(), 100, 0, now, &chain));
+
+        // The output of a replaced transaction.
+        let tx4 = Transaction::new(skey, &outputs[0..2], &[output.clone()], gamma, 200).unwrap();
+        let tx4_hash = pool.insert(tx4, 100, 100, now, &chain).unwrap();
+        assert!(!pool.contains(&tx1_hash));
+        pool.remove(&tx4_hash).unwrap();
+
+        // UTXO of the chain.
+        let tx3_hash = pool.insert(tx3.clone(), 100, 0, now, &chain).unwrap();
+        let block = block(&chain, &[tx1]);
+        let created: Vec<Hash> = block
+            .body
+            .outputs
+            .leafs()
+            .iter()
+            .map(|(o, _path)| Hash::digest(*o))
+            .collect();
+        chain.register_monetary_block(block).unwrap();
+        assert_eq!(pool.remove_created(&created), vec![tx3_hash]);
+        expect_collision(pool.insert(tx3, 100, 0, now, &chain));
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn blocks() {
+        simple_logger::init_with_level(log::Level::Debug).unwrap_or_default();
+        let keys = KeyChain::new_mem();
+        let (mut chain, outputs) = chain(&keys);
+        let now = SystemTime::now();
+        let mut pool = TxPool::new(10_000, Duration::from_secs(60));
+
+        let tx1 = transfer(&keys, &outputs[0..1], &[100]);
+        let tx2 = transfer(&keys, &outputs[1..2], &[200]);
+        let tx3 = transfer(&keys, &outputs[2..3], &[300]);
+        let tx1_hash = pool.insert(tx1.clone(), 100, 0, now, &chain).unwrap();
+        let tx2_hash = pool.insert(tx2.clone(), 100, 0, now, &chain).unwrap();
+        let tx3_hash = pool.insert(tx3.clone(), 100, 0, now, &chain).unwrap();
+
+        // A block includes the first transaction and another one conflicting with the second.
+        let conflict = transfer(&keys, &outputs[1..2], &[150, 50]);
+        let block1 = block(&chain, &[tx1.clone(), conflict]);
+        let removed = pool.remove_spent(&block1.body.inputs);
+        assert_eq!(removed, vec![tx1_hash, tx2_hash]);
+        chain.register_monetary_block(block1).unwrap();
+        assert_eq!(pool.len(), 1);
+        assert!(pool.revalidate(&chain).is_empty());
+
+        // The third transaction is invalidated by a rollback of the block which created the UTXO.
+        let tx4 = transfer(&keys, &tx1.body.txouts, &[100]);
+        let tx4_hash = pool.insert(tx4, 100, 0, now, &chain).unwrap();
+        assert_eq!(pool.len(), 2);
+        chain.unregister_last_block().unwrap();
+        assert_eq!(pool.revalidate(&chain), vec![tx4_hash]);
+        assert_eq!(pool.len(), 1);
+        assert!(pool.contains(&tx3_hash));
+    }
+}